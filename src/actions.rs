@@ -0,0 +1,168 @@
+use bevy::prelude::*;
+
+use crate::connections::LINE_WIDTH;
+use crate::pacing::next_frame_pacing;
+use crate::{Pattern, SimuConf};
+
+// Everything that can be done to the simulation. The keyboard and the mouse are
+// mapped to these events in `input.rs`, another input (gamepad, touch, a script...)
+// only has to send them too. The camera, the HUD and the presets aren't part of it.
+#[derive(Event, Clone, Copy, PartialEq, Debug)]
+pub enum SimAction {
+    // Added to the setting
+    ChangeConnectForce(f32),
+    ChangeAlphaFloor(f32),
+    ChangeSpeed(f32),
+    ChangeLineWidth(f32),
+    ChangeAttractorStrength(f32),
+    ChangeDamping(f32),
+    ChangeRestitution(f32),
+    ChangeBrushRadius(f32),
+    ChangeBrushCount(i32),
+    ToggleFreeze,
+    ToggleDensityAdaptive,
+    NextColorMode,
+    ToggleCollisions,
+    ToggleSparks,
+    ToggleGradient,
+    ToggleBloom,
+    NextBroadphase,
+    NextConnectionMode,
+    ToggleTriangles,
+    NextBoundaryMode,
+    // VSync, uncapped or capped frames
+    NextFramePacing,
+    ToggleGpu,
+    ToggleLinesOrder,
+    ToggleFlowField,
+    ToggleGravity,
+    ToggleSprings,
+    ToggleMerging,
+    ToggleThreeD,
+    // `SimuConf.brush_count` dots within `SimuConf.brush_radius` of this world
+    // position
+    SpawnDot(Vec2),
+    // A single one, with a size relative to `SimuConf.dot_size`, e.g. from the
+    // pressure of a stylus
+    SpawnSizedDot { position: Vec2, scale: f32 },
+    // Spawns a ring of `SimuConf.burst_count` dots around this world position, flying
+    // outward
+    Burst(Vec2),
+    // Spawns the pattern centered on this world position, turning around it when the
+    // last field is set
+    SpawnPattern(Pattern, Vec2, bool),
+    // Shows the dot at this world position in the inspector, nothing when there is
+    // no dot there
+    Select(Vec2),
+    // Selects the dots inside this world rectangle instead
+    SelectBox(Rect),
+    DeleteSelection,
+    PinSelection,
+    // Attaches the dot at this world position to the cursor
+    Grab(Vec2),
+    // Moves the grabbed dots to this world position
+    Drag(Vec2),
+    // Lets the grabbed dots go, with the velocity of the drag
+    Release,
+    // Pins the dot at this world position in place, or unpins it
+    TogglePin(Vec2),
+    // Splits the large dot at this world position into smaller ones
+    SplitDot(Vec2),
+    // Pulls the dots toward this world position for one frame, or pushes them away
+    Attract { target: Vec2, repel: bool },
+    // Pushes the dots closer than `SimuConf.repulsion_radius` away from this world
+    // position for one frame
+    Repulse(Vec2),
+    // Pulls the dots closer than `SimuConf.herd_radius` toward this world position
+    // for one frame
+    Herd(Vec2),
+    // Deletes the dots around this world position, within the radius of the brush
+    Erase(Vec2),
+    // Places a gravity well at this world position, or removes the one there
+    ToggleWell(Vec2),
+    // Same for a vortex, spinning counterclockwise unless `clockwise`
+    ToggleVortex { position: Vec2, clockwise: bool },
+    // Same for an emitter, with the emitter settings of `SimuConf`
+    ToggleEmitter(Vec2),
+    // Adds the dot steered with the move keys, or removes it
+    TogglePlayer,
+    Reverse,
+    Randomize,
+    Clear,
+    SaveScene,
+    LoadScene,
+}
+
+// Run condition, true when the action was sent since the last run
+pub(crate) fn on_sim_action(
+    action: SimAction,
+) -> impl FnMut(EventReader<SimAction>) -> bool + Clone {
+    // Counted rather than `any` so every event is read
+    move |mut actions| actions.read().filter(|sent| **sent == action).count() > 0
+}
+
+// Applies the actions changing `SimuConf`, the ones acting on the dots, the camera
+// or the scene file are read by their own systems
+pub(crate) fn apply_sim_actions(
+    mut actions: EventReader<SimAction>,
+    mut simu_conf: ResMut<SimuConf>,
+) {
+    for action in actions.read() {
+        match *action {
+            SimAction::ChangeConnectForce(delta) => simu_conf.connect_force += delta,
+            SimAction::ChangeAlphaFloor(delta) => {
+                simu_conf.min_alpha = (simu_conf.min_alpha + delta)
+                    .min(simu_conf.max_alpha)
+                    .max(0.);
+            }
+            SimAction::ChangeSpeed(delta) => {
+                simu_conf.speed = (simu_conf.speed + delta).max(0.);
+            }
+            SimAction::ChangeLineWidth(delta) => {
+                simu_conf.max_line_width = (simu_conf.max_line_width + delta).max(LINE_WIDTH);
+            }
+            SimAction::ChangeAttractorStrength(delta) => {
+                simu_conf.attractor_strength = (simu_conf.attractor_strength + delta).max(0.);
+            }
+            SimAction::ChangeDamping(delta) => simu_conf.damping += delta,
+            SimAction::ChangeRestitution(delta) => {
+                simu_conf.restitution = (simu_conf.restitution + delta).max(0.);
+            }
+            SimAction::ChangeBrushRadius(delta) => {
+                simu_conf.brush_radius = (simu_conf.brush_radius + delta).max(0.);
+            }
+            SimAction::ChangeBrushCount(delta) => {
+                simu_conf.brush_count = simu_conf.brush_count.saturating_add_signed(delta).max(1);
+            }
+            SimAction::ToggleFreeze => simu_conf.freeze_dots = !simu_conf.freeze_dots,
+            SimAction::ToggleDensityAdaptive => {
+                simu_conf.density_adaptive = !simu_conf.density_adaptive;
+            }
+            SimAction::NextColorMode => simu_conf.color_mode = simu_conf.color_mode.next(),
+            SimAction::ToggleCollisions => simu_conf.dot_collisions = !simu_conf.dot_collisions,
+            SimAction::ToggleSparks => {
+                simu_conf.collision_particles = !simu_conf.collision_particles;
+            }
+            SimAction::ToggleGradient => {
+                simu_conf.gradient_background = !simu_conf.gradient_background;
+            }
+            SimAction::ToggleBloom => simu_conf.bloom = !simu_conf.bloom,
+            SimAction::NextBroadphase => simu_conf.broadphase = simu_conf.broadphase.next(),
+            SimAction::ToggleTriangles => simu_conf.fill_triangles = !simu_conf.fill_triangles,
+            SimAction::NextConnectionMode => {
+                simu_conf.connection_mode = simu_conf.connection_mode.next();
+            }
+            SimAction::NextBoundaryMode => {
+                simu_conf.boundary_mode = simu_conf.boundary_mode.next();
+            }
+            SimAction::NextFramePacing => next_frame_pacing(&mut simu_conf),
+            SimAction::ToggleGpu => simu_conf.gpu_connections = !simu_conf.gpu_connections,
+            SimAction::ToggleFlowField => simu_conf.flow_field = !simu_conf.flow_field,
+            SimAction::ToggleGravity => simu_conf.gravity = !simu_conf.gravity,
+            SimAction::ToggleSprings => simu_conf.springs = !simu_conf.springs,
+            SimAction::ToggleMerging => simu_conf.merge_dots = !simu_conf.merge_dots,
+            SimAction::ToggleThreeD => simu_conf.three_d = !simu_conf.three_d,
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use bevy::prelude::*;
+use std::error::Error;
+use std::path::Path;
+
+use crate::physics::bounce;
+
+// Dots brought back onto the outline are put that far inside it
+const POLYGON_MARGIN: f32 = 0.5; // In px
+
+// Reads the outline of `BoundaryMode::Polygon`. A RON file is a list of world
+// positions, e.g. `[(-300, -200), (300, -200), (0, 300)]`. An SVG file gives the
+// points of its first `<polygon>` or `<polyline>`, centered on the origin with the
+// y axis flipped, so a logo drawn in an editor keeps its size in px.
+pub fn load_polygon(path: &Path) -> Result<Vec<Vec2>, Box<dyn Error>> {
+    let content = std::fs::read_to_string(path)?;
+    let points = match path.extension() {
+        Some(extension) if extension == "svg" => svg_points(&content)?,
+        _ => ron::from_str::<Vec<(f32, f32)>>(&content)?
+            .into_iter()
+            .map(|(x, y)| Vec2::new(x, y))
+            .collect(),
+    };
+    if points.len() < 3 {
+        return Err("the polygon needs at least 3 points".into());
+    }
+    Ok(points)
+}
+
+fn svg_points(content: &str) -> Result<Vec<Vec2>, Box<dyn Error>> {
+    let element = ["<polygon", "<polyline"]
+        .iter()
+        .filter_map(|tag| content.find(tag))
+        .min()
+        .ok_or("no <polygon> or <polyline> in the SVG file")?;
+    let element = &content[element..];
+    let element = &element[..element.find('>').unwrap_or(element.len())];
+    let start = element
+        .find("points=")
+        .ok_or("the SVG polygon has no points")?
+        + "points=".len();
+    let quote = element[start..].chars().next().ok_or("unclosed points")?;
+    let values = &element[start + 1..];
+    let values = &values[..values.find(quote).ok_or("unclosed points")?];
+
+    let numbers = values
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|number| !number.is_empty())
+        .map(str::parse::<f32>)
+        .collect::<Result<Vec<_>, _>>()?;
+    let points: Vec<Vec2> = numbers
+        .chunks_exact(2)
+        .map(|pair| Vec2::new(pair[0], -pair[1]))
+        .collect();
+    let Some(&first) = points.first() else {
+        return Ok(points);
+    };
+    let bounds = points
+        .iter()
+        .fold(Rect::from_corners(first, first), |bounds, &point| {
+            bounds.union_point(point)
+        });
+    Ok(points
+        .iter()
+        .map(|&point| point - bounds.center())
+        .collect())
+}
+
+// Even-odd rule, a ray cast to the right crosses the outline an odd number of times
+// from inside
+fn contains(polygon: &[Vec2], point: Vec2) -> bool {
+    let mut inside = false;
+    for (i, &a) in polygon.iter().enumerate() {
+        let b = polygon[(i + 1) % polygon.len()];
+        if (a.y > point.y) != (b.y > point.y) {
+            let x = a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x);
+            if point.x < x {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+fn closest_point_on_segment(a: Vec2, b: Vec2, point: Vec2) -> Vec2 {
+    let segment = b - a;
+    let t = (point - a).dot(segment) / segment.length_squared().max(f32::EPSILON);
+    a + segment * t.clamp(0., 1.)
+}
+
+// Moves a dot out of the polygon back onto its closest edge, and bounces it on that
+// edge
+pub(crate) fn clamp_and_reflect_polygon(
+    position: &mut Vec3,
+    velocity: &mut Vec2,
+    polygon: &[Vec2],
+    restitution: f32,
+) {
+    let point = position.truncate();
+    if contains(polygon, point) {
+        return;
+    }
+    let Some(closest) = polygon
+        .iter()
+        .enumerate()
+        .map(|(i, &a)| closest_point_on_segment(a, polygon[(i + 1) % polygon.len()], point))
+        .min_by(|a, b| {
+            a.distance_squared(point)
+                .total_cmp(&b.distance_squared(point))
+        })
+    else {
+        return;
+    };
+    let normal = (point - closest).normalize_or_zero();
+    // Slightly inside, so the dot isn't found outside again next frame
+    let inside = closest - normal * POLYGON_MARGIN;
+    position.x = inside.x;
+    position.y = inside.y;
+    bounce(velocity, normal, restitution);
+}
@@ -0,0 +1,122 @@
+use bevy::asset::load_internal_asset;
+use bevy::prelude::*;
+use bevy::render::render_resource::{AsBindGroup, ShaderRef};
+use bevy::render::view::RenderLayers;
+use bevy::sprite::{Material2d, Material2dPlugin, MaterialMesh2dBundle};
+
+use crate::camera::MainCamera;
+use crate::connections::{ConnectionRenderer, LINES_LAYER};
+use crate::theme::Theme;
+use crate::SimuConf;
+
+const GRADIENT_BACKGROUND_SHADER: Handle<Shader> =
+    Handle::weak_from_u128(0x2b7e_91c4_5f03_4d8a_b6e1_0c9f_d37a_5e12);
+const GRADIENT_PERIOD: f32 = 90.; // In s
+
+#[derive(Asset, TypePath, AsBindGroup, Clone)]
+pub(crate) struct GradientMaterial {
+    #[uniform(0)]
+    color_a: Color,
+    #[uniform(0)]
+    color_b: Color,
+    #[uniform(0)]
+    period: f32,
+}
+
+impl Material2d for GradientMaterial {
+    fn fragment_shader() -> ShaderRef {
+        GRADIENT_BACKGROUND_SHADER.into()
+    }
+}
+
+// Quad covering the whole view, behind everything else
+#[derive(Component)]
+pub(crate) struct GradientBackground;
+
+pub(crate) struct GradientBackgroundPlugin;
+
+impl Plugin for GradientBackgroundPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(
+            app,
+            GRADIENT_BACKGROUND_SHADER,
+            "gradient_background.wgsl",
+            Shader::from_wgsl
+        );
+        app.add_plugins(Material2dPlugin::<GradientMaterial>::default());
+    }
+}
+
+pub(crate) fn spawn_background(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<GradientMaterial>>,
+    theme: Res<Theme>,
+) {
+    let palette = theme.palette();
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: meshes.add(Rectangle::new(1., 1.)).into(),
+            material: materials.add(GradientMaterial {
+                color_a: palette.background,
+                color_b: palette.background_glow,
+                period: GRADIENT_PERIOD,
+            }),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        GradientBackground,
+        RenderLayers::default(),
+    ));
+}
+
+// Stretches the quad over what the main camera sees
+pub(crate) fn follow_main_camera(
+    camera: Query<(&Transform, &OrthographicProjection), With<MainCamera>>,
+    mut background: Query<&mut Transform, (With<GradientBackground>, Without<MainCamera>)>,
+) {
+    let (Ok((camera, projection)), Ok(mut transform)) =
+        (camera.get_single(), background.get_single_mut())
+    else {
+        return;
+    };
+    // Under the dots (at z = 1) and the connections (at z = 0.5)
+    transform.translation = camera.translation.truncate().extend(0.);
+    transform.scale = projection.area.size().extend(1.);
+}
+
+// The quad is drawn by the camera clearing the screen, so it stays behind the
+// connections wherever they are drawn
+pub(crate) fn update_background(
+    simu_conf: Res<SimuConf>,
+    theme: Res<Theme>,
+    renderer: Res<ConnectionRenderer>,
+    mut background: Query<
+        (
+            &mut Visibility,
+            &mut RenderLayers,
+            &Handle<GradientMaterial>,
+        ),
+        With<GradientBackground>,
+    >,
+    mut materials: ResMut<Assets<GradientMaterial>>,
+) {
+    let Ok((mut visibility, mut layers, material)) = background.get_single_mut() else {
+        return;
+    };
+    *visibility = if simu_conf.gradient_background {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+    *layers = if renderer.above_dots {
+        RenderLayers::default()
+    } else {
+        RenderLayers::layer(LINES_LAYER)
+    };
+    if let Some(material) = materials.get_mut(material) {
+        let palette = theme.palette();
+        material.color_a = palette.background;
+        material.color_b = palette.background_glow;
+    }
+}
@@ -0,0 +1,137 @@
+use bevy::prelude::*;
+use bevy::utils::Instant;
+use serde::{Deserialize, Serialize};
+
+use crate::connections::{max_connection_radius, PerfStats};
+use crate::dots::Dot;
+use crate::grid::SpatialGrid;
+use crate::quadtree::Quadtree;
+use crate::three_d::{depth_of, Depth};
+use crate::SimuConf;
+
+// Structure used to find the dots close to each other
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Reflect, Serialize, Deserialize)]
+pub enum Broadphase {
+    // Uniform grid, the fastest while the dots are spread evenly
+    #[default]
+    Grid,
+    // Adapts to uneven distributions, e.g. most dots piled in a corner
+    Quadtree,
+    // Every pair of dots, only there for comparison
+    BruteForce,
+}
+
+impl Broadphase {
+    pub(crate) fn next(self) -> Self {
+        match self {
+            Broadphase::Grid => Broadphase::Quadtree,
+            Broadphase::Quadtree => Broadphase::BruteForce,
+            Broadphase::BruteForce => Broadphase::Grid,
+        }
+    }
+}
+
+// Finds the pairs of points closer than a radius with the selected broadphase. The
+// resource holds the dots positions of the current frame, pairwise systems moving
+// the dots around keep their own instance.
+#[derive(Resource, Default)]
+pub(crate) struct NeighbourSearch {
+    broadphase: Broadphase,
+    radius: f32,
+    positions: Vec<Vec2>,
+    entities: Vec<Entity>,
+    depths: Vec<f32>,
+    grid: SpatialGrid,
+    quadtree: Quadtree,
+}
+
+impl NeighbourSearch {
+    pub(crate) fn rebuild(
+        &mut self,
+        broadphase: Broadphase,
+        radius: f32,
+        positions: impl IntoIterator<Item = Vec2>,
+    ) {
+        self.broadphase = broadphase;
+        // A null or negative connect force would put everything in a single cell
+        self.radius = radius.max(1.);
+        self.positions.clear();
+        self.positions.extend(positions);
+
+        match broadphase {
+            Broadphase::Grid => self.grid.rebuild(self.radius, &self.positions),
+            Broadphase::Quadtree => self.quadtree.rebuild(&self.positions),
+            Broadphase::BruteForce => {}
+        }
+    }
+
+    // Positions the search was built with, indexed like in `for_each_pair`
+    pub(crate) fn positions(&self) -> &[Vec2] {
+        &self.positions
+    }
+
+    // Dot of every position, only filled by `update_neighbour_search`
+    pub(crate) fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    // Depth of every position in the 3D mode, 0 otherwise. Only filled by
+    // `update_neighbour_search`.
+    pub(crate) fn depths(&self) -> &[f32] {
+        &self.depths
+    }
+
+    // Grid cells or quadtree leaves holding dots
+    pub(crate) fn cell_count(&self) -> usize {
+        match self.broadphase {
+            Broadphase::Grid => self.grid.cell_count(),
+            Broadphase::Quadtree => self.quadtree.cell_count(),
+            Broadphase::BruteForce => 0,
+        }
+    }
+
+    // Calls `f` once for every candidate pair, a superset of the pairs closer than
+    // the radius. Returns the number of pairs.
+    pub(crate) fn for_each_pair(&self, mut f: impl FnMut(usize, usize)) -> usize {
+        match self.broadphase {
+            Broadphase::Grid => self.grid.for_each_pair(f),
+            Broadphase::Quadtree => self.quadtree.for_each_pair(self.radius, &self.positions, f),
+            Broadphase::BruteForce => {
+                let count = self.positions.len();
+                for i in 0..count {
+                    for j in i + 1..count {
+                        f(i, j);
+                    }
+                }
+                count * count.saturating_sub(1) / 2
+            }
+        }
+    }
+}
+
+pub(crate) fn update_neighbour_search(
+    query: Query<(Entity, &Transform, Option<&Depth>), With<Dot>>,
+    simu_conf: Res<SimuConf>,
+    mut search: ResMut<NeighbourSearch>,
+    mut stats: ResMut<PerfStats>,
+) {
+    let start = Instant::now();
+    search.rebuild(
+        simu_conf.broadphase,
+        max_connection_radius(&simu_conf),
+        query
+            .iter()
+            .map(|(_, dot, _)| Vec2::new(dot.translation.x, dot.translation.y)),
+    );
+    search.entities.clear();
+    search
+        .entities
+        .extend(query.iter().map(|(entity, ..)| entity));
+    search.depths.clear();
+    search.depths.extend(
+        query
+            .iter()
+            .map(|(_, _, depth)| depth_of(depth, &simu_conf)),
+    );
+    stats.timings.neighbour_search = start.elapsed();
+}
@@ -0,0 +1,502 @@
+use bevy::core_pipeline::bloom::{BloomPrefilterSettings, BloomSettings};
+use bevy::core_pipeline::fxaa::Fxaa;
+use bevy::core_pipeline::tonemapping::Tonemapping;
+use bevy::input::mouse::MouseWheel;
+use bevy::prelude::*;
+use bevy::render::camera::ClearColorConfig;
+use bevy::render::view::ColorGrading;
+use bevy::render::view::RenderLayers;
+use bevy::window::PrimaryWindow;
+use serde::{Deserialize, Serialize};
+
+use crate::connections::{ConnectionRenderer, LINES_LAYER};
+use crate::dots::Velocity;
+use crate::inspector::Selected;
+use crate::keys::{Action, KeyBindings, SLOT_COUNT};
+use crate::player::Player;
+use crate::SimuConf;
+
+const CAMERA_PAN_SPEED: f32 = 600.; // In px/s, at zoom 1
+const CAMERA_ZOOM_STEP: f32 = 1.25;
+const CAMERA_SMOOTHING: f32 = 6.; // The higher, the faster bookmarks are reached
+const WHEEL_ZOOM_STEP: f32 = 1.1; // Per notch of the mouse wheel
+
+// A middle click moving less than that places a well instead of panning
+pub(crate) const PAN_DRAG_THRESHOLD: f32 = 5.; // In screen px
+
+// Curve bringing the HDR colors back to the range of the screen, see Bevy's
+// `Tonemapping`
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Reflect, Serialize, Deserialize)]
+pub enum ToneMapping {
+    // The colors are clamped, so they stay the ones of the theme
+    #[default]
+    None,
+    Reinhard,
+    ReinhardLuminance,
+    AcesFitted,
+    AgX,
+    SomewhatBoringDisplayTransform,
+    TonyMcMapface,
+    BlenderFilmic,
+}
+
+impl ToneMapping {
+    // Listed by the settings panel
+    #[cfg(feature = "egui")]
+    pub(crate) const ALL: [ToneMapping; 8] = [
+        ToneMapping::None,
+        ToneMapping::Reinhard,
+        ToneMapping::ReinhardLuminance,
+        ToneMapping::AcesFitted,
+        ToneMapping::AgX,
+        ToneMapping::SomewhatBoringDisplayTransform,
+        ToneMapping::TonyMcMapface,
+        ToneMapping::BlenderFilmic,
+    ];
+
+    fn tonemapping(self) -> Tonemapping {
+        match self {
+            ToneMapping::None => Tonemapping::None,
+            ToneMapping::Reinhard => Tonemapping::Reinhard,
+            ToneMapping::ReinhardLuminance => Tonemapping::ReinhardLuminance,
+            ToneMapping::AcesFitted => Tonemapping::AcesFitted,
+            ToneMapping::AgX => Tonemapping::AgX,
+            ToneMapping::SomewhatBoringDisplayTransform => {
+                Tonemapping::SomewhatBoringDisplayTransform
+            }
+            ToneMapping::TonyMcMapface => Tonemapping::TonyMcMapface,
+            ToneMapping::BlenderFilmic => Tonemapping::BlenderFilmic,
+        }
+    }
+}
+
+// Camera rendering the dots, it is the one used for the cursor position
+#[derive(Component)]
+pub(crate) struct MainCamera;
+
+// Camera rendering only the connection lines (on `LINES_LAYER`), it follows the
+// main camera
+#[derive(Component)]
+pub(crate) struct LinesCamera;
+
+// Initial framing of the camera
+#[derive(Resource, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraConfig {
+    pub position: Vec2,
+    pub zoom: f32,
+}
+
+impl Default for CameraConfig {
+    fn default() -> Self {
+        CameraConfig {
+            position: Vec2::ZERO,
+            zoom: 1.,
+        }
+    }
+}
+
+// Camera views saved with Alt + Shift + number, and the one being moved to
+#[derive(Resource, Default)]
+pub(crate) struct CameraBookmarks {
+    views: [Option<CameraConfig>; SLOT_COUNT],
+    target: Option<CameraConfig>,
+}
+
+// Dragging the view with the middle mouse button
+#[derive(Resource, Default)]
+pub(crate) struct MousePan {
+    last_cursor: Option<Vec2>,
+    // Moved since the button was pressed, in screen px
+    pub(crate) distance: f32,
+}
+
+// Set while the camera rides along with the selected dot
+#[derive(Resource, Default)]
+pub(crate) struct FollowCamera {
+    pub(crate) enabled: bool,
+    // Scale of the view when the dot is at rest, the one it had when following
+    // started
+    scale: f32,
+}
+
+pub(crate) fn spawn_cameras(
+    mut commands: Commands,
+    renderer: Res<ConnectionRenderer>,
+    camera_conf: Res<CameraConfig>,
+) {
+    let new_camera = || {
+        let mut camera = Camera2dBundle::default();
+        camera.transform.translation.x = camera_conf.position.x;
+        camera.transform.translation.y = camera_conf.position.y;
+        camera.projection.scale = 1. / camera_conf.zoom;
+        camera
+    };
+    let mut main_camera = new_camera();
+    let mut lines_camera = new_camera();
+
+    set_lines_order(
+        renderer.above_dots,
+        &mut main_camera.camera,
+        &mut lines_camera.camera,
+    );
+
+    commands.spawn((main_camera, MainCamera));
+    commands.spawn((lines_camera, LinesCamera, RenderLayers::layer(LINES_LAYER)));
+}
+
+// World position under the cursor, if it is inside the primary window. The window
+// may be missing, e.g. while the app is closing.
+pub(crate) fn cursor_world_position(
+    windows: &Query<&Window, With<PrimaryWindow>>,
+    camera_query: &Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+) -> Option<Vec2> {
+    let (camera, camera_transform) = camera_query.get_single().ok()?;
+    let cursor_position = windows.get_single().ok()?.cursor_position()?;
+    camera.viewport_to_world_2d(camera_transform, cursor_position)
+}
+
+// Draw order, from back to front, is decided by the cameras order: the first one
+// clears the screen and the next one draws over it without clearing. Connection
+// lines have their own camera so they can go below or above the dots (and their
+// particles), while the UI is drawn by the camera with the highest order, on top
+// of everything.
+fn set_lines_order(above_dots: bool, main_camera: &mut Camera, lines_camera: &mut Camera) {
+    let (first, last) = if above_dots {
+        (main_camera, lines_camera)
+    } else {
+        (lines_camera, main_camera)
+    };
+    first.order = 0;
+    first.clear_color = ClearColorConfig::Default;
+    last.order = 1;
+    last.clear_color = ClearColorConfig::None;
+}
+
+pub(crate) fn toggle_lines_order(
+    mut renderer: ResMut<ConnectionRenderer>,
+    mut main_camera: Query<&mut Camera, (With<MainCamera>, Without<LinesCamera>)>,
+    mut lines_camera: Query<&mut Camera, With<LinesCamera>>,
+) {
+    renderer.above_dots = !renderer.above_dots;
+    set_lines_order(
+        renderer.above_dots,
+        &mut main_camera.single_mut(),
+        &mut lines_camera.single_mut(),
+    );
+}
+
+// Both cameras draw into the same HDR texture while bloom or `SimuConf.hdr` is on.
+// Bloom is only applied by the last camera, over everything drawn before it, so the
+// first layer doesn't glow twice. Without tonemapping (the default, like the 2D
+// cameras), the colors stay the same and everything above `bloom_threshold` glows.
+#[allow(clippy::type_complexity)]
+pub(crate) fn update_bloom(
+    mut commands: Commands,
+    simu_conf: Res<SimuConf>,
+    mut cameras: Query<(Entity, &mut Camera), Or<(With<MainCamera>, With<LinesCamera>)>>,
+) {
+    let last_order = cameras.iter().map(|(_, camera)| camera.order).max();
+    for (entity, mut camera) in &mut cameras {
+        camera.hdr = simu_conf.hdr || simu_conf.bloom;
+        if simu_conf.bloom && Some(camera.order) == last_order {
+            commands.entity(entity).insert(BloomSettings {
+                intensity: simu_conf.bloom_intensity,
+                prefilter_settings: BloomPrefilterSettings {
+                    threshold: simu_conf.bloom_threshold,
+                    ..default()
+                },
+                ..default()
+            });
+        } else {
+            commands.entity(entity).remove::<BloomSettings>();
+        }
+    }
+}
+
+// Both cameras tone map the shared HDR texture, the last one over the first
+#[allow(clippy::type_complexity)]
+pub(crate) fn update_tonemapping(
+    mut commands: Commands,
+    simu_conf: Res<SimuConf>,
+    cameras: Query<Entity, Or<(With<MainCamera>, With<LinesCamera>)>>,
+) {
+    for entity in &cameras {
+        commands.entity(entity).insert((
+            simu_conf.tonemapping.tonemapping(),
+            ColorGrading {
+                exposure: simu_conf.exposure,
+                ..default()
+            },
+        ));
+    }
+}
+
+// MSAA smooths the edges of the dots and lines as they are drawn. FXAA smooths the
+// whole image once drawn, so like the bloom it is only applied by the last camera.
+#[allow(clippy::type_complexity)]
+pub(crate) fn update_anti_aliasing(
+    mut commands: Commands,
+    simu_conf: Res<SimuConf>,
+    mut msaa: ResMut<Msaa>,
+    cameras: Query<(Entity, &Camera), Or<(With<MainCamera>, With<LinesCamera>)>>,
+) {
+    let samples = match simu_conf.msaa_samples {
+        0 | 1 => Msaa::Off,
+        2 => Msaa::Sample2,
+        3 | 4 => Msaa::Sample4,
+        _ => Msaa::Sample8,
+    };
+    if *msaa != samples {
+        *msaa = samples;
+    }
+
+    let last_order = cameras.iter().map(|(_, camera)| camera.order).max();
+    for (entity, camera) in &cameras {
+        if simu_conf.fxaa && Some(camera.order) == last_order {
+            commands.entity(entity).insert(Fxaa::default());
+        } else {
+            commands.entity(entity).remove::<Fxaa>();
+        }
+    }
+}
+
+// Unit vector toward the held move keys, zero without them. Ctrl is kept for the
+// shortcuts like Ctrl + S.
+pub(crate) fn move_direction(
+    keyboard_input: &ButtonInput<KeyCode>,
+    bindings: &KeyBindings,
+) -> Vec2 {
+    if keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        return Vec2::ZERO;
+    }
+    let mut direction = Vec2::ZERO;
+    if bindings.pressed(keyboard_input, Action::MoveUp) {
+        direction.y += 1.;
+    }
+    if bindings.pressed(keyboard_input, Action::MoveDown) {
+        direction.y -= 1.;
+    }
+    if bindings.pressed(keyboard_input, Action::MoveLeft) {
+        direction.x -= 1.;
+    }
+    if bindings.pressed(keyboard_input, Action::MoveRight) {
+        direction.x += 1.;
+    }
+    direction.normalize_or_zero()
+}
+
+// The move keys steer the player instead while there is one
+pub(crate) fn control_camera(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut camera: Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+    player: Query<(), With<Player>>,
+    mut bookmarks: ResMut<CameraBookmarks>,
+    time: Res<Time>,
+) {
+    let (mut transform, mut projection) = camera.single_mut();
+
+    let direction = move_direction(&keyboard_input, &bindings);
+    if direction != Vec2::ZERO && player.is_empty() {
+        let offset = direction * CAMERA_PAN_SPEED * projection.scale * time.delta_seconds();
+        transform.translation += offset.extend(0.);
+        bookmarks.target = None;
+    }
+
+    if bindings.just_pressed(&keyboard_input, Action::ZoomIn) {
+        projection.scale /= CAMERA_ZOOM_STEP;
+        bookmarks.target = None;
+    }
+    if bindings.just_pressed(&keyboard_input, Action::ZoomOut) {
+        projection.scale *= CAMERA_ZOOM_STEP;
+        bookmarks.target = None;
+    }
+
+    // Plain and Ctrl + number are the presets, bookmarks use Alt
+    if !keyboard_input.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]) {
+        return;
+    }
+    let save = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    for (i, key) in bindings.slots().iter().enumerate() {
+        if !keyboard_input.just_pressed(*key) {
+            continue;
+        }
+        if save {
+            bookmarks.views[i] = Some(CameraConfig {
+                position: Vec2::new(transform.translation.x, transform.translation.y),
+                zoom: 1. / projection.scale,
+            });
+        } else if let Some(view) = bookmarks.views[i] {
+            bookmarks.target = Some(view);
+        }
+    }
+}
+
+// The world under the cursor follows it while the middle button is held, once it
+// moved past `PAN_DRAG_THRESHOLD`
+pub(crate) fn pan_camera(
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut camera: Query<(&mut Transform, &OrthographicProjection), With<MainCamera>>,
+    mut pan: ResMut<MousePan>,
+    mut bookmarks: ResMut<CameraBookmarks>,
+) {
+    let cursor = windows.get_single().ok().and_then(Window::cursor_position);
+    if mouse_input.just_pressed(MouseButton::Middle) {
+        pan.distance = 0.;
+        pan.last_cursor = cursor;
+        return;
+    }
+    if !mouse_input.pressed(MouseButton::Middle) {
+        pan.last_cursor = None;
+        return;
+    }
+    let (Some(last), Some(cursor)) = (pan.last_cursor, cursor) else {
+        return;
+    };
+    pan.last_cursor = Some(cursor);
+    let delta = cursor - last;
+    pan.distance += delta.length();
+    if pan.distance < PAN_DRAG_THRESHOLD || delta == Vec2::ZERO {
+        return;
+    }
+    // The y axis of the screen points down
+    let (mut transform, projection) = camera.single_mut();
+    transform.translation.x -= delta.x * projection.scale;
+    transform.translation.y += delta.y * projection.scale;
+    bookmarks.target = None;
+}
+
+// The wheel zooms around the cursor, the point under it stays in place. With a
+// modifier or the attract key, it changes a setting instead (see `map_mouse_wheel`).
+pub(crate) fn zoom_camera(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut camera: Query<
+        (
+            &Camera,
+            &GlobalTransform,
+            &mut Transform,
+            &mut OrthographicProjection,
+        ),
+        With<MainCamera>,
+    >,
+    mut wheel_events: EventReader<MouseWheel>,
+    mut bookmarks: ResMut<CameraBookmarks>,
+) {
+    let modified = keyboard_input.any_pressed([
+        KeyCode::ShiftLeft,
+        KeyCode::ShiftRight,
+        KeyCode::ControlLeft,
+        KeyCode::ControlRight,
+    ]) || bindings.pressed(&keyboard_input, Action::Attract);
+    let notches: f32 = wheel_events
+        .read()
+        .filter(|event| event.y != 0.)
+        .map(|event| event.y.signum())
+        .sum();
+    if modified || notches == 0. {
+        return;
+    }
+    let (camera, global_transform, mut transform, mut projection) = camera.single_mut();
+    let cursor = windows
+        .get_single()
+        .ok()
+        .and_then(Window::cursor_position)
+        .and_then(|cursor| camera.viewport_to_world_2d(global_transform, cursor));
+    let ratio = WHEEL_ZOOM_STEP.powf(-notches);
+    if let Some(cursor) = cursor {
+        let position = transform.translation.truncate();
+        let position = cursor + (position - cursor) * ratio;
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+    }
+    projection.scale *= ratio;
+    bookmarks.target = None;
+}
+
+// Eases the camera toward the recalled bookmark instead of snapping to it
+pub(crate) fn move_camera_to_bookmark(
+    mut camera: Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+    mut bookmarks: ResMut<CameraBookmarks>,
+    time: Res<Time>,
+) {
+    let Some(target) = bookmarks.target else {
+        return;
+    };
+    let (mut transform, mut projection) = camera.single_mut();
+
+    let t = 1. - (-CAMERA_SMOOTHING * time.delta_seconds()).exp();
+    let position = Vec2::new(transform.translation.x, transform.translation.y);
+    let position = position.lerp(target.position, t);
+    // Zoom is interpolated in log space so zooming in and out feel the same
+    let scale = projection.scale.ln().lerp(-target.zoom.ln(), t).exp();
+
+    if position.distance(target.position) < 0.5 && (scale * target.zoom - 1.).abs() < 1e-3 {
+        transform.translation.x = target.position.x;
+        transform.translation.y = target.position.y;
+        projection.scale = 1. / target.zoom;
+        bookmarks.target = None;
+    } else {
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+        projection.scale = scale;
+    }
+}
+
+// Starts following the selected dot, only one can be followed, or stops
+pub(crate) fn toggle_follow_camera(
+    selected: Query<(), With<Selected>>,
+    camera: Query<&OrthographicProjection, With<MainCamera>>,
+    mut follow: ResMut<FollowCamera>,
+) {
+    follow.enabled = !follow.enabled && selected.iter().count() == 1;
+    follow.scale = camera.single().scale;
+}
+
+// Eases the camera toward the followed dot like toward a bookmark. With
+// `SimuConf.follow_zoom`, the view widens as the dot speeds up. Stops once the dot
+// is deleted or no longer selected.
+#[allow(clippy::type_complexity)]
+pub(crate) fn follow_selected_dot(
+    selected: Query<(&Transform, &Velocity), (With<Selected>, Without<MainCamera>)>,
+    mut camera: Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+    mut follow: ResMut<FollowCamera>,
+    mut bookmarks: ResMut<CameraBookmarks>,
+    simu_conf: Res<SimuConf>,
+    time: Res<Time>,
+) {
+    let Ok((dot, velocity)) = selected.get_single() else {
+        follow.enabled = false;
+        return;
+    };
+    let (mut transform, mut projection) = camera.single_mut();
+    bookmarks.target = None;
+
+    let t = 1. - (-CAMERA_SMOOTHING * time.delta_seconds()).exp();
+    let position = transform
+        .translation
+        .truncate()
+        .lerp(dot.translation.truncate(), t);
+    transform.translation.x = position.x;
+    transform.translation.y = position.y;
+    let target_scale = follow.scale * (1. + velocity.length() * simu_conf.follow_zoom.max(0.));
+    projection.scale = projection.scale.ln().lerp(target_scale.ln(), t).exp();
+}
+
+// Only follows the `MainCamera`, the host app may have cameras of its own
+#[allow(clippy::type_complexity)]
+pub(crate) fn sync_lines_camera(
+    main_camera: Query<(&Transform, &OrthographicProjection), With<MainCamera>>,
+    mut lines_camera: Query<
+        (&mut Transform, &mut OrthographicProjection),
+        (With<LinesCamera>, Without<MainCamera>),
+    >,
+) {
+    let (transform, projection) = main_camera.single();
+    let (mut lines_transform, mut lines_projection) = lines_camera.single_mut();
+    *lines_transform = *transform;
+    lines_projection.scale = projection.scale;
+}
@@ -0,0 +1,76 @@
+use bevy::prelude::*;
+
+use crate::broadphase::NeighbourSearch;
+use crate::connections::Connections;
+
+// Sets of dot indices, merged along the connections. The root of a set is always
+// its smallest index, so a cluster keeps the same label while it only grows.
+#[derive(Default)]
+struct UnionFind {
+    parents: Vec<usize>,
+}
+
+impl UnionFind {
+    fn reset(&mut self, count: usize) {
+        self.parents.clear();
+        self.parents.extend(0..count);
+    }
+
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.parents[i] != i {
+            // Path halving, every other node is moved closer to the root
+            self.parents[i] = self.parents[self.parents[i]];
+            i = self.parents[i];
+        }
+        i
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        self.parents[a.max(b)] = a.min(b);
+    }
+}
+
+// Connected islands of dots of the last frame, indexed like the positions of
+// `NeighbourSearch`. Only kept up to date in the cluster color mode.
+#[derive(Resource, Default)]
+pub(crate) struct Clusters {
+    union_find: UnionFind,
+    labels: Vec<usize>,
+    sizes: Vec<u32>,
+}
+
+impl Clusters {
+    // Label of the cluster of a dot, the same for every dot of the cluster. Dots
+    // without any connection aren't part of a cluster.
+    pub(crate) fn label(&self, dot: usize) -> Option<usize> {
+        let label = *self.labels.get(dot)?;
+        (self.sizes[label] > 1).then_some(label)
+    }
+}
+
+pub(crate) fn update_clusters(
+    connections: Res<Connections>,
+    search: Res<NeighbourSearch>,
+    mut clusters: ResMut<Clusters>,
+) {
+    let Clusters {
+        union_find,
+        labels,
+        sizes,
+    } = &mut *clusters;
+    let count = search.positions().len();
+
+    union_find.reset(count);
+    for connection in connections.iter() {
+        union_find.union(connection.dots[0], connection.dots[1]);
+    }
+
+    labels.clear();
+    labels.extend((0..count).map(|dot| union_find.find(dot)));
+    sizes.clear();
+    sizes.resize(count, 0);
+    for &label in labels.iter() {
+        sizes[label] += 1;
+    }
+}
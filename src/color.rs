@@ -0,0 +1,185 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::broadphase::NeighbourSearch;
+use crate::clusters::Clusters;
+use crate::connections::map;
+use crate::dots::{Age, Degree, Dot, DotAssets, DotScale, Velocity};
+use crate::particles::Lifetime;
+use crate::player::Player;
+use crate::SimuConf;
+
+// Number of materials of every gradient
+pub(crate) const PALETTE_STEPS: usize = 16;
+pub(crate) const SPEED_COLOR_SLOW: Color = Color::rgb(0.2, 0.4, 1.);
+pub(crate) const SPEED_COLOR_FAST: Color = Color::rgb(1., 0.2, 0.2);
+pub(crate) const DEGREE_COLOR_LOW: Color = Color::rgb(0.35, 0.2, 0.4);
+pub(crate) const DEGREE_COLOR_HIGH: Color = Color::rgb(1., 0.85, 1.);
+// Dots with this many connections or more get the brightest color and biggest scale
+const HUB_DEGREE: f32 = 12.;
+const HUB_SCALE: f32 = 1.5;
+pub(crate) const CLUSTER_HUES: usize = 12;
+// Spreads consecutive labels far apart on the hue wheel
+const GOLDEN_RATIO_CONJUGATE: f32 = 0.618_034;
+
+// What the color of a dot is based on
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Reflect, Serialize, Deserialize)]
+pub enum ColorMode {
+    // Every dot has the same color
+    #[default]
+    Uniform,
+    // From `age_color_young` to `age_color_old` as the dot gets older
+    Age,
+    // From blue for the slowest dots to red for the fastest ones
+    Speed,
+    // The more connections a dot has, the brighter and bigger it is
+    Degree,
+    // Every connected island of dots, and its connections, gets its own hue
+    Clusters,
+}
+
+impl ColorMode {
+    pub(crate) fn next(self) -> Self {
+        match self {
+            ColorMode::Uniform => ColorMode::Age,
+            ColorMode::Age => ColorMode::Speed,
+            ColorMode::Speed => ColorMode::Degree,
+            ColorMode::Degree => ColorMode::Clusters,
+            ColorMode::Clusters => ColorMode::Uniform,
+        }
+    }
+}
+
+// Material of `palette` for `t` between 0 and 1
+fn palette_material(palette: &[Handle<ColorMaterial>], t: f32) -> &Handle<ColorMaterial> {
+    let t = t.clamp(0., 1.);
+    &palette[(t * (palette.len() - 1) as f32).round() as usize]
+}
+
+// Index of the hue of a cluster, in `CLUSTER_HUES`
+pub(crate) fn cluster_hue(label: usize) -> usize {
+    ((label as f32 * GOLDEN_RATIO_CONJUGATE).fract() * CLUSTER_HUES as f32) as usize
+}
+
+pub(crate) fn hue_color(hue: usize) -> Color {
+    Color::hsl(hue as f32 * 360. / CLUSTER_HUES as f32, 0.8, 0.65)
+}
+
+pub(crate) fn lerp_color(from: Color, to: Color, t: f32) -> Color {
+    let from = Vec4::from(from.as_rgba_f32());
+    let to = Vec4::from(to.as_rgba_f32());
+    Color::rgba_from_array(from.lerp(to, t))
+}
+
+// Keeps the age palette in sync with the configured gradient
+pub(crate) fn update_age_palette(
+    simu_conf: Res<SimuConf>,
+    dot_assets: Res<DotAssets>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for (i, handle) in dot_assets.age_palette.iter().enumerate() {
+        let t = i as f32 / (PALETTE_STEPS - 1) as f32;
+        let color = lerp_color(simu_conf.age_color_young, simu_conf.age_color_old, t);
+        if let Some(material) = materials.get_mut(handle) {
+            if material.color != color {
+                material.color = color;
+            }
+        }
+    }
+}
+
+// Gives every dot the material of the active color mode
+#[allow(clippy::type_complexity)]
+pub(crate) fn recolor_dots(
+    mut query: Query<
+        (&Age, &Velocity, &Degree, &mut Handle<ColorMaterial>),
+        (With<Dot>, Without<Player>),
+    >,
+    simu_conf: Res<SimuConf>,
+    dot_assets: Res<DotAssets>,
+) {
+    let max_speed = simu_conf.max_spawn_speed();
+
+    for (age, velocity, degree, mut material) in &mut query {
+        let wanted = match simu_conf.color_mode {
+            ColorMode::Uniform => &dot_assets.material,
+            ColorMode::Age => {
+                palette_material(&dot_assets.age_palette, age.0 / simu_conf.age_for_old_color)
+            }
+            ColorMode::Speed => palette_material(
+                &dot_assets.speed_palette,
+                map(velocity.length(), 0., max_speed, 0., 1.),
+            ),
+            ColorMode::Degree => palette_material(&dot_assets.degree_palette, hub_ratio(degree)),
+            // Done by `recolor_clusters`
+            ColorMode::Clusters => continue,
+        };
+        if *material != *wanted {
+            *material = wanted.clone();
+        }
+    }
+}
+
+// Lowers the alpha of the dots with a lifetime as it runs out, once the color mode
+// picked their material. Rounded to the palette steps so the dots share materials.
+pub(crate) fn fade_dots(
+    mut query: Query<(&Lifetime, &mut Handle<ColorMaterial>), With<Dot>>,
+    mut dot_assets: ResMut<DotAssets>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let steps = (PALETTE_STEPS - 1) as f32;
+    for (lifetime, mut material) in &mut query {
+        let alpha = ((1. - lifetime.fraction()) * steps).ceil() / steps;
+        let Some(faded) = dot_assets.faded(&mut materials, &material, alpha) else {
+            continue;
+        };
+        if *material != faded {
+            *material = faded;
+        }
+    }
+}
+
+fn hub_ratio(degree: &Degree) -> f32 {
+    (degree.0 as f32 / HUB_DEGREE).min(1.)
+}
+
+// Makes the hubs bigger in the degree mode, on top of the size the dot was spawned
+// with. The hub scale isn't used by the physics.
+pub(crate) fn scale_dots(
+    mut query: Query<(&Degree, &DotScale, &mut Transform), With<Dot>>,
+    simu_conf: Res<SimuConf>,
+) {
+    for (degree, dot_scale, mut transform) in &mut query {
+        let hub_scale = if simu_conf.color_mode == ColorMode::Degree {
+            map(hub_ratio(degree), 0., 1., 1., HUB_SCALE)
+        } else {
+            1.
+        };
+        let scale = Vec3::splat(hub_scale * **dot_scale);
+        if transform.scale != scale {
+            transform.scale = scale;
+        }
+    }
+}
+
+// Gives every dot the material of its cluster, the dots without connections keep
+// the default one
+pub(crate) fn recolor_clusters(
+    mut query: Query<&mut Handle<ColorMaterial>, (With<Dot>, Without<Player>)>,
+    search: Res<NeighbourSearch>,
+    clusters: Res<Clusters>,
+    dot_assets: Res<DotAssets>,
+) {
+    for (dot, &entity) in search.entities().iter().enumerate() {
+        let Ok(mut material) = query.get_mut(entity) else {
+            continue;
+        };
+        let wanted = match clusters.label(dot) {
+            Some(label) => &dot_assets.cluster_palette[cluster_hue(label)],
+            None => &dot_assets.material,
+        };
+        if *material != *wanted {
+            *material = wanted.clone();
+        }
+    }
+}
@@ -0,0 +1,339 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{BoundaryMode, Broadphase, ColorMode, ConnectionMode, ToneMapping};
+
+/**
+ * Default values.
+ */
+const CONNECT_FORCE: f32 = 300.;
+const SPEED: f32 = 1.;
+const DOT_SIZE: f32 = 6.;
+const MIN_VEL: f32 = -600.;
+const MAX_VEL: f32 = 600.;
+const MAX_CONNECTIONS_DRAWN: usize = 20_000;
+const TARGET_DEGREE: f32 = 6.;
+const NEAREST_NEIGHBOURS: u32 = 3;
+const MIN_ALPHA: f32 = 0.05;
+const MAX_ALPHA: f32 = 1.;
+const MAX_LINE_WIDTH: f32 = 3.; // In px
+const TRIANGLE_ALPHA: f32 = 0.15;
+const ATTRACTOR_STRENGTH: f32 = 80_000.;
+const REPULSION_STRENGTH: f32 = 4_000.; // In px/s², at the cursor
+const REPULSION_RADIUS: f32 = 150.; // In px
+const HERD_STRENGTH: f32 = 3_000.; // In px/s², at the cursor
+const HERD_RADIUS: f32 = 250.; // In px
+const WELL_STRENGTH: f32 = 20_000_000.; // In px³/s², 2000 px/s² at 100 px
+const VORTEX_STRENGTH: f32 = 150_000.; // In px²/s², 1250 px/s² at 100 px
+const FLOW_STRENGTH: f32 = 1.5;
+const FLOW_SCALE: f32 = 300.; // In px
+const FLOW_SPEED: f32 = 0.1;
+const GRAVITY_STRENGTH: f32 = 600.; // In px/s²
+const FLOOR_RESTITUTION: f32 = 0.8;
+const RESTITUTION: f32 = 1.;
+const SPRING_STIFFNESS: f32 = 4.; // In 1/s², the acceleration per px of stretch
+const SPRING_REST_LENGTH: f32 = 120.; // In px
+const BURST_COUNT: u32 = 24;
+const BURST_SPEED: f32 = 400.; // In px/s
+const PATTERN_SPACING: f32 = 30.; // In px
+const PATTERN_SPIN: f32 = 1.; // In rad/s
+const GRID_COLUMNS: u32 = 12;
+const GRID_ROWS: u32 = 8;
+const RING_DOTS: u32 = 48;
+const SPIRAL_DOTS: u32 = 150;
+const EMITTER_RATE: f32 = 5.; // In dots/s
+const EMITTER_ANGLE: f32 = 90.; // In degrees, counterclockwise from the right
+const EMITTER_SPEED: f32 = 300.; // In px/s
+const EMITTER_SPREAD: f32 = 20.; // In degrees
+const HOVER_DIM: f32 = 0.2;
+const PLAYER_SPEED: f32 = 500.; // In px/s
+const PLAYER_ACCELERATION: f32 = 2_000.; // In px/s²
+const ARENA_RADIUS: f32 = 400.; // In px
+const FOLLOW_ZOOM: f32 = 0.001; // In s/px, the view is 1.6 times wider at 600 px/s
+
+const AGING_RATE: f32 = 1.;
+const AGE_FOR_OLD_COLOR: f32 = 60.; // In s
+const AGE_COLOR_YOUNG: Color = Color::rgb(1., 0.8, 1.);
+const AGE_COLOR_OLD: Color = Color::rgb(0.45, 0.4, 0.5);
+
+const COLLISION_ITERATIONS: u32 = 1;
+
+const BLOOM_INTENSITY: f32 = 0.3;
+const BLOOM_THRESHOLD: f32 = 0.;
+const MSAA_SAMPLES: u32 = 4;
+
+const DEPTH: f32 = 600.; // In px
+const ORBIT_SPEED: f32 = 0.1; // In rad/s
+const DEPTH_FADE: f32 = 0.8;
+
+// Variables of the simulation
+#[derive(Resource, Reflect, Clone, Serialize, Deserialize)]
+#[reflect(Resource)]
+#[serde(default)]
+pub struct SimuConf {
+    pub dot_size: f32,
+    // Spread of the sizes around `dot_size`, relative to it: with 0.5, the dots are
+    // spawned from half to one and a half `dot_size`
+    pub dot_size_variance: f32,
+    // In s, the dots spawned with a lifetime fade out and are deleted after that
+    // long. 0 for dots living forever.
+    pub dot_lifetime: f32,
+    // Past that many dots, the oldest ones are deleted to make room for the new ones.
+    // 0 for no limit.
+    pub max_dots: u32,
+    // Dots placed at every tick of a click, scattered within `brush_radius` of the
+    // cursor
+    pub brush_count: u32,
+    pub brush_radius: f32,
+    // Ring of dots spawned by a right click, flying outward at `burst_speed`
+    pub burst_count: u32,
+    pub burst_speed: f32,
+    // Distance between the neighbours of the patterns spawned with their keys, and
+    // the speed they turn at with Shift
+    pub pattern_spacing: f32,
+    pub pattern_spin: f32,
+    pub grid_columns: u32,
+    pub grid_rows: u32,
+    pub ring_dots: u32,
+    pub spiral_dots: u32,
+    // Emitters placed with Alt + middle click shoot `emitter_rate` dots per second,
+    // in a cone of `emitter_spread` degrees around `emitter_angle`
+    pub emitter_rate: f32,
+    pub emitter_angle: f32,
+    pub emitter_speed: f32,
+    pub emitter_spread: f32,
+    pub speed: f32,
+    pub connect_force: f32,
+    pub connection_mode: ConnectionMode,
+    // Dots each dot is connected to in `ConnectionMode::Nearest`
+    pub nearest_neighbours: u32,
+    pub min_vel: f32,
+    pub max_vel: f32,
+    pub max_connections_drawn: usize,
+    // Connections kept by each dot, its closest ones. 0 for no limit.
+    pub max_connections_per_dot: u32,
+    pub density_adaptive: bool,
+    pub target_degree: f32,
+    pub min_alpha: f32,
+    pub max_alpha: f32,
+    pub max_line_width: f32,
+    // Fill the triangles of three dots all closer than `connect_force` to each
+    // other, more opaque the closer they are, up to `triangle_alpha`
+    pub fill_triangles: bool,
+    pub triangle_alpha: f32,
+    // Once the cursor rests, the connections of the closest dot are drawn bright and
+    // the alpha of the others multiplied by `hover_dim`
+    pub hover_highlight: bool,
+    pub hover_dim: f32,
+    pub attractor_strength: f32,
+    // The dot steered with the move keys, added with its key
+    pub player_speed: f32,
+    pub player_acceleration: f32,
+    // Widening of the view following a dot, per px/s of its speed. 0 keeps the zoom.
+    pub follow_zoom: f32,
+    // Field of the right mouse button, it fades to nothing at the radius
+    pub repulsion_strength: f32,
+    pub repulsion_radius: f32,
+    // Same for the herd key, pulling the dots toward the cursor
+    pub herd_strength: f32,
+    pub herd_radius: f32,
+    // Pull of the gravity wells, divided by the squared distance
+    pub well_strength: f32,
+    // Spin of the vortices, divided by the distance. A negative one reverses their
+    // direction.
+    pub vortex_strength: f32,
+    // Noise field turning the dots, so they flow in streams
+    pub flow_field: bool,
+    // How fast the dots turn toward the field
+    pub flow_strength: f32,
+    // Size of the swirls of the field
+    pub flow_scale: f32,
+    // How fast the field changes, in noise cells per second
+    pub flow_speed: f32,
+    // Pulls every dot down, they then bounce on the bottom of the window
+    pub gravity: bool,
+    pub gravity_strength: f32,
+    // Part of the vertical speed kept when a dot bounces on the floor with gravity
+    pub floor_restitution: f32,
+    // Part of the speed kept by a dot bouncing on the edges. Below 1 the dots settle
+    // along them, above 1 they gain speed at every bounce.
+    pub restitution: f32,
+    // Air friction, the velocities decay by exp(-damping * t) so the dots settle. A
+    // negative one speeds them up, until the fastest spawn speed.
+    pub damping: f32,
+    // The connections act as springs of this rest length, the network then settles
+    // into a web (with some damping)
+    pub springs: bool,
+    pub spring_stiffness: f32,
+    pub spring_rest_length: f32,
+    pub color_mode: ColorMode,
+    pub aging_rate: f32,
+    pub age_for_old_color: f32,
+    pub age_color_young: Color,
+    pub age_color_old: Color,
+    pub freeze_dots: bool,
+    pub dot_collisions: bool,
+    // Touching dots merge into a larger one
+    pub merge_dots: bool,
+    pub collision_iterations: u32,
+    pub collision_particles: bool,
+    pub broadphase: Broadphase,
+    pub gpu_connections: bool,
+    pub gradient_background: bool,
+    pub bloom: bool,
+    pub bloom_intensity: f32,
+    pub bloom_threshold: f32,
+    // Render in HDR, so the colors can go over 1 where the lines add up. Always on
+    // with `bloom`.
+    pub hdr: bool,
+    pub tonemapping: ToneMapping,
+    // In stops, every +1 doubles the brightness before the tonemapping
+    pub exposure: f32,
+    // Strength of the effects drawn over the image, 0 to leave them out. The
+    // vignette darkens the corners, the chromatic aberration splits the colors toward
+    // the edges and the film grain adds noise.
+    pub vignette: f32,
+    pub chromatic_aberration: f32,
+    pub film_grain: f32,
+    // Samples per pixel of the MSAA, 1 to turn it off. 4 is supported by every GPU,
+    // 2 and 8 by most desktop ones.
+    pub msaa_samples: u32,
+    // Anti-aliasing of the final image, on top of the MSAA
+    pub fxaa: bool,
+    // Wait for the screen refresh before showing a frame. Without it, the frame
+    // rate is only limited by `fps_cap`.
+    pub vsync: bool,
+    // Most frames per second, 0 for no limit
+    pub fps_cap: f32,
+    // Spread the dots in a box of `depth` and show them with a camera turning around
+    // them at `orbit_speed`
+    pub three_d: bool,
+    pub depth: f32,
+    pub orbit_speed: f32,
+    // Share of the opacity lost by the dots and the connections at the back of the
+    // box, from 0 to 1
+    pub depth_fade: f32,
+    // Size of the area the dots bounce in, centered on `world_offset`. The window
+    // size when unset, the world can then be larger than the view.
+    pub world_size: Option<Vec2>,
+    pub world_offset: Vec2,
+    pub boundary_mode: BoundaryMode,
+    // Radius of the circle of `BoundaryMode::Circle`
+    pub arena_radius: f32,
+    // World positions of the outline of `BoundaryMode::Polygon`, e.g. loaded with
+    // `load_polygon`
+    pub arena_polygon: Vec<Vec2>,
+    // Seed of the random spawns, read at startup. A random one is used when unset.
+    pub seed: Option<u64>,
+    // Dots spawned at random positions when the app starts
+    #[serde(skip)]
+    pub number_of_dots: u32,
+}
+
+impl Default for SimuConf {
+    fn default() -> Self {
+        SimuConf {
+            dot_size: DOT_SIZE,
+            dot_size_variance: 0.,
+            dot_lifetime: 0.,
+            max_dots: 0,
+            brush_count: 1,
+            brush_radius: 0.,
+            burst_count: BURST_COUNT,
+            burst_speed: BURST_SPEED,
+            pattern_spacing: PATTERN_SPACING,
+            pattern_spin: PATTERN_SPIN,
+            grid_columns: GRID_COLUMNS,
+            grid_rows: GRID_ROWS,
+            ring_dots: RING_DOTS,
+            spiral_dots: SPIRAL_DOTS,
+            emitter_rate: EMITTER_RATE,
+            emitter_angle: EMITTER_ANGLE,
+            emitter_speed: EMITTER_SPEED,
+            emitter_spread: EMITTER_SPREAD,
+            speed: SPEED,
+            min_vel: MIN_VEL,
+            max_vel: MAX_VEL,
+            connect_force: CONNECT_FORCE,
+            connection_mode: ConnectionMode::Distance,
+            nearest_neighbours: NEAREST_NEIGHBOURS,
+            max_connections_drawn: MAX_CONNECTIONS_DRAWN,
+            max_connections_per_dot: 0,
+            density_adaptive: false,
+            target_degree: TARGET_DEGREE,
+            min_alpha: MIN_ALPHA,
+            max_alpha: MAX_ALPHA,
+            max_line_width: MAX_LINE_WIDTH,
+            fill_triangles: false,
+            triangle_alpha: TRIANGLE_ALPHA,
+            hover_highlight: true,
+            hover_dim: HOVER_DIM,
+            attractor_strength: ATTRACTOR_STRENGTH,
+            player_speed: PLAYER_SPEED,
+            player_acceleration: PLAYER_ACCELERATION,
+            follow_zoom: FOLLOW_ZOOM,
+            repulsion_strength: REPULSION_STRENGTH,
+            repulsion_radius: REPULSION_RADIUS,
+            herd_strength: HERD_STRENGTH,
+            herd_radius: HERD_RADIUS,
+            well_strength: WELL_STRENGTH,
+            vortex_strength: VORTEX_STRENGTH,
+            flow_field: false,
+            flow_strength: FLOW_STRENGTH,
+            flow_scale: FLOW_SCALE,
+            flow_speed: FLOW_SPEED,
+            gravity: false,
+            gravity_strength: GRAVITY_STRENGTH,
+            floor_restitution: FLOOR_RESTITUTION,
+            restitution: RESTITUTION,
+            damping: 0.,
+            springs: false,
+            spring_stiffness: SPRING_STIFFNESS,
+            spring_rest_length: SPRING_REST_LENGTH,
+            color_mode: ColorMode::Uniform,
+            aging_rate: AGING_RATE,
+            age_for_old_color: AGE_FOR_OLD_COLOR,
+            age_color_young: AGE_COLOR_YOUNG,
+            age_color_old: AGE_COLOR_OLD,
+            freeze_dots: false,
+            dot_collisions: false,
+            merge_dots: false,
+            collision_iterations: COLLISION_ITERATIONS,
+            collision_particles: true,
+            broadphase: Broadphase::Grid,
+            gpu_connections: false,
+            gradient_background: false,
+            bloom: false,
+            bloom_intensity: BLOOM_INTENSITY,
+            bloom_threshold: BLOOM_THRESHOLD,
+            hdr: false,
+            tonemapping: ToneMapping::None,
+            exposure: 0.,
+            vignette: 0.,
+            chromatic_aberration: 0.,
+            film_grain: 0.,
+            msaa_samples: MSAA_SAMPLES,
+            fxaa: false,
+            vsync: true,
+            fps_cap: 0.,
+            three_d: false,
+            depth: DEPTH,
+            orbit_speed: ORBIT_SPEED,
+            depth_fade: DEPTH_FADE,
+            world_size: None,
+            world_offset: Vec2::ZERO,
+            boundary_mode: BoundaryMode::Walls,
+            arena_radius: ARENA_RADIUS,
+            arena_polygon: Vec::new(),
+            seed: None,
+            number_of_dots: 0,
+        }
+    }
+}
+
+impl SimuConf {
+    // Fastest speed a dot can be spawned with
+    pub(crate) fn max_spawn_speed(&self) -> f32 {
+        self.min_vel.abs().max(self.max_vel.abs()) * std::f32::consts::SQRT_2
+    }
+}
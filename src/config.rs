@@ -0,0 +1,213 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy::window::{CompositeAlphaMode, WindowLevel, WindowMode, WindowResolution};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::theme::Theme;
+use crate::{Action, CameraConfig, KeyBindings, SimuConf};
+
+// Looked up in the working directory when no path is given
+const CONFIG_FILE_PATHS: [&str; 2] = ["dot-network.toml", "dot-network.ron"];
+
+// Startup settings of the app. Every field has a default value so a file only
+// needs the ones it changes.
+#[derive(Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Config {
+    // Dots spawned at random positions when the app starts
+    pub dots: u32,
+    pub simu_conf: SimuConf,
+    pub camera: CameraConfig,
+    // Name of the color theme, e.g. "Ocean"
+    pub theme: Option<String>,
+    // Width of the lines drawn with gizmos (the sparks), Bevy's default when unset
+    pub gizmo_line_width: Option<f32>,
+    pub window: WindowConfig,
+    // Keys replacing the default ones of these actions, read at startup
+    pub keys: HashMap<Action, Vec<KeyCode>>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct WindowConfig {
+    // Width and height, Bevy's default when unset
+    pub size: Option<Vec2>,
+    // Borderless fullscreen
+    pub fullscreen: bool,
+    // Position on the desktop, it wins over `monitor`
+    pub position: Option<IVec2>,
+    // Index of the monitor to center the window on
+    pub monitor: Option<usize>,
+    // Borderless fullscreen, transparent, behind the other windows and letting the
+    // clicks through, with a `Wallpaper`
+    pub wallpaper: bool,
+}
+
+impl WindowConfig {
+    pub fn window(&self) -> Window {
+        let mut window = Window {
+            position: match (self.position, self.monitor) {
+                (Some(position), _) => WindowPosition::At(position),
+                (None, Some(index)) => WindowPosition::Centered(MonitorSelection::Index(index)),
+                (None, None) => WindowPosition::Automatic,
+            },
+            resolution: match self.size {
+                Some(size) => WindowResolution::new(size.x, size.y),
+                None => default(),
+            },
+            mode: if self.fullscreen || self.wallpaper {
+                WindowMode::BorderlessFullscreen
+            } else {
+                WindowMode::Windowed
+            },
+            ..default()
+        };
+        if self.wallpaper {
+            window.transparent = true;
+            // Without it, the transparent parts are drawn black on macOS
+            if cfg!(target_os = "macos") {
+                window.composite_alpha_mode = CompositeAlphaMode::PostMultiplied;
+            }
+            window.decorations = false;
+            window.window_level = WindowLevel::AlwaysOnBottom;
+            window.cursor.hit_test = false;
+        }
+        window
+    }
+}
+
+impl Config {
+    // Reads a TOML file, or a RON one when the path ends with `.ron`
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let content = std::fs::read_to_string(path)?;
+        let config: Config = match path.extension() {
+            Some(extension) if extension == "ron" => ron::from_str(&content)?,
+            _ => toml::from_str(&content)?,
+        };
+        config.check()?;
+        Ok(config)
+    }
+
+    // Values the simulation can't run with, the UI never sets them
+    fn check(&self) -> Result<(), String> {
+        let simu_conf = &self.simu_conf;
+        if simu_conf.depth < 0. {
+            return Err(format!("depth is negative ({})", simu_conf.depth));
+        }
+        if simu_conf.min_vel >= simu_conf.max_vel {
+            return Err(format!(
+                "min_vel ({}) isn't below max_vel ({})",
+                simu_conf.min_vel, simu_conf.max_vel
+            ));
+        }
+        Ok(())
+    }
+
+    // The default keys, with the ones of the file
+    pub fn key_bindings(&self) -> KeyBindings {
+        let mut bindings = KeyBindings::default();
+        for (action, keys) in &self.keys {
+            bindings.bind(*action, keys.clone());
+        }
+        bindings
+    }
+
+    // The SimuConf of the file, with the dots to spawn
+    pub fn simu_conf(&self) -> SimuConf {
+        SimuConf {
+            number_of_dots: self.dots,
+            ..self.simu_conf.clone()
+        }
+    }
+}
+
+// The config file in use, inserted before `DotNetworkPlugin` to pick the theme.
+// It's reloaded when saved, see `reload_config_file`.
+#[derive(Resource, Default)]
+pub struct ConfigFile {
+    // None when no file was found and the defaults are used
+    pub path: Option<PathBuf>,
+    pub config: Config,
+    // Of the file when it was last read
+    pub(crate) modified: Option<SystemTime>,
+}
+
+impl ConfigFile {
+    // Loads the given file, or the first of `CONFIG_FILE_PATHS` found. Falls back to
+    // the defaults when no path is given and none of them exists.
+    pub fn load(path: Option<PathBuf>) -> Result<Self, Box<dyn Error>> {
+        let path = path.or_else(|| {
+            CONFIG_FILE_PATHS
+                .iter()
+                .map(PathBuf::from)
+                .find(|path| path.exists())
+        });
+        let config = match &path {
+            Some(path) => {
+                Config::load(path).map_err(|error| format!("{}: {error}", path.display()))?
+            }
+            None => Config::default(),
+        };
+        let modified = path.as_deref().and_then(modified_time);
+        Ok(ConfigFile {
+            path,
+            config,
+            modified,
+        })
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+// Applies the file again when it's saved, so the visual can be tuned from a text
+// editor while the simulation runs. The dots, camera and window of the file are
+// only read at startup. A half written file is reported and skipped, the next save
+// is read again.
+pub(crate) fn reload_config_file(
+    mut file: ResMut<ConfigFile>,
+    mut simu_conf: ResMut<SimuConf>,
+    mut theme: ResMut<Theme>,
+) {
+    let Some(path) = file.path.clone() else {
+        return;
+    };
+    let modified = modified_time(&path);
+    if modified == file.modified {
+        return;
+    }
+    file.modified = modified;
+
+    match Config::load(&path) {
+        Ok(config) => {
+            info!("Reloaded {}", path.display());
+            *simu_conf = SimuConf {
+                number_of_dots: simu_conf.number_of_dots,
+                ..config.simu_conf.clone()
+            };
+            if let Some(name) = &config.theme {
+                match Theme::named(name) {
+                    Some(named) if named != *theme => *theme = named,
+                    Some(_) => {}
+                    None => warn!("Unknown theme {name}"),
+                }
+            }
+            file.config = config;
+        }
+        Err(error) => warn!("Can't reload {}: {error}", path.display()),
+    }
+}
+
+pub(crate) fn apply_gizmo_config(file: Res<ConfigFile>, mut gizmos: ResMut<GizmoConfigStore>) {
+    let (config, _) = gizmos.config_mut::<DefaultGizmoConfigGroup>();
+    config.line_width = file
+        .config
+        .gizmo_line_width
+        .unwrap_or(GizmoConfig::default().line_width);
+}
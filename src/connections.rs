@@ -0,0 +1,556 @@
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology, VertexAttributeValues};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::view::{NoFrustumCulling, RenderLayers};
+use bevy::sprite::{MaterialMesh2dBundle, Mesh2dHandle};
+use bevy::utils::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+
+use crate::broadphase::NeighbourSearch;
+use crate::camera::MainCamera;
+use crate::clusters::Clusters;
+use crate::color::{cluster_hue, hue_color, lerp_color};
+use crate::delaunay::Delaunay;
+use crate::dots::Degree;
+use crate::gpu::GpuEdges;
+use crate::grid::SpatialGrid;
+use crate::hud::{DistanceHistogram, HISTOGRAM_BUCKETS};
+use crate::inspector::HoveredDot;
+use crate::spanning_tree::SpanningTree;
+use crate::theme::Theme;
+use crate::{ColorMode, SimuConf};
+
+// How the dots to connect are picked
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Reflect, Serialize, Deserialize)]
+pub enum ConnectionMode {
+    // Every pair closer than `SimuConf.connect_force`
+    #[default]
+    Distance,
+    // Edges of the Delaunay triangulation of the dots, a mesh of triangles without
+    // crossing lines
+    Delaunay,
+    // Minimum spanning tree of the dots, the skeleton of the cloud
+    SpanningTree,
+    // Every dot to its `SimuConf.nearest_neighbours` closest dots, however far they
+    // are
+    Nearest,
+}
+
+impl ConnectionMode {
+    // Listed by the settings panel
+    #[cfg(feature = "egui")]
+    pub(crate) const ALL: [ConnectionMode; 4] = [
+        ConnectionMode::Distance,
+        ConnectionMode::Delaunay,
+        ConnectionMode::SpanningTree,
+        ConnectionMode::Nearest,
+    ];
+
+    pub(crate) fn next(self) -> Self {
+        match self {
+            ConnectionMode::Distance => ConnectionMode::Delaunay,
+            ConnectionMode::Delaunay => ConnectionMode::SpanningTree,
+            ConnectionMode::SpanningTree => ConnectionMode::Nearest,
+            ConnectionMode::Nearest => ConnectionMode::Distance,
+        }
+    }
+}
+
+const ADAPTIVE_MIN_RADIUS_RATIO: f32 = 0.25;
+const ADAPTIVE_MAX_RADIUS_RATIO: f32 = 2.;
+
+// Width of the longest connections, the closest ones get `SimuConf.max_line_width`
+pub(crate) const LINE_WIDTH: f32 = 1.; // In px
+pub(crate) const LINES_LAYER: u8 = 1;
+
+// Draws every connection as a quad of one shared mesh (on the `ConnectionMesh`
+// entity), rebuilt each frame, so all the lines take a single draw call. The mesh
+// is on `LINES_LAYER`, only the lines camera renders it.
+#[derive(Resource, Default)]
+pub(crate) struct ConnectionRenderer {
+    pub(crate) above_dots: bool,
+}
+
+#[derive(Component)]
+pub(crate) struct ConnectionMesh;
+
+// Work done by `connect_dot` during the last frame, plain counters so measuring
+// stays cheap
+#[derive(Resource, Default)]
+pub(crate) struct PerfStats {
+    pub(crate) pair_checks: usize,
+    pub(crate) cells: usize,
+    pub(crate) on_gpu: bool,
+    pub(crate) candidates: usize,
+    pub(crate) drawn: usize,
+    pub(crate) timings: SystemTimings,
+}
+
+// Time spent in the heaviest systems during the last frame, reset once reported
+// so a disabled system reads 0
+#[derive(Default)]
+pub(crate) struct SystemTimings {
+    pub(crate) neighbour_search: Duration,
+    pub(crate) connections: Duration,
+    pub(crate) collisions: Duration,
+}
+
+fn distance_between_points(p1: Vec2, p2: Vec2) -> f32 {
+    ((p2.x - p1.x).powi(2) + (p2.y - p1.y).powi(2)).sqrt()
+}
+
+pub(crate) fn map(value: f32, from_low: f32, from_high: f32, to_low: f32, to_high: f32) -> f32 {
+    to_low + (to_high - to_low) * ((value - from_low) / (from_high - from_low))
+}
+
+// Farthest distance at which two dots can be connected
+pub(crate) fn max_connection_radius(simu_conf: &SimuConf) -> f32 {
+    if simu_conf.density_adaptive {
+        simu_conf.connect_force * ADAPTIVE_MAX_RADIUS_RATIO
+    } else {
+        simu_conf.connect_force
+    }
+}
+
+// Effective connection radius of every dot. In density adaptive mode, the local
+// density of a dot is estimated by its number of neighbours within `connect_force`.
+// Its radius then scales with sqrt(target_degree / neighbours), since the number of
+// neighbours in a disk grows with its area, and is clamped so isolated dots don't
+// reach across the whole screen. The neighbours are counted during the same pair
+// pass as the connections, so the mode only costs an extra radius per dot.
+fn connection_radii(degrees: &[u32], simu_conf: &SimuConf, radii: &mut Vec<f32>) {
+    radii.clear();
+    radii.extend(degrees.iter().map(|&degree| {
+        if !simu_conf.density_adaptive {
+            return simu_conf.connect_force;
+        }
+        let ratio = (simu_conf.target_degree / degree.max(1) as f32).sqrt();
+        simu_conf.connect_force * ratio.clamp(ADAPTIVE_MIN_RADIUS_RATIO, ADAPTIVE_MAX_RADIUS_RATIO)
+    }));
+}
+
+// Two dots close enough to be connected, `radius` is the distance at which they
+// would disconnect
+#[derive(Clone, Copy)]
+pub(crate) struct Connection {
+    // Indices of the two dots in the positions of `NeighbourSearch`
+    pub(crate) dots: [usize; 2],
+    pub(crate) from: Vec2,
+    pub(crate) to: Vec2,
+    pub(crate) dist: f32,
+    pub(crate) radius: f32,
+}
+
+// Connections found by `connect_dot` this frame
+#[derive(Resource, Default, Deref, DerefMut)]
+pub(crate) struct Connections(Vec<Connection>);
+
+// Buffers reused by `connect_dot` from one frame to the next
+#[derive(Default)]
+pub(crate) struct ConnectionBuffers {
+    degrees: Vec<u32>,
+    radii: Vec<f32>,
+    candidates: Vec<(usize, usize, f32)>,
+    delaunay: Delaunay,
+    spanning_tree: SpanningTree,
+    nearest: NearestPairs,
+    kept: Vec<u32>,
+}
+
+// Pairs of `ConnectionMode::Nearest`, the neighbours are looked for on the plane
+// with a grid holding a few dots per cell
+#[derive(Default)]
+struct NearestPairs {
+    grid: SpatialGrid,
+    nearest: Vec<(f32, usize)>,
+    pairs: Vec<[usize; 2]>,
+}
+
+impl NearestPairs {
+    fn find(&mut self, positions: &[Vec2], count: usize) -> &[[usize; 2]] {
+        self.pairs.clear();
+        let Some(&first) = positions.first() else {
+            return &self.pairs;
+        };
+        let bounds = positions
+            .iter()
+            .fold(Rect::from_corners(first, first), |bounds, &position| {
+                bounds.union_point(position)
+            });
+        let cell_size = (bounds.width() * bounds.height() * count as f32 / positions.len() as f32)
+            .sqrt()
+            .max(1.);
+        self.grid.rebuild(cell_size, positions);
+        for i in 0..positions.len() {
+            self.grid.nearest(positions, i, count, &mut self.nearest);
+            self.pairs
+                .extend(self.nearest.iter().map(|&(_, j)| [i.min(j), i.max(j)]));
+        }
+        // Two dots among the closest of each other are only connected once
+        self.pairs.sort_unstable();
+        self.pairs.dedup();
+        &self.pairs
+    }
+}
+
+// Distance between two dots of the search. In 3D, the dots close on the plane can
+// still be kept apart by their depth.
+fn dot_distance(search: &NeighbourSearch, i: usize, j: usize) -> f32 {
+    let (positions, depths) = (search.positions(), search.depths());
+    distance_between_points(positions[i], positions[j]).hypot(depths[j] - depths[i])
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn connect_dot(
+    mut connections: ResMut<Connections>,
+    search: Res<NeighbourSearch>,
+    gpu_edges: Res<GpuEdges>,
+    simu_conf: Res<SimuConf>,
+    mut stats: ResMut<PerfStats>,
+    mut histogram: ResMut<DistanceHistogram>,
+    mut buffers: Local<ConnectionBuffers>,
+    time: Res<Time>,
+) {
+    let start = Instant::now();
+    let max_radius = max_connection_radius(&simu_conf);
+    connections.clear();
+    match simu_conf.connection_mode {
+        ConnectionMode::Distance => connect_close_pairs(
+            &mut connections,
+            &search,
+            &gpu_edges,
+            &simu_conf,
+            &mut stats,
+            &mut buffers,
+        ),
+        mode => {
+            // Every edge is drawn, the ones longer than the connect force at the
+            // alpha floor
+            stats.cells = 0;
+            stats.on_gpu = false;
+            stats.pair_checks = 0;
+            let positions = search.positions();
+            let edges = match mode {
+                ConnectionMode::Delaunay => buffers.delaunay.triangulate(positions),
+                ConnectionMode::SpanningTree => {
+                    buffers
+                        .spanning_tree
+                        .update(positions, time.delta_seconds(), |i, j| {
+                            dot_distance(&search, i, j)
+                        })
+                }
+                _ => buffers
+                    .nearest
+                    .find(positions, simu_conf.nearest_neighbours as usize),
+            };
+            connections.extend(edges.iter().map(|&[i, j]| Connection {
+                dots: [i, j],
+                from: positions[i],
+                to: positions[j],
+                dist: dot_distance(&search, i, j),
+                radius: max_radius,
+            }));
+        }
+    }
+    if simu_conf.max_connections_per_dot > 0 {
+        limit_connections_per_dot(
+            &mut connections,
+            search.positions().len(),
+            simu_conf.max_connections_per_dot,
+            &mut buffers.kept,
+        );
+    }
+
+    // Over the budget, only keep the strongest connections. A partial sort is enough
+    // to split them from the weakest ones, no need to fully sort every frame.
+    stats.candidates = connections.len();
+    let max = simu_conf.max_connections_drawn;
+    if connections.len() > max {
+        connections.select_nth_unstable_by(max, |a, b| {
+            (a.dist / a.radius).total_cmp(&(b.dist / b.radius))
+        });
+        connections.truncate(max);
+    }
+    stats.drawn = connections.len();
+
+    if histogram.enabled {
+        histogram.counts = [0; HISTOGRAM_BUCKETS];
+        histogram.max_distance = max_radius;
+        for connection in connections.iter() {
+            let bucket = (connection.dist / max_radius * HISTOGRAM_BUCKETS as f32) as usize;
+            histogram.counts[bucket.min(HISTOGRAM_BUCKETS - 1)] += 1;
+        }
+    }
+    stats.timings.connections = start.elapsed();
+}
+
+// Every pair closer than the connection radius, found with the broadphase or on
+// the GPU
+fn connect_close_pairs(
+    connections: &mut Connections,
+    search: &NeighbourSearch,
+    gpu_edges: &GpuEdges,
+    simu_conf: &SimuConf,
+    stats: &mut PerfStats,
+    buffers: &mut ConnectionBuffers,
+) {
+    let ConnectionBuffers {
+        degrees,
+        radii,
+        candidates,
+        ..
+    } = buffers;
+
+    // The search radius is `max_radius`, so every connection is among its pairs
+    let positions = search.positions();
+    degrees.clear();
+    degrees.resize(positions.len(), 0);
+    let max_radius = max_connection_radius(simu_conf);
+
+    candidates.clear();
+    let mut check_pair = |i: usize, j: usize| {
+        let dist = dot_distance(search, i, j);
+        if dist < simu_conf.connect_force {
+            degrees[i] += 1;
+            degrees[j] += 1;
+        }
+        if dist < max_radius {
+            candidates.push((i, j, dist));
+        }
+    };
+
+    // The GPU pairs come from a previous frame, they are only used while the dots
+    // are the same. Their distance is measured again with the current positions.
+    stats.cells = search.cell_count();
+    let gpu_edges = gpu_edges.0.lock().unwrap();
+    let gpu_pairs = gpu_edges
+        .as_ref()
+        .filter(|edges| simu_conf.gpu_connections && edges.dot_count == positions.len());
+    stats.on_gpu = gpu_pairs.is_some();
+    stats.pair_checks = match gpu_pairs {
+        Some(edges) => {
+            for &[i, j] in &edges.pairs {
+                check_pair(i as usize, j as usize);
+            }
+            positions.len() * positions.len().saturating_sub(1) / 2
+        }
+        None => search.for_each_pair(&mut check_pair),
+    };
+
+    connection_radii(degrees, simu_conf, radii);
+    connections.extend(candidates.iter().filter_map(|&(i, j, dist)| {
+        let radius = (radii[i] + radii[j]) / 2.;
+        (dist < radius).then_some(Connection {
+            dots: [i, j],
+            from: positions[i],
+            to: positions[j],
+            dist,
+            radius,
+        })
+    }));
+}
+
+// Every dot keeps its closest connections. Taken from the shortest one, a
+// connection is kept while both its dots have room for it, so dense clusters are
+// thinned out and there are at most `max` lines per dot.
+fn limit_connections_per_dot(
+    connections: &mut Connections,
+    dot_count: usize,
+    max: u32,
+    kept: &mut Vec<u32>,
+) {
+    kept.clear();
+    kept.resize(dot_count, 0);
+    connections.sort_unstable_by(|a, b| a.dist.total_cmp(&b.dist));
+    connections.retain(|connection| {
+        let [i, j] = connection.dots;
+        if kept[i] >= max || kept[j] >= max {
+            return false;
+        }
+        kept[i] += 1;
+        kept[j] += 1;
+        true
+    });
+}
+
+// Counts the drawn connections of every dot
+pub(crate) fn update_degrees(
+    connections: Res<Connections>,
+    search: Res<NeighbourSearch>,
+    mut dots: Query<&mut Degree>,
+    mut counts: Local<Vec<u32>>,
+) {
+    counts.clear();
+    counts.resize(search.entities().len(), 0);
+    for connection in connections.iter() {
+        for dot in connection.dots {
+            counts[dot] += 1;
+        }
+    }
+    for (&entity, &count) in search.entities().iter().zip(counts.iter()) {
+        if let Ok(mut degree) = dots.get_mut(entity) {
+            degree.set_if_neq(Degree(count));
+        }
+    }
+}
+
+// Colored triangles rewritten every frame. Kept in the main world too, so the
+// systems drawing into it can change it.
+pub(crate) fn dynamic_mesh() -> Mesh {
+    let mut mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, Vec::<[f32; 3]>::new());
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, Vec::<[f32; 4]>::new());
+    mesh.insert_indices(Indices::U32(Vec::new()));
+    mesh
+}
+
+// Vertices of a `dynamic_mesh`, emptied. They are taken out and put back with
+// `put_mesh_buffers` to reuse their allocations.
+pub(crate) fn take_mesh_buffers(mesh: &mut Mesh) -> (Vec<[f32; 3]>, Vec<[f32; 4]>, Vec<u32>) {
+    let mut positions = match mesh.remove_attribute(Mesh::ATTRIBUTE_POSITION) {
+        Some(VertexAttributeValues::Float32x3(positions)) => positions,
+        _ => Vec::new(),
+    };
+    let mut colors = match mesh.remove_attribute(Mesh::ATTRIBUTE_COLOR) {
+        Some(VertexAttributeValues::Float32x4(colors)) => colors,
+        _ => Vec::new(),
+    };
+    let mut indices = match mesh.remove_indices() {
+        Some(Indices::U32(indices)) => indices,
+        _ => Vec::new(),
+    };
+    positions.clear();
+    colors.clear();
+    indices.clear();
+    (positions, colors, indices)
+}
+
+pub(crate) fn put_mesh_buffers(
+    mesh: &mut Mesh,
+    positions: Vec<[f32; 3]>,
+    colors: Vec<[f32; 4]>,
+    indices: Vec<u32>,
+) {
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+    mesh.insert_indices(Indices::U32(indices));
+}
+
+pub(crate) fn spawn_connection_mesh(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: meshes.add(dynamic_mesh()).into(),
+            // The vertex colors are used as is
+            material: materials.add(Color::WHITE),
+            // Above the gradient background, drawn by the same camera when the lines
+            // are below the dots
+            transform: Transform::from_xyz(0., 0., 0.5),
+            // Shown once there are connections, an empty mesh can't be drawn
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        ConnectionMesh,
+        RenderLayers::layer(LINES_LAYER),
+        // The bounds of the mesh change every frame
+        NoFrustumCulling,
+    ));
+}
+
+// The closer the dots, the brighter and the nearer to `line_near` the connection
+pub(crate) fn connection_color(
+    connection: &Connection,
+    clusters: &Clusters,
+    hovered: &HoveredDot,
+    simu_conf: &SimuConf,
+    theme: &Theme,
+) -> Color {
+    // A loaded file may set a floor above the ceiling, `clamp` would panic
+    let max_alpha = simu_conf.max_alpha.max(simu_conf.min_alpha);
+    let alpha =
+        map(connection.dist, 0., connection.radius, 1., 0.).clamp(simu_conf.min_alpha, max_alpha);
+    // Around a highlighted dot, only its own connections stand out
+    let alpha = match hovered.0 {
+        Some(dot) if connection.dots.contains(&dot) => max_alpha,
+        Some(_) => alpha * simu_conf.hover_dim,
+        None => alpha,
+    };
+    let palette = theme.palette();
+    let line_color = match clusters.label(connection.dots[0]) {
+        Some(label) if simu_conf.color_mode == ColorMode::Clusters => hue_color(cluster_hue(label)),
+        _ => lerp_color(
+            palette.line_near,
+            palette.line_far,
+            (connection.dist / connection.radius).clamp(0., 1.),
+        ),
+    };
+    line_color.with_a(alpha)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn draw_connections(
+    connections: Res<Connections>,
+    clusters: Res<Clusters>,
+    hovered: Res<HoveredDot>,
+    theme: Res<Theme>,
+    simu_conf: Res<SimuConf>,
+    camera: Query<&OrthographicProjection, With<MainCamera>>,
+    mut connection_mesh: Query<(&Mesh2dHandle, &mut Visibility), With<ConnectionMesh>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    let Ok((handle, mut visibility)) = connection_mesh.get_single_mut() else {
+        return;
+    };
+    let Some(mesh) = meshes.get_mut(&handle.0) else {
+        return;
+    };
+    let shown = if connections.is_empty() {
+        Visibility::Hidden
+    } else {
+        Visibility::Inherited
+    };
+    visibility.set_if_neq(shown);
+
+    let (mut positions, mut colors, mut indices) = take_mesh_buffers(mesh);
+
+    // Lines keep the same width on screen whatever the zoom
+    let scale = camera
+        .get_single()
+        .map_or(1., |projection| projection.scale);
+    let max_width = simu_conf.max_line_width.max(LINE_WIDTH);
+    for connection in connections.iter() {
+        // Scaled like the alpha, the closer the dots the thicker the line
+        let width = map(
+            connection.dist,
+            0.,
+            connection.radius,
+            max_width,
+            LINE_WIDTH,
+        )
+        .clamp(LINE_WIDTH, max_width);
+        let half_width = width / 2. * scale;
+        let color = connection_color(connection, &clusters, &hovered, &simu_conf, &theme)
+            .as_linear_rgba_f32();
+        let normal = (connection.to - connection.from).normalize_or_zero().perp() * half_width;
+
+        let first = positions.len() as u32;
+        for corner in [
+            connection.from + normal,
+            connection.from - normal,
+            connection.to + normal,
+            connection.to - normal,
+        ] {
+            positions.push(corner.extend(0.).to_array());
+            colors.push(color);
+        }
+        indices.extend([0, 1, 2, 2, 1, 3].map(|index| first + index));
+    }
+
+    put_mesh_buffers(mesh, positions, colors, indices);
+}
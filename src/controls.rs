@@ -0,0 +1,222 @@
+use bevy::prelude::*;
+
+use crate::dots::MAX_DOT_SIZE_VARIANCE;
+use crate::hud::{hud_text, toggle_visibility, HudRoot, HISTOGRAM_BAR_ALPHA};
+use crate::input::PointerOverUi;
+use crate::slider::{spawn_slider, Slider};
+use crate::theme::Theme;
+use crate::tools::Toolbar;
+use crate::SimuConf;
+
+const CONTROL_BAR_PADDING: Val = Val::Px(6.0);
+const CONTROL_BAR_BACKGROUND: Color = Color::rgba(0., 0., 0., 0.5);
+const CONTROL_LABEL_WIDTH: f32 = 110.; // In px
+const CONTROL_VALUE_WIDTH: f32 = 50.; // In px
+
+// Field of `SimuConf` that can be set with a slider of the control bar
+struct ConfField {
+    name: &'static str,
+    min: f32,
+    max: f32,
+    // Number of decimals shown
+    precision: usize,
+    get: fn(&SimuConf) -> f32,
+    set: fn(&mut SimuConf, f32),
+}
+
+const CONF_FIELDS: [ConfField; 7] = [
+    ConfField {
+        name: "Connect force",
+        min: 0.,
+        max: 1000.,
+        precision: 0,
+        get: |conf| conf.connect_force,
+        set: |conf, value| conf.connect_force = value,
+    },
+    ConfField {
+        name: "Speed",
+        min: 0.,
+        max: 5.,
+        precision: 2,
+        get: |conf| conf.speed,
+        set: |conf, value| conf.speed = value,
+    },
+    ConfField {
+        name: "Dot size",
+        min: 1.,
+        max: 30.,
+        precision: 1,
+        get: |conf| conf.dot_size,
+        set: |conf, value| conf.dot_size = value,
+    },
+    ConfField {
+        name: "Size variance",
+        min: 0.,
+        max: MAX_DOT_SIZE_VARIANCE,
+        precision: 2,
+        get: |conf| conf.dot_size_variance,
+        set: |conf, value| conf.dot_size_variance = value,
+    },
+    ConfField {
+        name: "Lifetime",
+        min: 0.,
+        max: 60.,
+        precision: 0,
+        get: |conf| conf.dot_lifetime,
+        set: |conf, value| conf.dot_lifetime = value,
+    },
+    ConfField {
+        name: "Damping",
+        min: -0.5,
+        max: 2.,
+        precision: 2,
+        get: |conf| conf.damping,
+        set: |conf, value| conf.damping = value,
+    },
+    ConfField {
+        name: "Bounciness",
+        min: 0.,
+        max: 1.5,
+        precision: 2,
+        get: |conf| conf.restitution,
+        set: |conf, value| conf.restitution = value,
+    },
+];
+
+// The control bar, at the bottom right of the screen
+#[derive(Component)]
+pub(crate) struct ControlBar;
+
+// Binds a slider of the control bar to its field of `SimuConf`
+#[derive(Component)]
+pub(crate) struct ConfSlider {
+    field: &'static ConfField,
+    value_text: Entity,
+}
+
+// Spawns an hidden control bar with one row (name, slider, value) per field
+pub(crate) fn spawn_control_bar(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    simu_conf: Res<SimuConf>,
+    root: Query<Entity, With<HudRoot>>,
+) {
+    let fill_color = theme.palette().line_near.with_a(HISTOGRAM_BAR_ALPHA);
+    let mut bindings = Vec::with_capacity(CONF_FIELDS.len());
+
+    let bar = commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: CONTROL_BAR_PADDING,
+                    right: CONTROL_BAR_PADDING,
+                    padding: UiRect::all(CONTROL_BAR_PADDING),
+                    row_gap: CONTROL_BAR_PADDING,
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                background_color: CONTROL_BAR_BACKGROUND.into(),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            // Tells when the pointer is over the bar
+            Interaction::default(),
+            ControlBar,
+        ))
+        .with_children(|bar| {
+            for field in &CONF_FIELDS {
+                bar.spawn(NodeBundle {
+                    style: Style {
+                        align_items: AlignItems::Center,
+                        column_gap: CONTROL_BAR_PADDING,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|row| {
+                    let (label, marker) = hud_text(field.name, &theme);
+                    row.spawn((
+                        label.with_style(Style {
+                            width: Val::Px(CONTROL_LABEL_WIDTH),
+                            ..default()
+                        }),
+                        marker,
+                    ));
+                    let value = (field.get)(&simu_conf);
+                    let slider = Slider {
+                        min: field.min,
+                        max: field.max,
+                        value,
+                    };
+                    let slider = spawn_slider(row, slider, fill_color);
+                    let (value_text, marker) =
+                        hud_text(&format!("{:.*}", field.precision, value), &theme);
+                    let value_text = row
+                        .spawn((
+                            value_text.with_style(Style {
+                                width: Val::Px(CONTROL_VALUE_WIDTH),
+                                ..default()
+                            }),
+                            marker,
+                        ))
+                        .id();
+                    bindings.push((slider, ConfSlider { field, value_text }));
+                });
+            }
+        })
+        .id();
+    commands.entity(root.single()).add_child(bar);
+
+    for (slider, binding) in bindings {
+        commands.entity(slider).insert(binding);
+    }
+}
+
+pub(crate) fn toggle_control_bar(mut query: Query<&mut Visibility, With<ControlBar>>) {
+    let mut visibility = query.single_mut();
+    toggle_visibility(&mut visibility);
+}
+
+// Writes the dragged sliders to `SimuConf`, the other ones follow it so they stay
+// right when the keyboard changes a value
+pub(crate) fn sync_conf_sliders(
+    mut simu_conf: ResMut<SimuConf>,
+    mut sliders: Query<(&ConfSlider, &Interaction, &mut Slider)>,
+) {
+    for (binding, interaction, mut slider) in &mut sliders {
+        let value = (binding.field.get)(&simu_conf);
+        if *interaction == Interaction::Pressed {
+            if value != slider.value {
+                (binding.field.set)(&mut simu_conf, slider.value);
+            }
+        } else if value != slider.value {
+            slider.value = value;
+        }
+    }
+}
+
+pub(crate) fn update_slider_values(
+    sliders: Query<(&ConfSlider, &Slider), Changed<Slider>>,
+    mut texts: Query<&mut Text>,
+) {
+    for (binding, slider) in &sliders {
+        if let Ok(mut text) = texts.get_mut(binding.value_text) {
+            text.sections[0].value = format!("{:.*}", binding.field.precision, slider.value);
+        }
+    }
+}
+
+// The control bar and the toolbar take the clicks over them
+#[allow(clippy::type_complexity)]
+pub(crate) fn track_pointer_over_controls(
+    panels: Query<(&Interaction, &ViewVisibility), Or<(With<ControlBar>, With<Toolbar>)>>,
+    mut pointer: ResMut<PointerOverUi>,
+) {
+    let over = panels
+        .iter()
+        .any(|(interaction, visibility)| visibility.get() && *interaction != Interaction::None);
+    if pointer.0 != over {
+        pointer.0 = over;
+    }
+}
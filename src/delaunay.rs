@@ -0,0 +1,148 @@
+use bevy::math::DVec2;
+use bevy::prelude::*;
+
+// The corners of the triangle holding every point are that many times the size of
+// their bounding box away from it. The farther, the fewer edges of the convex hull
+// are missed, an artifact of the algorithm.
+const SUPER_TRIANGLE_SCALE: f64 = 1000.;
+
+#[derive(Clone, Copy)]
+struct Triangle {
+    vertices: [usize; 3],
+    center: DVec2,
+    radius_squared: f64,
+}
+
+// Delaunay triangulation with the Bowyer-Watson algorithm, following Paul Bourke:
+// the points are inserted from left to right, so a triangle whose circumcircle is
+// entirely left of the current point can't be broken by the next ones and is set
+// aside. The buffers are kept from one frame to the next.
+#[derive(Default)]
+pub(crate) struct Delaunay {
+    // The points in f64, with the 3 corners of the super triangle at the end
+    points: Vec<DVec2>,
+    order: Vec<usize>,
+    open: Vec<Triangle>,
+    closed: Vec<Triangle>,
+    polygon: Vec<[usize; 2]>,
+    edges: Vec<[usize; 2]>,
+}
+
+impl Delaunay {
+    // Edges of the triangulation, as pairs of indices in `positions` with the lowest
+    // one first. Points at the same position are only linked once.
+    pub(crate) fn triangulate(&mut self, positions: &[Vec2]) -> &[[usize; 2]] {
+        self.edges.clear();
+        self.open.clear();
+        self.closed.clear();
+        let count = positions.len();
+        if count < 2 {
+            return &self.edges;
+        }
+
+        self.points.clear();
+        self.points
+            .extend(positions.iter().map(|position| position.as_dvec2()));
+        let (min, max) = self.points.iter().fold(
+            (DVec2::splat(f64::MAX), DVec2::splat(f64::MIN)),
+            |(min, max), &point| (min.min(point), max.max(point)),
+        );
+        let size = (max - min).max_element().max(1.) * SUPER_TRIANGLE_SCALE;
+        let middle = (min + max) / 2.;
+        self.points.extend([
+            DVec2::new(middle.x - size, middle.y - size),
+            DVec2::new(middle.x, middle.y + size),
+            DVec2::new(middle.x + size, middle.y - size),
+        ]);
+        if let Some(triangle) = Triangle::new(&self.points, [count, count + 1, count + 2]) {
+            self.open.push(triangle);
+        }
+
+        self.order.clear();
+        self.order.extend(0..count);
+        let points = &self.points;
+        self.order.sort_unstable_by(|&a, &b| {
+            points[a]
+                .x
+                .total_cmp(&points[b].x)
+                .then(points[a].y.total_cmp(&points[b].y))
+        });
+
+        let mut previous: Option<DVec2> = None;
+        for &index in &self.order {
+            let point = self.points[index];
+            // A second point at the same place would make flat triangles
+            if previous == Some(point) {
+                continue;
+            }
+            previous = Some(point);
+
+            self.polygon.clear();
+            let mut i = 0;
+            while i < self.open.len() {
+                let triangle = self.open[i];
+                let dx = point.x - triangle.center.x;
+                if dx > 0. && dx * dx > triangle.radius_squared {
+                    self.closed.push(triangle);
+                    self.open.swap_remove(i);
+                } else if point.distance_squared(triangle.center) <= triangle.radius_squared {
+                    let [a, b, c] = triangle.vertices;
+                    self.polygon
+                        .extend([[a, b], [b, c], [c, a]].map(|[a, b]| [a.min(b), a.max(b)]));
+                    self.open.swap_remove(i);
+                } else {
+                    i += 1;
+                }
+            }
+
+            // The edges shared by two removed triangles are inside the hole, the
+            // others are its outline
+            self.polygon.sort_unstable();
+            let mut j = 0;
+            while j < self.polygon.len() {
+                if self.polygon.get(j + 1) == Some(&self.polygon[j]) {
+                    j += 2;
+                    continue;
+                }
+                let [a, b] = self.polygon[j];
+                if let Some(triangle) = Triangle::new(&self.points, [a, b, index]) {
+                    self.open.push(triangle);
+                }
+                j += 1;
+            }
+        }
+
+        for triangle in self.closed.iter().chain(&self.open) {
+            let [a, b, c] = triangle.vertices;
+            for [a, b] in [[a, b], [b, c], [c, a]] {
+                if a < count && b < count {
+                    self.edges.push([a.min(b), a.max(b)]);
+                }
+            }
+        }
+        self.edges.sort_unstable();
+        self.edges.dedup();
+        &self.edges
+    }
+}
+
+impl Triangle {
+    // None for 3 aligned points, which have no circumcircle
+    fn new(points: &[DVec2], vertices: [usize; 3]) -> Option<Self> {
+        let [a, b, c] = vertices.map(|vertex| points[vertex]);
+        let (ab, ac) = (b - a, c - a);
+        let d = 2. * ab.perp_dot(ac);
+        if d == 0. {
+            return None;
+        }
+        let offset = DVec2::new(
+            ac.y * ab.length_squared() - ab.y * ac.length_squared(),
+            ab.x * ac.length_squared() - ac.x * ab.length_squared(),
+        ) / d;
+        Some(Triangle {
+            vertices,
+            center: a + offset,
+            radius_squared: offset.length_squared(),
+        })
+    }
+}
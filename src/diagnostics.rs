@@ -0,0 +1,58 @@
+use bevy::diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic};
+use bevy::prelude::*;
+use bevy::utils::Duration;
+
+use crate::connections::{connect_dot, Connections, PerfStats};
+use crate::dots::Dot;
+
+// Measures the simulation every frame, so the counters show up in the output of
+// `LogDiagnosticsPlugin` and in the `DiagnosticsStore`. Added by `DotNetworkPlugin`.
+pub struct DotNetworkDiagnosticsPlugin;
+
+impl Plugin for DotNetworkDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_diagnostic(Diagnostic::new(Self::DOTS))
+            .register_diagnostic(Diagnostic::new(Self::CONNECTIONS))
+            .register_diagnostic(Diagnostic::new(Self::PAIR_CHECKS))
+            .register_diagnostic(Diagnostic::new(Self::NEIGHBOUR_SEARCH_TIME).with_suffix("ms"))
+            .register_diagnostic(Diagnostic::new(Self::CONNECTIONS_TIME).with_suffix("ms"))
+            .register_diagnostic(Diagnostic::new(Self::COLLISIONS_TIME).with_suffix("ms"))
+            .add_systems(Update, Self::diagnostic_system.after(connect_dot));
+    }
+}
+
+impl DotNetworkDiagnosticsPlugin {
+    pub const DOTS: DiagnosticPath = DiagnosticPath::const_new("dot_network/dots");
+    // Connections drawn this frame
+    pub const CONNECTIONS: DiagnosticPath = DiagnosticPath::const_new("dot_network/connections");
+    // Distance checks done by the broadphase to find the connections
+    pub const PAIR_CHECKS: DiagnosticPath = DiagnosticPath::const_new("dot_network/pair_checks");
+    // Time spent rebuilding the broadphase, finding the connections and resolving
+    // the collisions between the dots, in ms
+    pub const NEIGHBOUR_SEARCH_TIME: DiagnosticPath =
+        DiagnosticPath::const_new("dot_network/neighbour_search_time");
+    pub const CONNECTIONS_TIME: DiagnosticPath =
+        DiagnosticPath::const_new("dot_network/connections_time");
+    pub const COLLISIONS_TIME: DiagnosticPath =
+        DiagnosticPath::const_new("dot_network/collisions_time");
+
+    fn diagnostic_system(
+        mut diagnostics: Diagnostics,
+        dots: Query<(), With<Dot>>,
+        connections: Res<Connections>,
+        mut stats: ResMut<PerfStats>,
+    ) {
+        diagnostics.add_measurement(&Self::DOTS, || dots.iter().len() as f64);
+        diagnostics.add_measurement(&Self::CONNECTIONS, || connections.len() as f64);
+        diagnostics.add_measurement(&Self::PAIR_CHECKS, || stats.pair_checks as f64);
+
+        let timings = std::mem::take(&mut stats.timings);
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.;
+        diagnostics.add_measurement(
+            &Self::NEIGHBOUR_SEARCH_TIME,
+            || ms(timings.neighbour_search),
+        );
+        diagnostics.add_measurement(&Self::CONNECTIONS_TIME, || ms(timings.connections));
+        diagnostics.add_measurement(&Self::COLLISIONS_TIME, || ms(timings.collisions));
+    }
+}
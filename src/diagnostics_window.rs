@@ -0,0 +1,103 @@
+use bevy::prelude::*;
+use bevy::render::camera::RenderTarget;
+use bevy::render::view::RenderLayers;
+use bevy::ui::TargetCamera;
+use bevy::window::{WindowRef, WindowResolution};
+
+use crate::connections::LINES_LAYER;
+use crate::controls::ControlBar;
+use crate::hud::{
+    show_histogram, DistanceHistogram, DistanceHistogramPanel, HudRoot, PerfHud, SpeedHistogram,
+    SpeedHistogramPanel,
+};
+
+const DIAGNOSTICS_WINDOW_SIZE: Vec2 = Vec2::new(1100., 700.);
+// Nothing is drawn on it, so the camera of the diagnostics window only renders the
+// HUD
+const DIAGNOSTICS_LAYER: u8 = LINES_LAYER + 1;
+
+// Second OS window holding the whole HUD, so the main one only shows the dots
+#[derive(Component)]
+pub(crate) struct DiagnosticsWindow;
+
+// Renders the HUD in the diagnostics window, until the window is closed
+#[derive(Component)]
+pub(crate) struct DiagnosticsCamera {
+    window: Entity,
+}
+
+// Opens the window with the perf HUD, the histograms and the control bar shown, or
+// closes it
+#[allow(clippy::type_complexity)]
+pub(crate) fn toggle_diagnostics_window(
+    mut commands: Commands,
+    window: Query<Entity, With<DiagnosticsWindow>>,
+    root: Query<Entity, With<HudRoot>>,
+    mut panels: Query<&mut Visibility, Or<(With<PerfHud>, With<ControlBar>)>>,
+    mut histogram_panels: Query<
+        &mut Style,
+        Or<(With<DistanceHistogramPanel>, With<SpeedHistogramPanel>)>,
+    >,
+    mut distance_histogram: ResMut<DistanceHistogram>,
+    mut speed_histogram: ResMut<SpeedHistogram>,
+) {
+    if let Ok(window) = window.get_single() {
+        // `close_diagnostics_window` gives the HUD back to the main window
+        commands.entity(window).despawn();
+        return;
+    }
+
+    let window = commands
+        .spawn((
+            Window {
+                title: "Dot Network diagnostics".into(),
+                resolution: WindowResolution::new(
+                    DIAGNOSTICS_WINDOW_SIZE.x,
+                    DIAGNOSTICS_WINDOW_SIZE.y,
+                ),
+                ..default()
+            },
+            DiagnosticsWindow,
+        ))
+        .id();
+    let camera = commands
+        .spawn((
+            Camera2dBundle {
+                camera: Camera {
+                    target: RenderTarget::Window(WindowRef::Entity(window)),
+                    ..default()
+                },
+                ..default()
+            },
+            RenderLayers::layer(DIAGNOSTICS_LAYER),
+            DiagnosticsCamera { window },
+        ))
+        .id();
+    commands.entity(root.single()).insert(TargetCamera(camera));
+
+    for mut visibility in &mut panels {
+        *visibility = Visibility::Inherited;
+    }
+    distance_histogram.enabled = true;
+    speed_histogram.enabled = true;
+    for mut style in &mut histogram_panels {
+        show_histogram(&mut style, true);
+    }
+}
+
+// Once the window is gone, closed with its button or with the key, the HUD is drawn
+// on the main window again
+pub(crate) fn close_diagnostics_window(
+    mut commands: Commands,
+    cameras: Query<(Entity, &DiagnosticsCamera)>,
+    windows: Query<(), With<Window>>,
+    root: Query<Entity, With<HudRoot>>,
+) {
+    for (camera, diagnostics) in &cameras {
+        if windows.contains(diagnostics.window) {
+            continue;
+        }
+        commands.entity(camera).despawn();
+        commands.entity(root.single()).remove::<TargetCamera>();
+    }
+}
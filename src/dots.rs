@@ -0,0 +1,859 @@
+use bevy::prelude::*;
+use bevy::sprite::{MaterialMesh2dBundle, Mesh2dHandle};
+use bevy::utils::HashMap;
+use bevy::window::PrimaryWindow;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::VecDeque;
+use std::f32::consts::{PI, TAU};
+
+use crate::actions::SimAction;
+use crate::camera::{cursor_world_position, MainCamera};
+use crate::color::{
+    hue_color, lerp_color, CLUSTER_HUES, DEGREE_COLOR_HIGH, DEGREE_COLOR_LOW, PALETTE_STEPS,
+    SPEED_COLOR_FAST, SPEED_COLOR_SLOW,
+};
+use crate::particles::Lifetime;
+use crate::physics::WorldBounds;
+use crate::theme::Theme;
+use crate::SimuConf;
+
+// Keeps the smallest dots visible
+pub(crate) const MAX_DOT_SIZE_VARIANCE: f32 = 0.9;
+
+// Lighter dots can't be split, a click on them spawns dots as usual
+const SPLIT_MIN_MASS: f32 = 2.;
+const SPLIT_MAX_PARTS: usize = 4;
+const SPLIT_SPEED: f32 = 150.; // In px/s, added to the speed of the split dot
+
+const PIN_MARKER_GAP: f32 = 3.; // In px, between a pinned dot and its ring
+
+// Radius of the eraser while the brush is a point
+const ERASER_MIN_RADIUS: f32 = 20.; // In px
+
+// Used to identify the Dots
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct Dot;
+
+#[derive(Component, Reflect, Default, Deref, DerefMut)]
+#[reflect(Component)]
+pub struct Velocity(pub Vec2);
+
+// Time the dot has been alive, scaled by `SimuConf.aging_rate`
+#[derive(Component, Default, Deref, DerefMut)]
+pub(crate) struct Age(pub(crate) f32);
+
+// Size of a dot relative to `SimuConf.dot_size`, sampled on spawn within
+// `SimuConf.dot_size_variance` and multiplied by the pressure of a stylus
+#[derive(Component, Deref)]
+pub(crate) struct DotScale(pub(crate) f32);
+
+// Proportional to the area of the dot, 1 for a dot of `SimuConf.dot_size`. The
+// collisions and the forces pushing the dots move the heavy ones less.
+#[derive(Component, Deref)]
+pub(crate) struct Mass(pub(crate) f32);
+
+// Pinned dots stay in place, the forces and the collisions don't move them but they
+// are still connected. Another click unpins them.
+#[derive(Component)]
+pub(crate) struct Pinned;
+
+// Dot held under the cursor by the mouse, it keeps the velocity of the drag once
+// released
+#[derive(Component)]
+pub(crate) struct Grabbed {
+    // World position of the cursor in the last frame
+    last_position: Vec2,
+}
+
+// A dot to spawn, `respawn_from` replaces every dot by a list of them
+pub(crate) struct NewDot {
+    pub(crate) position: Vec2,
+    pub(crate) velocity: Vec2,
+    pub(crate) age: f32,
+    // See `DotScale`
+    pub(crate) scale: f32,
+}
+
+// Number of connections drawn to the dot in the last frame
+#[derive(Component, Default, Deref, DerefMut, PartialEq)]
+pub(crate) struct Degree(pub(crate) u32);
+
+// Mesh and materials shared by every dot, so spawning doesn't create new assets.
+// Meshes are keyed by dot size and materials by color, `mesh` and `material` are
+// the ones of the current settings. Color modes pick one of the palette materials
+// instead of creating their own.
+#[derive(Resource)]
+pub(crate) struct DotAssets {
+    meshes: HashMap<u32, Handle<Mesh>>,
+    materials: HashMap<[u8; 4], Handle<ColorMaterial>>,
+    size: f32,
+    pub(crate) mesh: Handle<Mesh>,
+    pub(crate) material: Handle<ColorMaterial>,
+    pub(crate) age_palette: Vec<Handle<ColorMaterial>>,
+    pub(crate) speed_palette: Vec<Handle<ColorMaterial>>,
+    pub(crate) degree_palette: Vec<Handle<ColorMaterial>>,
+    pub(crate) cluster_palette: Vec<Handle<ColorMaterial>>,
+    // Of the player only, whatever the color mode. Its color follows the theme.
+    pub(crate) player_material: Handle<ColorMaterial>,
+}
+
+impl DotAssets {
+    fn mesh_for(&mut self, meshes: &mut Assets<Mesh>, size: f32) -> Handle<Mesh> {
+        self.meshes
+            .entry(size.to_bits())
+            .or_insert_with(|| meshes.add(Circle { radius: size }))
+            .clone()
+    }
+
+    fn material_for(
+        &mut self,
+        materials: &mut Assets<ColorMaterial>,
+        color: Color,
+    ) -> Handle<ColorMaterial> {
+        self.materials
+            .entry(color.as_rgba_u8())
+            .or_insert_with(|| materials.add(color))
+            .clone()
+    }
+
+    // Material of the color of `material` with this alpha, shared by the dots fading
+    // out at the same step. None while `material` isn't loaded.
+    pub(crate) fn faded(
+        &mut self,
+        materials: &mut Assets<ColorMaterial>,
+        material: &Handle<ColorMaterial>,
+        alpha: f32,
+    ) -> Option<Handle<ColorMaterial>> {
+        let color = materials.get(material)?.color.with_a(alpha);
+        Some(self.material_for(materials, color))
+    }
+
+    // Makes `color` the one of the uniform color mode
+    pub(crate) fn set_color(&mut self, materials: &mut Assets<ColorMaterial>, color: Color) {
+        self.material = self.material_for(materials, color);
+    }
+
+    pub(crate) fn set_player_color(&self, materials: &mut Assets<ColorMaterial>, color: Color) {
+        if let Some(material) = materials.get_mut(&self.player_material) {
+            material.color = color;
+        }
+    }
+
+    // Makes `size` the current one. The meshes of the other sizes are forgotten, their
+    // asset is freed once `resize_dots` moved every dot to the new mesh.
+    fn set_size(&mut self, meshes: &mut Assets<Mesh>, size: f32) {
+        self.size = size;
+        self.mesh = self.mesh_for(meshes, size);
+        self.meshes.retain(|&key, _| key == size.to_bits());
+    }
+}
+
+pub(crate) fn setup_dot_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    simu_conf: Res<SimuConf>,
+) {
+    let mut dot_assets = DotAssets {
+        meshes: HashMap::new(),
+        materials: HashMap::new(),
+        size: simu_conf.dot_size,
+        mesh: Handle::default(),
+        material: Handle::default(),
+        // Filled by `update_age_palette`
+        age_palette: (0..PALETTE_STEPS)
+            .map(|_| materials.add(Color::VIOLET))
+            .collect(),
+        speed_palette: Vec::with_capacity(PALETTE_STEPS),
+        degree_palette: Vec::with_capacity(PALETTE_STEPS),
+        cluster_palette: Vec::with_capacity(CLUSTER_HUES),
+        // Not shared with the other dots, `apply_theme` changes its color
+        player_material: materials.add(Color::VIOLET),
+    };
+    dot_assets.set_size(&mut meshes, simu_conf.dot_size);
+    // Replaced by the color of the theme in `apply_theme`
+    dot_assets.material = dot_assets.material_for(&mut materials, Color::VIOLET);
+    for i in 0..PALETTE_STEPS {
+        let t = i as f32 / (PALETTE_STEPS - 1) as f32;
+        let speed_color = lerp_color(SPEED_COLOR_SLOW, SPEED_COLOR_FAST, t);
+        let speed_material = dot_assets.material_for(&mut materials, speed_color);
+        dot_assets.speed_palette.push(speed_material);
+        let degree_color = lerp_color(DEGREE_COLOR_LOW, DEGREE_COLOR_HIGH, t);
+        let degree_material = dot_assets.material_for(&mut materials, degree_color);
+        dot_assets.degree_palette.push(degree_material);
+    }
+    for hue in 0..CLUSTER_HUES {
+        let material = dot_assets.material_for(&mut materials, hue_color(hue));
+        dot_assets.cluster_palette.push(material);
+    }
+    commands.insert_resource(dot_assets);
+}
+
+// Keeps every dot on the mesh of the current `dot_size`, including dots spawned
+// with the previous mesh earlier in the frame
+pub(crate) fn resize_dots(
+    mut query: Query<&mut Mesh2dHandle, With<Dot>>,
+    simu_conf: Res<SimuConf>,
+    mut dot_assets: ResMut<DotAssets>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    if dot_assets.size != simu_conf.dot_size {
+        dot_assets.set_size(&mut meshes, simu_conf.dot_size);
+    }
+
+    for mut mesh in &mut query {
+        if mesh.0 != dot_assets.mesh {
+            mesh.0 = dot_assets.mesh.clone();
+        }
+    }
+}
+
+pub(crate) fn clear_dots(
+    mut query: Query<Entity, With<Dot>>,
+    mut commands: Commands,
+    mut simu_conf: ResMut<SimuConf>,
+) {
+    for dot in &mut query {
+        commands.entity(dot).despawn();
+    }
+    simu_conf.number_of_dots = 0;
+}
+
+// Every dot, from the oldest to the newest. The deleted dots are only removed from
+// it when it gets much longer than `SimuConf.number_of_dots`.
+#[derive(Resource, Default)]
+pub(crate) struct SpawnOrder(VecDeque<Entity>);
+
+// Random generator of every spawn, seeded with `SimuConf::seed`
+#[derive(Resource, Deref, DerefMut)]
+pub(crate) struct DotRng(StdRng);
+
+impl FromWorld for DotRng {
+    fn from_world(world: &mut World) -> Self {
+        let seed = world.get_resource::<SimuConf>().and_then(|conf| conf.seed);
+        DotRng(match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        })
+    }
+}
+
+fn random_velocity(rng: &mut impl Rng, simu_conf: &SimuConf) -> Vec2 {
+    Vec2::new(
+        rng.gen_range(simu_conf.min_vel..simu_conf.max_vel),
+        rng.gen_range(simu_conf.min_vel..simu_conf.max_vel),
+    )
+}
+
+// Uniform between `1 - dot_size_variance` and `1 + dot_size_variance`
+pub(crate) fn random_scale(rng: &mut impl Rng, simu_conf: &SimuConf) -> f32 {
+    let variance = simu_conf.dot_size_variance.clamp(0., MAX_DOT_SIZE_VARIANCE);
+    rng.gen_range(1. - variance..=1. + variance)
+}
+
+fn random_position(rng: &mut impl Rng, bounds: Rect) -> Vec2 {
+    Vec2::new(
+        rng.gen_range(bounds.min.x..=bounds.max.x),
+        rng.gen_range(bounds.min.y..=bounds.max.y),
+    )
+}
+
+// Dots spawned while `SimuConf.dot_lifetime` is set fade out, even the ones loaded
+// from a scene
+pub(crate) fn spawn_dot(
+    commands: &mut Commands,
+    dot_assets: &DotAssets,
+    simu_conf: &SimuConf,
+    dot: NewDot,
+) -> Entity {
+    let NewDot {
+        position,
+        velocity,
+        age,
+        scale,
+    } = dot;
+    let mut dot = commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: dot_assets.mesh.clone().into(),
+            transform: Transform::from_xyz(position.x, position.y, 1.),
+            material: dot_assets.material.clone(),
+            ..default()
+        },
+        Dot,
+        Velocity(velocity),
+        Age(age),
+        DotScale(scale),
+        Mass(scale * scale),
+        Degree::default(),
+    ));
+    if simu_conf.dot_lifetime > 0. {
+        dot.insert(Lifetime(Timer::from_seconds(
+            simu_conf.dot_lifetime,
+            TimerMode::Once,
+        )));
+    }
+    dot.id()
+}
+
+// Replaces all the current dots by new ones, used by every bulk spawning path
+pub(crate) fn respawn_from(
+    commands: &mut Commands,
+    dot_assets: &DotAssets,
+    simu_conf: &mut SimuConf,
+    dots: impl IntoIterator<Item = Entity>,
+    new_dots: impl IntoIterator<Item = NewDot>,
+) {
+    for dot in dots {
+        commands.entity(dot).despawn();
+    }
+    simu_conf.number_of_dots = 0;
+
+    for new_dot in new_dots {
+        spawn_dot(commands, dot_assets, simu_conf, new_dot);
+        simu_conf.number_of_dots += 1;
+    }
+}
+
+pub(crate) fn randomize_dots(
+    query: Query<Entity, With<Dot>>,
+    mut commands: Commands,
+    dot_assets: Res<DotAssets>,
+    bounds: Res<WorldBounds>,
+    mut simu_conf: ResMut<SimuConf>,
+    mut rng: ResMut<DotRng>,
+) {
+    let rng = &mut **rng;
+    let new_dots: Vec<NewDot> = (0..simu_conf.number_of_dots)
+        .map(|_| NewDot {
+            position: random_position(rng, **bounds),
+            velocity: random_velocity(rng, &simu_conf),
+            age: 0.,
+            scale: random_scale(rng, &simu_conf),
+        })
+        .collect();
+
+    respawn_from(&mut commands, &dot_assets, &mut simu_conf, &query, new_dots);
+}
+
+// Adds `count` dots at random positions, the existing ones are kept
+#[cfg(feature = "egui")]
+pub(crate) fn spawn_random_dots(
+    commands: &mut Commands,
+    dot_assets: &DotAssets,
+    bounds: Rect,
+    simu_conf: &mut SimuConf,
+    rng: &mut DotRng,
+    count: u32,
+) {
+    for _ in 0..count {
+        let dot = NewDot {
+            position: random_position(&mut **rng, bounds),
+            velocity: random_velocity(&mut **rng, simu_conf),
+            age: 0.,
+            scale: random_scale(&mut **rng, simu_conf),
+        };
+        spawn_dot(commands, dot_assets, simu_conf, dot);
+    }
+    simu_conf.number_of_dots += count;
+}
+
+// Dots spawned at once, scattered uniformly within `radius` of the cursor. `scale`
+// multiplies their random size.
+struct Brush {
+    count: u32,
+    radius: f32,
+    scale: f32,
+}
+
+fn spawn_brush(
+    commands: &mut Commands,
+    dot_assets: &DotAssets,
+    simu_conf: &mut SimuConf,
+    rng: &mut impl Rng,
+    center: Vec2,
+    brush: Brush,
+) {
+    for _ in 0..brush.count {
+        let distance = brush.radius * rng.gen::<f32>().sqrt();
+        let dot = NewDot {
+            position: center + Vec2::from_angle(rng.gen_range(0. ..TAU)) * distance,
+            velocity: random_velocity(rng, simu_conf),
+            age: 0.,
+            scale: brush.scale * random_scale(rng, simu_conf),
+        };
+        spawn_dot(commands, dot_assets, simu_conf, dot);
+    }
+    simu_conf.number_of_dots += brush.count;
+}
+
+pub(crate) fn spawn_dots_at(
+    mut actions: EventReader<SimAction>,
+    mut commands: Commands,
+    dot_assets: Res<DotAssets>,
+    mut simu_conf: ResMut<SimuConf>,
+    mut rng: ResMut<DotRng>,
+) {
+    for action in actions.read() {
+        let (position, count, scale) = match *action {
+            SimAction::SpawnDot(position) => (position, simu_conf.brush_count, 1.),
+            SimAction::SpawnSizedDot { position, scale } => (position, 1, scale),
+            _ => continue,
+        };
+        let brush = Brush {
+            count,
+            radius: simu_conf.brush_radius,
+            scale,
+        };
+        spawn_brush(
+            &mut commands,
+            &dot_assets,
+            &mut simu_conf,
+            &mut **rng,
+            position,
+            brush,
+        );
+    }
+}
+
+// Outline of the brush around the cursor, when it's larger than a point
+pub(crate) fn draw_brush(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    simu_conf: Res<SimuConf>,
+    theme: Res<Theme>,
+    mut gizmos: Gizmos,
+) {
+    if let Some(cursor_pos) = cursor_world_position(&windows, &camera_query) {
+        gizmos.circle_2d(cursor_pos, simu_conf.brush_radius, theme.palette().hud);
+    }
+}
+
+// The eraser takes the radius of the brush, with a minimum so it always erases
+// something
+pub(crate) fn eraser_radius(simu_conf: &SimuConf) -> f32 {
+    simu_conf.brush_radius.max(ERASER_MIN_RADIUS)
+}
+
+// Deletes the dots around the position of `SimAction::Erase`, the reverse of the
+// brush
+pub(crate) fn erase_dots(
+    mut actions: EventReader<SimAction>,
+    dots: Query<(Entity, &Transform), With<Dot>>,
+    mut commands: Commands,
+    mut simu_conf: ResMut<SimuConf>,
+) {
+    for action in actions.read() {
+        let SimAction::Erase(position) = *action else {
+            continue;
+        };
+        let radius = eraser_radius(&simu_conf);
+        for (dot, transform) in &dots {
+            if transform.translation.truncate().distance(position) < radius {
+                commands.entity(dot).despawn();
+                simu_conf.number_of_dots -= 1;
+            }
+        }
+    }
+}
+
+// Outline of the eraser around the cursor while its key is held
+pub(crate) fn draw_eraser(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    simu_conf: Res<SimuConf>,
+    theme: Res<Theme>,
+    mut gizmos: Gizmos,
+) {
+    if let Some(cursor_pos) = cursor_world_position(&windows, &camera_query) {
+        gizmos.circle_2d(cursor_pos, eraser_radius(&simu_conf), theme.palette().spark);
+    }
+}
+
+// Spawns a ring of dots around the position of the action, every one flying straight
+// away from its center. The dots are twice their size apart on the ring.
+pub(crate) fn spawn_bursts(
+    mut actions: EventReader<SimAction>,
+    mut commands: Commands,
+    dot_assets: Res<DotAssets>,
+    mut simu_conf: ResMut<SimuConf>,
+    mut rng: ResMut<DotRng>,
+) {
+    for action in actions.read() {
+        let SimAction::Burst(center) = *action else {
+            continue;
+        };
+        let count = simu_conf.burst_count;
+        let radius = 2. * simu_conf.dot_size * count as f32 / TAU;
+        let start_angle = rng.gen_range(0. ..TAU);
+        for i in 0..count {
+            let direction = Vec2::from_angle(start_angle + TAU * i as f32 / count as f32);
+            let dot = NewDot {
+                position: center + direction * radius,
+                velocity: direction * simu_conf.burst_speed,
+                age: 0.,
+                scale: random_scale(&mut **rng, &simu_conf),
+            };
+            spawn_dot(&mut commands, &dot_assets, &simu_conf, dot);
+        }
+        simu_conf.number_of_dots += count;
+    }
+}
+
+// Dot under this world position, the last one drawn when they overlap
+pub(crate) fn dot_at<'a>(
+    position: Vec2,
+    dots: impl IntoIterator<Item = (Entity, &'a Transform, &'a DotScale)>,
+    simu_conf: &SimuConf,
+) -> Option<Entity> {
+    dots.into_iter()
+        .filter(|(_, transform, scale)| {
+            transform.translation.truncate().distance(position) < simu_conf.dot_size * ***scale
+        })
+        .max_by(|(_, a, _), (_, b, _)| a.translation.z.total_cmp(&b.translation.z))
+        .map(|(dot, ..)| dot)
+}
+
+// Pins the dot at the position of the action, or unpins it
+pub(crate) fn toggle_pins(
+    mut actions: EventReader<SimAction>,
+    mut commands: Commands,
+    dots: Query<(Entity, &Transform, &DotScale), With<Dot>>,
+    pinned: Query<(), With<Pinned>>,
+    simu_conf: Res<SimuConf>,
+) {
+    for action in actions.read() {
+        let SimAction::TogglePin(position) = *action else {
+            continue;
+        };
+        let Some(dot) = dot_at(position, &dots, &simu_conf) else {
+            continue;
+        };
+        if pinned.contains(dot) {
+            commands.entity(dot).remove::<Pinned>();
+        } else {
+            commands.entity(dot).insert(Pinned);
+        }
+    }
+}
+
+// Grabs the dot at the position of `SimAction::Grab`, moves the grabbed dots with
+// `SimAction::Drag` and lets them go on `SimAction::Release`. Run after the dots
+// moved, their velocity is the one of the cursor so they fly on once released.
+pub(crate) fn grab_dots(
+    mut actions: EventReader<SimAction>,
+    mut commands: Commands,
+    mut dots: Query<(Entity, &mut Transform, &DotScale, &mut Velocity), With<Dot>>,
+    mut grabbed: Query<(Entity, &mut Grabbed)>,
+    time: Res<Time>,
+    simu_conf: Res<SimuConf>,
+) {
+    for action in actions.read() {
+        match *action {
+            SimAction::Grab(position) => {
+                let under_cursor = dots
+                    .iter()
+                    .map(|(dot, transform, scale, _)| (dot, transform, scale));
+                if let Some(dot) = dot_at(position, under_cursor, &simu_conf) {
+                    commands.entity(dot).insert(Grabbed {
+                        last_position: position,
+                    });
+                }
+            }
+            SimAction::Drag(position) => {
+                let dt = simu_conf.speed * time.delta_seconds();
+                for (dot, mut grab) in &mut grabbed {
+                    let Ok((_, mut transform, _, mut velocity)) = dots.get_mut(dot) else {
+                        continue;
+                    };
+                    if dt > 0. {
+                        velocity.0 = (position - grab.last_position) / dt;
+                    }
+                    transform.translation = position.extend(transform.translation.z);
+                    grab.last_position = position;
+                }
+            }
+            SimAction::Release => {
+                for (dot, _) in &grabbed {
+                    commands.entity(dot).remove::<Grabbed>();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// The forces changed the velocity of the pinned dots too, it's dropped before the
+// dots move
+pub(crate) fn hold_pinned_dots(mut query: Query<&mut Velocity, With<Pinned>>) {
+    for mut velocity in &mut query {
+        velocity.0 = Vec2::ZERO;
+    }
+}
+
+// A ring around the pinned dots
+pub(crate) fn draw_pins(
+    query: Query<(&Transform, &DotScale), With<Pinned>>,
+    simu_conf: Res<SimuConf>,
+    theme: Res<Theme>,
+    mut gizmos: Gizmos,
+) {
+    for (transform, scale) in &query {
+        gizmos.circle_2d(
+            transform.translation.truncate(),
+            simu_conf.dot_size * **scale + PIN_MARKER_GAP,
+            theme.palette().hud,
+        );
+    }
+}
+
+// Dot under this world position heavy enough to be split, the last one drawn when
+// they overlap
+pub(crate) fn splittable_dot_at<'a>(
+    position: Vec2,
+    dots: impl IntoIterator<Item = (Entity, &'a Transform, &'a DotScale, &'a Mass)>,
+    simu_conf: &SimuConf,
+) -> Option<Entity> {
+    dots.into_iter()
+        .filter(|(_, transform, scale, mass)| {
+            ***mass >= SPLIT_MIN_MASS
+                && transform.translation.truncate().distance(position)
+                    < simu_conf.dot_size * ***scale
+        })
+        .max_by(|(_, a, ..), (_, b, ..)| a.translation.z.total_cmp(&b.translation.z))
+        .map(|(dot, ..)| dot)
+}
+
+// Splits the dot at the position of the action into equal parts flying apart, the
+// reverse of `merge_dots`: the area and the momentum are kept. The parts don't
+// touch, so they aren't merged back right away.
+pub(crate) fn split_dots(
+    mut actions: EventReader<SimAction>,
+    mut commands: Commands,
+    dots: Query<(Entity, &Transform, &DotScale, &Mass), With<Dot>>,
+    details: Query<(&Velocity, &Age)>,
+    dot_assets: Res<DotAssets>,
+    mut simu_conf: ResMut<SimuConf>,
+    mut rng: ResMut<DotRng>,
+) {
+    for action in actions.read() {
+        let SimAction::SplitDot(position) = *action else {
+            continue;
+        };
+        let Some(dot) = splittable_dot_at(position, &dots, &simu_conf) else {
+            continue;
+        };
+        let (_, transform, _, mass) = dots.get(dot).unwrap();
+        let (velocity, age) = details.get(dot).unwrap();
+        let parts = (mass.floor() as usize).clamp(2, SPLIT_MAX_PARTS);
+        let scale = (**mass / parts as f32).sqrt();
+        // From the center to the parts, a bit more than needed to keep them apart
+        let spread = simu_conf.dot_size * scale / (PI / parts as f32).sin() * 1.1;
+        let start_angle = rng.gen_range(0. ..TAU);
+
+        commands.entity(dot).despawn();
+        for part in 0..parts {
+            let direction = Vec2::from_angle(start_angle + TAU * part as f32 / parts as f32);
+            let dot = NewDot {
+                position: transform.translation.truncate() + direction * spread,
+                velocity: velocity.0 + direction * SPLIT_SPEED,
+                age: age.0,
+                scale,
+            };
+            spawn_dot(&mut commands, &dot_assets, &simu_conf, dot);
+        }
+        simu_conf.number_of_dots += parts as u32 - 1;
+    }
+}
+
+// Deletes the dots whose lifetime ran out, they don't age while frozen. Run before
+// the `MapInput` actions so a dot isn't deleted twice, e.g. also merged or cleared.
+pub(crate) fn expire_dots(
+    mut query: Query<(Entity, &mut Lifetime), With<Dot>>,
+    mut commands: Commands,
+    mut simu_conf: ResMut<SimuConf>,
+    time: Res<Time>,
+) {
+    if simu_conf.freeze_dots {
+        return;
+    }
+    for (dot, mut lifetime) in &mut query {
+        if lifetime.tick(time.delta()).finished() {
+            commands.entity(dot).despawn();
+            simu_conf.number_of_dots -= 1;
+        }
+    }
+}
+
+pub(crate) fn track_spawn_order(
+    spawned: Query<Entity, Added<Dot>>,
+    dots: Query<(), With<Dot>>,
+    simu_conf: Res<SimuConf>,
+    mut order: ResMut<SpawnOrder>,
+) {
+    order.0.extend(&spawned);
+    if order.0.len() > 2 * simu_conf.number_of_dots as usize + 64 {
+        order.0.retain(|&dot| dots.contains(dot));
+    }
+}
+
+// Deletes the oldest dots past `SimuConf.max_dots`, run like `expire_dots`
+pub(crate) fn evict_oldest_dots(
+    dots: Query<(), With<Dot>>,
+    mut commands: Commands,
+    mut simu_conf: ResMut<SimuConf>,
+    mut order: ResMut<SpawnOrder>,
+) {
+    while simu_conf.number_of_dots > simu_conf.max_dots {
+        let Some(dot) = order.0.pop_front() else {
+            break;
+        };
+        if dots.contains(dot) {
+            commands.entity(dot).despawn();
+            simu_conf.number_of_dots -= 1;
+        }
+    }
+}
+
+// Sends every dot back the way it came, the speed itself stays positive
+pub(crate) fn reverse_dots(mut query: Query<&mut Velocity, With<Dot>>) {
+    for mut velocity in &mut query {
+        velocity.0 = -velocity.0;
+    }
+}
+
+pub(crate) fn age_dots(mut query: Query<&mut Age>, time: Res<Time>, simu_conf: Res<SimuConf>) {
+    if simu_conf.freeze_dots {
+        return;
+    }
+
+    for mut age in &mut query {
+        age.0 += simu_conf.aging_rate * time.delta_seconds();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+    use std::time::Duration;
+
+    use crate::physics::apply_dot_velocity;
+
+    fn respawn_three(
+        query: Query<Entity, With<Dot>>,
+        mut commands: Commands,
+        dot_assets: Res<DotAssets>,
+        mut simu_conf: ResMut<SimuConf>,
+    ) {
+        let new_dots = (0..3).map(|i| NewDot {
+            position: Vec2::splat(i as f32),
+            velocity: Vec2::X,
+            age: 0.,
+            scale: 1.,
+        });
+        respawn_from(&mut commands, &dot_assets, &mut simu_conf, &query, new_dots);
+    }
+
+    #[test]
+    fn respawning_reuses_the_shared_assets() {
+        let mut app = App::new();
+        app.init_resource::<Assets<Mesh>>()
+            .init_resource::<Assets<ColorMaterial>>()
+            .init_resource::<SimuConf>();
+        app.world.run_system_once(setup_dot_assets);
+        app.world.run_system_once(respawn_three);
+        let meshes = app.world.resource::<Assets<Mesh>>().len();
+        let materials = app.world.resource::<Assets<ColorMaterial>>().len();
+
+        app.world.run_system_once(clear_dots);
+        app.world.run_system_once(respawn_three);
+        let mut dots = app.world.query_filtered::<(), With<Dot>>();
+        assert_eq!(dots.iter(&app.world).count(), 3);
+        assert_eq!(app.world.resource::<Assets<Mesh>>().len(), meshes);
+        assert_eq!(
+            app.world.resource::<Assets<ColorMaterial>>().len(),
+            materials
+        );
+    }
+
+    #[test]
+    fn respawning_replaces_the_dots_with_shared_handles() {
+        let mut app = App::new();
+        app.init_resource::<Assets<Mesh>>()
+            .init_resource::<Assets<ColorMaterial>>()
+            .init_resource::<SimuConf>();
+        app.world.run_system_once(setup_dot_assets);
+        app.world.run_system_once(respawn_three);
+        let mut dots = app.world.query_filtered::<Entity, With<Dot>>();
+        let first: Vec<Entity> = dots.iter(&app.world).collect();
+
+        app.world.run_system_once(respawn_three);
+        let second: Vec<Entity> = dots.iter(&app.world).collect();
+        assert_eq!(second.len(), 3);
+        assert_eq!(app.world.resource::<SimuConf>().number_of_dots, 3);
+        assert!(first.iter().all(|dot| app.world.get_entity(*dot).is_none()));
+        let dot_assets = app.world.resource::<DotAssets>();
+        for dot in second {
+            let mesh = app.world.get::<Mesh2dHandle>(dot).unwrap();
+            assert_eq!(mesh.0, dot_assets.mesh);
+            let material = app.world.get::<Handle<ColorMaterial>>(dot).unwrap();
+            assert_eq!(*material, dot_assets.material);
+        }
+    }
+
+    #[test]
+    fn reversing_negates_each_velocity() {
+        let mut world = World::new();
+        let velocities = [Vec2::new(1., 2.), Vec2::new(-3., 0.5), Vec2::ZERO];
+        let dots: Vec<Entity> = velocities
+            .iter()
+            .map(|&velocity| world.spawn((Dot, Velocity(velocity))).id())
+            .collect();
+        world.run_system_once(reverse_dots);
+        for (dot, velocity) in dots.into_iter().zip(velocities) {
+            assert_eq!(world.get::<Velocity>(dot).unwrap().0, -velocity);
+        }
+    }
+
+    #[test]
+    fn reversed_dots_retrace_their_path() {
+        let mut world = World::new();
+        world.init_resource::<SimuConf>();
+        let mut time = Time::<()>::default();
+        time.advance_by(Duration::from_millis(100));
+        world.insert_resource(time);
+        let starts = [Vec3::new(10., -20., 1.), Vec3::new(-5., 40., 1.)];
+        let velocities = [Vec2::new(30., 12.), Vec2::new(-8., -50.)];
+        let dots: Vec<Entity> = starts
+            .iter()
+            .zip(velocities)
+            .map(|(&start, velocity)| {
+                let dot = (Dot, Transform::from_translation(start), Velocity(velocity));
+                world.spawn(dot).id()
+            })
+            .collect();
+
+        for _ in 0..5 {
+            world.run_system_once(apply_dot_velocity);
+        }
+        for (&dot, start) in dots.iter().zip(starts) {
+            assert_ne!(world.get::<Transform>(dot).unwrap().translation, start);
+        }
+        world.run_system_once(reverse_dots);
+        for _ in 0..5 {
+            world.run_system_once(apply_dot_velocity);
+        }
+
+        assert!(world.resource::<SimuConf>().speed > 0.);
+        for ((dot, start), velocity) in dots.into_iter().zip(starts).zip(velocities) {
+            let position = world.get::<Transform>(dot).unwrap().translation;
+            assert!(
+                position.distance(start) < 1e-3,
+                "{position} isn't back at {start}"
+            );
+            assert_eq!(world.get::<Velocity>(dot).unwrap().0, -velocity);
+        }
+    }
+}
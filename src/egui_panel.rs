@@ -0,0 +1,192 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPlugin};
+
+use crate::actions::SimAction;
+use crate::controls::track_pointer_over_controls;
+use crate::dots::{spawn_random_dots, DotAssets, DotRng, MAX_DOT_SIZE_VARIANCE};
+use crate::hud::HudRoot;
+use crate::input::PointerOverUi;
+use crate::physics::WorldBounds;
+use crate::{ConnectionMode, SimuConf, ToneMapping};
+
+const SPAWN_COUNT: u32 = 100;
+const MAX_SPAWN_COUNT: u32 = 2_000;
+const MSAA_SAMPLE_COUNTS: [u32; 4] = [1, 2, 4, 8];
+
+// Side panel with a slider for the main settings of `SimuConf`, only built with
+// the `egui` feature
+pub(crate) struct EguiPanelPlugin;
+
+impl Plugin for EguiPanelPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+        // After the control bar, which also tells if the pointer is over it
+        app.add_systems(
+            Update,
+            settings_panel
+                .after(track_pointer_over_controls)
+                .run_if(hud_shown),
+        );
+    }
+}
+
+// The panel is hidden with the rest of the HUD
+fn hud_shown(root: Query<&Visibility, With<HudRoot>>) -> bool {
+    root.get_single()
+        .map_or(true, |visibility| *visibility != Visibility::Hidden)
+}
+
+fn settings_panel(
+    mut contexts: EguiContexts,
+    mut commands: Commands,
+    mut simu_conf: ResMut<SimuConf>,
+    mut pointer: ResMut<PointerOverUi>,
+    mut actions: EventWriter<SimAction>,
+    (dot_assets, bounds, mut rng): (Res<DotAssets>, Res<WorldBounds>, ResMut<DotRng>),
+    mut spawn_count: Local<Option<u32>>,
+) {
+    let Some(ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+    let spawn_count = spawn_count.get_or_insert(SPAWN_COUNT);
+
+    egui::SidePanel::left("settings_panel")
+        .resizable(false)
+        .show(ctx, |ui| {
+            egui::CollapsingHeader::new("Settings")
+                .default_open(true)
+                .show(ui, |ui| {
+                    ui.add(
+                        egui::Slider::new(&mut simu_conf.connect_force, 0.0..=1000.)
+                            .text("Connect force"),
+                    );
+                    ui.horizontal(|ui| {
+                        egui::ComboBox::from_label("Connections")
+                            .selected_text(format!("{:?}", simu_conf.connection_mode))
+                            .show_ui(ui, |ui| {
+                                for mode in ConnectionMode::ALL {
+                                    ui.selectable_value(
+                                        &mut simu_conf.connection_mode,
+                                        mode,
+                                        format!("{mode:?}"),
+                                    );
+                                }
+                            });
+                    });
+                    ui.add(
+                        egui::Slider::new(&mut simu_conf.max_connections_per_dot, 0..=20)
+                            .text("Max connections per dot"),
+                    );
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut simu_conf.fill_triangles, "Fill triangles");
+                        ui.add(
+                            egui::Slider::new(&mut simu_conf.triangle_alpha, 0.0..=1.)
+                                .text("Opacity"),
+                        );
+                    });
+                    if simu_conf.connection_mode == ConnectionMode::Nearest {
+                        ui.add(
+                            egui::Slider::new(&mut simu_conf.nearest_neighbours, 1..=12)
+                                .text("Nearest neighbours"),
+                        );
+                    }
+                    ui.add(egui::Slider::new(&mut simu_conf.speed, 0.0..=5.).text("Speed"));
+                    ui.add(egui::Slider::new(&mut simu_conf.dot_size, 1.0..=30.).text("Dot size"));
+                    ui.add(
+                        egui::Slider::new(
+                            &mut simu_conf.dot_size_variance,
+                            0.0..=MAX_DOT_SIZE_VARIANCE,
+                        )
+                        .text("Size variance"),
+                    );
+                    ui.add(
+                        egui::Slider::new(&mut simu_conf.dot_lifetime, 0.0..=60.)
+                            .text("Lifetime (s)"),
+                    );
+                    ui.add(egui::Slider::new(&mut simu_conf.damping, -0.5..=2.).text("Damping"));
+                    ui.add(
+                        egui::Slider::new(&mut simu_conf.restitution, 0.0..=1.5).text("Bounciness"),
+                    );
+
+                    // Kept apart so the velocity range is never empty
+                    let max_vel = simu_conf.max_vel;
+                    ui.add(
+                        egui::Slider::new(&mut simu_conf.min_vel, -2000.0..=max_vel - 1.)
+                            .text("Min velocity"),
+                    );
+                    let min_vel = simu_conf.min_vel;
+                    ui.add(
+                        egui::Slider::new(&mut simu_conf.max_vel, min_vel + 1.0..=2000.)
+                            .text("Max velocity"),
+                    );
+
+                    ui.checkbox(&mut simu_conf.freeze_dots, "Freeze");
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut simu_conf.three_d, "3D");
+                        ui.add(egui::Slider::new(&mut simu_conf.depth, 0.0..=2000.).text("Depth"));
+                    });
+                    ui.add(
+                        egui::Slider::new(&mut simu_conf.depth_fade, 0.0..=1.).text("Depth fade"),
+                    );
+
+                    ui.horizontal(|ui| {
+                        egui::ComboBox::from_label("MSAA")
+                            .selected_text(format!("{}x", simu_conf.msaa_samples))
+                            .show_ui(ui, |ui| {
+                                for samples in MSAA_SAMPLE_COUNTS {
+                                    ui.selectable_value(
+                                        &mut simu_conf.msaa_samples,
+                                        samples,
+                                        format!("{samples}x"),
+                                    );
+                                }
+                            });
+                        ui.checkbox(&mut simu_conf.fxaa, "FXAA");
+                    });
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut simu_conf.hdr, "HDR");
+                        egui::ComboBox::from_label("Tonemapping")
+                            .selected_text(format!("{:?}", simu_conf.tonemapping))
+                            .show_ui(ui, |ui| {
+                                for tonemapping in ToneMapping::ALL {
+                                    ui.selectable_value(
+                                        &mut simu_conf.tonemapping,
+                                        tonemapping,
+                                        format!("{tonemapping:?}"),
+                                    );
+                                }
+                            });
+                    });
+                    ui.add(egui::Slider::new(&mut simu_conf.exposure, -4.0..=4.).text("Exposure"));
+                    ui.add(egui::Slider::new(&mut simu_conf.vignette, 0.0..=1.).text("Vignette"));
+                    ui.add(
+                        egui::Slider::new(&mut simu_conf.chromatic_aberration, 0.0..=0.05)
+                            .text("Chromatic aberration"),
+                    );
+                    ui.add(
+                        egui::Slider::new(&mut simu_conf.film_grain, 0.0..=0.3).text("Film grain"),
+                    );
+
+                    ui.horizontal(|ui| {
+                        if ui.button("Spawn").clicked() {
+                            spawn_random_dots(
+                                &mut commands,
+                                &dot_assets,
+                                **bounds,
+                                &mut simu_conf,
+                                &mut rng,
+                                *spawn_count,
+                            );
+                        }
+                        ui.add(egui::Slider::new(spawn_count, 1..=MAX_SPAWN_COUNT).text("dots"));
+                    });
+                    if ui.button("Clear").clicked() {
+                        actions.send(SimAction::Clear);
+                    }
+                });
+        });
+
+    pointer.0 |= ctx.is_pointer_over_area() || ctx.wants_pointer_input();
+}
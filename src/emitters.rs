@@ -0,0 +1,125 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::actions::SimAction;
+use crate::dots::{random_scale, spawn_dot, DotAssets, DotRng, NewDot};
+use crate::theme::Theme;
+use crate::SimuConf;
+
+// A click closer than that to an emitter removes it
+const EMITTER_PICK_RADIUS: f32 = 20.; // In px
+const EMITTER_MARKER_RADIUS: f32 = 8.; // In px
+const EMITTER_MARKER_LENGTH: f32 = 20.; // In px, the line showing the direction
+
+// Keeps the period of the timer finite
+const MIN_EMITTER_RATE: f32 = 0.1; // In dots/s
+
+// Spawns dots on its own, placed and removed with Alt + the middle mouse button or the
+// emitter tool. The settings are the ones of `SimuConf` when it's placed. The emitters
+// aren't saved in the scene files.
+#[derive(Component)]
+pub(crate) struct Emitter {
+    timer: Timer,
+    direction: Vec2,
+    speed: f32,
+    // Half the angle of the cone the dots are shot in, in radians
+    spread: f32,
+}
+
+impl Emitter {
+    pub(crate) fn new(simu_conf: &SimuConf) -> Self {
+        let rate = simu_conf.emitter_rate.max(MIN_EMITTER_RATE);
+        Emitter {
+            timer: Timer::from_seconds(1. / rate, TimerMode::Repeating),
+            direction: Vec2::from_angle(simu_conf.emitter_angle.to_radians()),
+            speed: simu_conf.emitter_speed,
+            spread: simu_conf.emitter_spread.abs().to_radians() / 2.,
+        }
+    }
+
+    // Shoots the dots in every direction instead of a cone
+    pub(crate) fn all_around(self) -> Self {
+        Emitter {
+            spread: std::f32::consts::PI,
+            ..self
+        }
+    }
+}
+
+// Places an emitter at the position of the action, or removes the one there
+pub(crate) fn toggle_emitters(
+    mut actions: EventReader<SimAction>,
+    mut commands: Commands,
+    emitters: Query<(Entity, &Transform), With<Emitter>>,
+    simu_conf: Res<SimuConf>,
+) {
+    for action in actions.read() {
+        let SimAction::ToggleEmitter(position) = *action else {
+            continue;
+        };
+        let clicked = emitters.iter().find(|(_, transform)| {
+            transform.translation.truncate().distance(position) < EMITTER_PICK_RADIUS
+        });
+        match clicked {
+            Some((emitter, _)) => commands.entity(emitter).despawn(),
+            None => {
+                commands.spawn((
+                    Emitter::new(&simu_conf),
+                    SpatialBundle::from_transform(Transform::from_translation(position.extend(0.))),
+                ));
+            }
+        }
+    }
+}
+
+// Every emitter spawns its dots at its rate, in the simulation time: nothing while
+// frozen, faster with a higher speed
+pub(crate) fn emit_dots(
+    mut emitters: Query<(&Transform, &mut Emitter)>,
+    mut commands: Commands,
+    dot_assets: Res<DotAssets>,
+    mut simu_conf: ResMut<SimuConf>,
+    mut rng: ResMut<DotRng>,
+    time: Res<Time>,
+) {
+    if simu_conf.freeze_dots {
+        return;
+    }
+    let delta = time.delta().mul_f32(simu_conf.speed);
+
+    for (transform, mut emitter) in &mut emitters {
+        let count = emitter.timer.tick(delta).times_finished_this_tick();
+        if count == 0 {
+            continue;
+        }
+        for _ in 0..count {
+            let angle = rng.gen_range(-emitter.spread..=emitter.spread);
+            let dot = NewDot {
+                position: transform.translation.truncate(),
+                velocity: Vec2::from_angle(angle).rotate(emitter.direction) * emitter.speed,
+                age: 0.,
+                scale: random_scale(&mut **rng, &simu_conf),
+            };
+            spawn_dot(&mut commands, &dot_assets, &simu_conf, dot);
+        }
+        simu_conf.number_of_dots += count;
+    }
+}
+
+// Drawn like the wells, a ring with a line in the direction of the dots
+pub(crate) fn draw_emitters(
+    emitters: Query<(&Transform, &Emitter)>,
+    theme: Res<Theme>,
+    mut gizmos: Gizmos,
+) {
+    let color = theme.palette().spark;
+    for (transform, emitter) in &emitters {
+        let position = transform.translation.truncate();
+        gizmos.circle_2d(position, EMITTER_MARKER_RADIUS, color);
+        gizmos.line_2d(
+            position + emitter.direction * EMITTER_MARKER_RADIUS,
+            position + emitter.direction * EMITTER_MARKER_LENGTH,
+            color,
+        );
+    }
+}
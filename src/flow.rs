@@ -0,0 +1,139 @@
+use bevy::prelude::*;
+use std::f32::consts::TAU;
+
+use crate::camera::MainCamera;
+use crate::dots::{Dot, Velocity};
+use crate::theme::Theme;
+use crate::SimuConf;
+
+const FLOW_VECTOR_SPACING: f32 = 40.; // In px
+const FLOW_VECTOR_LENGTH: f32 = 16.; // In px
+
+// Middles of the edges of a cube, the gradients of the Perlin noise
+const GRADIENTS: [Vec3; 12] = [
+    Vec3::new(1., 1., 0.),
+    Vec3::new(-1., 1., 0.),
+    Vec3::new(1., -1., 0.),
+    Vec3::new(-1., -1., 0.),
+    Vec3::new(1., 0., 1.),
+    Vec3::new(-1., 0., 1.),
+    Vec3::new(1., 0., -1.),
+    Vec3::new(-1., 0., -1.),
+    Vec3::new(0., 1., 1.),
+    Vec3::new(0., -1., 1.),
+    Vec3::new(0., 1., -1.),
+    Vec3::new(0., -1., -1.),
+];
+
+// Draws the direction of the flow field on a grid, it's shown even when the field
+// doesn't move the dots
+#[derive(Resource, Default)]
+pub(crate) struct FlowFieldVectors {
+    pub(crate) enabled: bool,
+}
+
+// Gradient of a lattice point, picked by a hash of its coordinates instead of a
+// permutation table
+fn gradient(cell: IVec3) -> Vec3 {
+    let mut hash = (cell.x as u32).wrapping_mul(0x8da6_b343)
+        ^ (cell.y as u32).wrapping_mul(0xd816_3841)
+        ^ (cell.z as u32).wrapping_mul(0xcb1a_b31f);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0x5bd1_e995);
+    hash ^= hash >> 15;
+    GRADIENTS[hash as usize % GRADIENTS.len()]
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6. - 15.) + 10.)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+// Perlin noise, about between -1 and 1. The third coordinate is the time, so the
+// 2D field changes smoothly.
+fn perlin(point: Vec3) -> f32 {
+    let cell = point.floor();
+    let offset = point - cell;
+    let cell = cell.as_ivec3();
+    let corner = |x: i32, y: i32, z: i32| {
+        let corner = IVec3::new(x, y, z);
+        gradient(cell + corner).dot(offset - corner.as_vec3())
+    };
+    let (u, v, w) = (fade(offset.x), fade(offset.y), fade(offset.z));
+
+    let near = lerp(
+        lerp(corner(0, 0, 0), corner(1, 0, 0), u),
+        lerp(corner(0, 1, 0), corner(1, 1, 0), u),
+        v,
+    );
+    let far = lerp(
+        lerp(corner(0, 0, 1), corner(1, 0, 1), u),
+        lerp(corner(0, 1, 1), corner(1, 1, 1), u),
+        v,
+    );
+    lerp(near, far, w)
+}
+
+// Direction of the flow at this world position, a unit vector
+fn flow_direction(position: Vec2, time: f32, simu_conf: &SimuConf) -> Vec2 {
+    let point = (position / simu_conf.flow_scale).extend(time * simu_conf.flow_speed);
+    Vec2::from_angle(perlin(point) * TAU)
+}
+
+// Turns the dots toward the direction of the field at their position, their speed
+// is kept so the field doesn't add energy
+pub(crate) fn apply_flow_field(
+    mut query: Query<(&Transform, &mut Velocity), With<Dot>>,
+    time: Res<Time>,
+    simu_conf: Res<SimuConf>,
+) {
+    if simu_conf.freeze_dots {
+        return;
+    }
+    let dt = simu_conf.speed * time.delta_seconds();
+    let elapsed = time.elapsed_seconds();
+
+    for (transform, mut velocity) in &mut query {
+        let speed = velocity.length();
+        let direction = flow_direction(transform.translation.truncate(), elapsed, &simu_conf);
+        let steered = velocity.0 + direction * speed * simu_conf.flow_strength * dt;
+        velocity.0 = steered.normalize_or_zero() * speed;
+    }
+}
+
+pub(crate) fn toggle_flow_vectors(mut vectors: ResMut<FlowFieldVectors>) {
+    vectors.enabled = !vectors.enabled;
+}
+
+// An arrow every `FLOW_VECTOR_SPACING` px of the visible area
+pub(crate) fn draw_flow_vectors(
+    camera: Query<(&Transform, &OrthographicProjection), With<MainCamera>>,
+    time: Res<Time>,
+    simu_conf: Res<SimuConf>,
+    theme: Res<Theme>,
+    mut gizmos: Gizmos,
+) {
+    let (camera_transform, projection) = camera.single();
+    let center = camera_transform.translation.truncate();
+    let (min, max) = (center + projection.area.min, center + projection.area.max);
+    // Same spacing on screen whatever the zoom
+    let spacing = FLOW_VECTOR_SPACING * projection.scale;
+    let length = FLOW_VECTOR_LENGTH * projection.scale;
+    let color = theme.palette().hud.with_a(0.5);
+    let elapsed = time.elapsed_seconds();
+
+    let mut y = (min.y / spacing).ceil() * spacing;
+    while y < max.y {
+        let mut x = (min.x / spacing).ceil() * spacing;
+        while x < max.x {
+            let start = Vec2::new(x, y);
+            let direction = flow_direction(start, elapsed, &simu_conf);
+            gizmos.arrow_2d(start, start + direction * length, color);
+            x += spacing;
+        }
+        y += spacing;
+    }
+}
@@ -0,0 +1,132 @@
+use bevy::prelude::*;
+
+use crate::actions::SimAction;
+use crate::physics::WorldBounds;
+use crate::theme::Theme;
+
+const CURSOR_SPEED: f32 = 600.; // In px/s, with the stick fully tilted
+const CURSOR_RADIUS: f32 = 12.; // In px
+
+// Change of the connect force per frame, with a trigger fully pressed
+const FORCE_STEP: f32 = 4.;
+const SPEED_STEP: f32 = 0.04; // Per frame while a bumper is held
+
+// Stands for the mouse cursor on a gamepad: moved by the left stick, the dots are
+// spawned under it. Shown while a gamepad is connected.
+#[derive(Component)]
+pub(crate) struct GamepadCursor;
+
+pub(crate) fn spawn_gamepad_cursor(mut commands: Commands) {
+    commands.spawn((
+        GamepadCursor,
+        SpatialBundle {
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+    ));
+}
+
+pub(crate) fn move_gamepad_cursor(
+    gamepads: Res<Gamepads>,
+    axes: Res<Axis<GamepadAxis>>,
+    bounds: Res<WorldBounds>,
+    time: Res<Time>,
+    mut cursor: Query<(&mut Transform, &mut Visibility), With<GamepadCursor>>,
+) {
+    let (mut transform, mut visibility) = cursor.single_mut();
+    *visibility = if gamepads.iter().next().is_some() {
+        Visibility::Visible
+    } else {
+        Visibility::Hidden
+    };
+
+    for gamepad in gamepads.iter() {
+        let axis = |axis_type| axes.get(GamepadAxis::new(gamepad, axis_type)).unwrap_or(0.);
+        // Zero inside the dead zone
+        let stick = Vec2::new(
+            axis(GamepadAxisType::LeftStickX),
+            axis(GamepadAxisType::LeftStickY),
+        );
+        let position =
+            transform.translation.truncate() + stick * CURSOR_SPEED * time.delta_seconds();
+        transform.translation = position
+            .clamp(bounds.min, bounds.max)
+            .extend(transform.translation.z);
+    }
+}
+
+pub(crate) fn draw_gamepad_cursor(
+    cursor: Query<(&Transform, &Visibility), With<GamepadCursor>>,
+    theme: Res<Theme>,
+    mut gizmos: Gizmos,
+) {
+    let (transform, visibility) = cursor.single();
+    if *visibility == Visibility::Visible {
+        gizmos.circle_2d(
+            transform.translation.truncate(),
+            CURSOR_RADIUS,
+            theme.palette().hud,
+        );
+    }
+}
+
+// The triggers change the connect force (bottom ones) and the speed (top ones, the
+// bumpers), B pauses and X deletes every dot
+pub(crate) fn map_gamepad_input(
+    gamepads: Res<Gamepads>,
+    buttons: Res<ButtonInput<GamepadButton>>,
+    button_axes: Res<Axis<GamepadButton>>,
+    mut actions: EventWriter<SimAction>,
+) {
+    for gamepad in gamepads.iter() {
+        let button = |button_type| GamepadButton::new(gamepad, button_type);
+        // The bottom triggers are analog, pressing them harder changes the force faster
+        let trigger = |button_type| button_axes.get(button(button_type)).unwrap_or(0.);
+        let held = |button_type| buttons.pressed(button(button_type));
+
+        let force =
+            trigger(GamepadButtonType::RightTrigger2) - trigger(GamepadButtonType::LeftTrigger2);
+        if force != 0. {
+            actions.send(SimAction::ChangeConnectForce(force * FORCE_STEP));
+        }
+        if held(GamepadButtonType::RightTrigger) {
+            actions.send(SimAction::ChangeSpeed(SPEED_STEP));
+        }
+        if held(GamepadButtonType::LeftTrigger) {
+            actions.send(SimAction::ChangeSpeed(-SPEED_STEP));
+        }
+
+        if buttons.just_pressed(button(GamepadButtonType::East)) {
+            actions.send(SimAction::ToggleFreeze);
+        }
+        if buttons.just_pressed(button(GamepadButtonType::West)) {
+            actions.send(SimAction::Clear);
+        }
+    }
+}
+
+// Run while A is held, on a timer, like the left mouse button
+pub(crate) fn map_gamepad_spawn(
+    gamepads: Res<Gamepads>,
+    buttons: Res<ButtonInput<GamepadButton>>,
+    cursor: Query<&Transform, With<GamepadCursor>>,
+    mut actions: EventWriter<SimAction>,
+) {
+    let held = gamepads
+        .iter()
+        .any(|gamepad| buttons.pressed(GamepadButton::new(gamepad, GamepadButtonType::South)));
+    if held {
+        actions.send(SimAction::SpawnDot(cursor.single().translation.truncate()));
+    }
+}
+
+// Run condition of `quit`, Y on any gamepad
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn gamepad_quit_pressed(
+    gamepads: Res<Gamepads>,
+    buttons: Res<ButtonInput<GamepadButton>>,
+) -> bool {
+    gamepads
+        .iter()
+        .any(|gamepad| buttons.just_pressed(GamepadButton::new(gamepad, GamepadButtonType::North)))
+}
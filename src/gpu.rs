@@ -0,0 +1,446 @@
+use bevy::asset::load_internal_asset;
+use bevy::prelude::*;
+use bevy::render::render_graph::{self, RenderGraph, RenderLabel};
+use bevy::render::render_resource::binding_types::{
+    storage_buffer_read_only_sized, storage_buffer_sized, uniform_buffer_sized,
+};
+use bevy::render::render_resource::*;
+use bevy::render::renderer::{RenderContext, RenderDevice, RenderQueue};
+use bevy::render::{Extract, Render, RenderApp, RenderSet};
+use std::sync::{Arc, Mutex};
+
+use crate::broadphase::NeighbourSearch;
+use crate::connections::max_connection_radius;
+use crate::SimuConf;
+
+const GPU_CONNECTIONS_SHADER: Handle<Shader> =
+    Handle::weak_from_u128(0x6d1f_3c2a_9b84_4e07_a5d2_1c8e_f04b_7a93);
+const GPU_WORKGROUP_SIZE: u32 = 64;
+const GPU_MAX_EDGES: usize = 1 << 18;
+// The edges buffer starts with the edges count, padded to the alignment of a pair
+const GPU_EDGES_HEADER_SIZE: u64 = 8; // In bytes
+const GPU_EDGE_SIZE: u64 = 8; // In bytes
+
+// Edges copied for the read back on top of the count of the previous read back, so
+// a few more connections still fit
+const GPU_READBACK_MIN_EDGES: usize = 4096;
+
+// Close pairs found by the GPU, as indices in the positions of `NeighbourSearch`
+pub(crate) struct GpuEdgeList {
+    pub(crate) dot_count: usize,
+    pub(crate) pairs: Vec<[u32; 2]>,
+}
+
+// Latest edge list read back from the GPU, shared between the main and render
+// worlds. It is one to three frames late, `dot_count` tells if it still matches
+// the current dots.
+#[derive(Resource, Clone, Default)]
+pub(crate) struct GpuEdges(pub(crate) Arc<Mutex<Option<GpuEdgeList>>>);
+
+// Finds the connections with a compute shader when `SimuConf.gpu_connections` is
+// set. Without a GPU (e.g. headless), the edge list stays empty and `connect_dot`
+// keeps using the CPU.
+pub(crate) struct GpuConnectionsPlugin;
+
+impl Plugin for GpuConnectionsPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(
+            app,
+            GPU_CONNECTIONS_SHADER,
+            "gpu_connections.wgsl",
+            Shader::from_wgsl
+        );
+
+        let edges = GpuEdges::default();
+        app.insert_resource(edges.clone());
+
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .insert_resource(edges)
+            .add_systems(ExtractSchedule, extract_dot_positions)
+            .add_systems(
+                Render,
+                (
+                    prepare_gpu_connections.in_set(RenderSet::PrepareBindGroups),
+                    read_back_edges
+                        .after(RenderSet::Render)
+                        .before(RenderSet::Cleanup),
+                )
+                    .run_if(resource_exists::<GpuConnectionsPipeline>),
+            );
+
+        let mut render_graph = render_app.world.resource_mut::<RenderGraph>();
+        render_graph.add_node(GpuConnectionsLabel, GpuConnectionsNode);
+        render_graph.add_node_edge(GpuConnectionsLabel, bevy::render::graph::CameraDriverLabel);
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        // WebGL2 has no storage buffers, the CPU is used instead
+        let limits = render_app.world.resource::<RenderDevice>().limits();
+        if limits.max_storage_buffers_per_shader_stage < 2 {
+            info!("No storage buffers on this GPU, the connections are found on the CPU");
+            return;
+        }
+        render_app
+            .init_resource::<GpuConnectionsPipeline>()
+            .init_resource::<GpuReadback>();
+    }
+}
+
+#[derive(Resource)]
+struct ExtractedDotPositions {
+    positions: Vec<Vec2>,
+    radius: f32,
+}
+
+fn extract_dot_positions(
+    mut commands: Commands,
+    simu_conf: Extract<Res<SimuConf>>,
+    search: Extract<Res<NeighbourSearch>>,
+) {
+    if !simu_conf.gpu_connections || search.positions().is_empty() {
+        commands.remove_resource::<ExtractedDotPositions>();
+        return;
+    }
+    commands.insert_resource(ExtractedDotPositions {
+        positions: search.positions().to_vec(),
+        radius: max_connection_radius(&simu_conf).max(0.),
+    });
+}
+
+#[derive(Resource)]
+struct GpuConnectionsPipeline {
+    layout: BindGroupLayout,
+    pipeline: CachedComputePipelineId,
+}
+
+impl FromWorld for GpuConnectionsPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let layout = world.resource::<RenderDevice>().create_bind_group_layout(
+            "gpu_connections_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (
+                    storage_buffer_read_only_sized(false, None),
+                    storage_buffer_sized(false, None),
+                    uniform_buffer_sized(false, None),
+                ),
+            ),
+        );
+        let pipeline =
+            world
+                .resource::<PipelineCache>()
+                .queue_compute_pipeline(ComputePipelineDescriptor {
+                    label: Some("gpu_connections_pipeline".into()),
+                    layout: vec![layout.clone()],
+                    push_constant_ranges: Vec::new(),
+                    shader: GPU_CONNECTIONS_SHADER,
+                    shader_defs: Vec::new(),
+                    entry_point: "main".into(),
+                });
+        GpuConnectionsPipeline { layout, pipeline }
+    }
+}
+
+// Buffers of the current frame, only there while there are dots to connect
+#[derive(Resource)]
+struct GpuConnectionBuffers {
+    positions: Buffer,
+    edges: Buffer,
+    params: Buffer,
+    bind_group: BindGroup,
+    capacity: usize,
+    dot_count: usize,
+}
+
+// Mappable copies of the edges buffer, storage buffers can't be read from the CPU.
+// There are two so the edges of a frame are copied to one while the other is
+// still being mapped, nothing waits for the GPU.
+#[derive(Resource)]
+struct GpuReadback {
+    slots: [ReadbackSlot; 2],
+    // Edges found by the GPU in the latest read back, to size the next copy
+    last_count: usize,
+}
+
+struct ReadbackSlot {
+    buffer: Buffer,
+    state: ReadbackState,
+}
+
+type MapResult = Arc<Mutex<Option<Result<(), BufferAsyncError>>>>;
+
+enum ReadbackState {
+    Free,
+    // Filled by `GpuConnectionsNode` this frame, mapped once the frame is submitted
+    Copied {
+        dot_count: usize,
+        edges: usize,
+    },
+    Mapping {
+        dot_count: usize,
+        edges: usize,
+        result: MapResult,
+    },
+}
+
+impl FromWorld for GpuReadback {
+    fn from_world(world: &mut World) -> Self {
+        let device = world.resource::<RenderDevice>();
+        let slot = || ReadbackSlot {
+            buffer: device.create_buffer(&BufferDescriptor {
+                label: Some("gpu_connections_readback"),
+                size: edges_size(GPU_MAX_EDGES),
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            state: ReadbackState::Free,
+        };
+        GpuReadback {
+            slots: [slot(), slot()],
+            last_count: 0,
+        }
+    }
+}
+
+// Header and `edges` pairs, in bytes
+fn edges_size(edges: usize) -> u64 {
+    GPU_EDGES_HEADER_SIZE + GPU_EDGE_SIZE * edges as u64
+}
+
+#[allow(clippy::too_many_arguments)]
+fn prepare_gpu_connections(
+    mut commands: Commands,
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
+    pipeline: Res<GpuConnectionsPipeline>,
+    pipeline_cache: Res<PipelineCache>,
+    extracted: Option<Res<ExtractedDotPositions>>,
+    buffers: Option<ResMut<GpuConnectionBuffers>>,
+    mut readback: ResMut<GpuReadback>,
+) {
+    let Some(extracted) = extracted else {
+        commands.remove_resource::<GpuConnectionBuffers>();
+        return;
+    };
+    let dot_count = extracted.positions.len();
+
+    let mut buffers = match buffers {
+        Some(buffers) if buffers.capacity >= dot_count => buffers,
+        _ => {
+            // Grown by powers of two so adding dots one by one doesn't reallocate every frame
+            commands.insert_resource(create_buffers(
+                &device,
+                &pipeline,
+                dot_count.next_power_of_two(),
+            ));
+            return;
+        }
+    };
+
+    queue.write_buffer(
+        &buffers.positions,
+        0,
+        bytemuck::cast_slice(&extracted.positions),
+    );
+    let params = [
+        dot_count as u32,
+        GPU_MAX_EDGES as u32,
+        extracted.radius.to_bits(),
+        0,
+    ];
+    queue.write_buffer(&buffers.params, 0, bytemuck::cast_slice(&params));
+    queue.write_buffer(&buffers.edges, 0, &[0; GPU_EDGES_HEADER_SIZE as usize]);
+    buffers.dot_count = dot_count;
+
+    // Nothing is dispatched until the shader is compiled, or while both copies are
+    // still being read
+    if pipeline_cache
+        .get_compute_pipeline(pipeline.pipeline)
+        .is_none()
+    {
+        return;
+    }
+    let edges = (readback.last_count * 3 / 2 + GPU_READBACK_MIN_EDGES).min(GPU_MAX_EDGES);
+    if let Some(slot) = readback
+        .slots
+        .iter_mut()
+        .find(|slot| matches!(slot.state, ReadbackState::Free))
+    {
+        slot.state = ReadbackState::Copied { dot_count, edges };
+    }
+}
+
+fn create_buffers(
+    device: &RenderDevice,
+    pipeline: &GpuConnectionsPipeline,
+    capacity: usize,
+) -> GpuConnectionBuffers {
+    let positions = device.create_buffer(&BufferDescriptor {
+        label: Some("gpu_connections_positions"),
+        size: (capacity * std::mem::size_of::<Vec2>()) as u64,
+        usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let edges = device.create_buffer(&BufferDescriptor {
+        label: Some("gpu_connections_edges"),
+        size: edges_size(GPU_MAX_EDGES),
+        usage: BufferUsages::STORAGE | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+    let params = device.create_buffer(&BufferDescriptor {
+        label: Some("gpu_connections_params"),
+        size: 16,
+        usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let bind_group = device.create_bind_group(
+        "gpu_connections_bind_group",
+        &pipeline.layout,
+        &BindGroupEntries::sequential((
+            positions.as_entire_binding(),
+            edges.as_entire_binding(),
+            params.as_entire_binding(),
+        )),
+    );
+
+    GpuConnectionBuffers {
+        positions,
+        edges,
+        params,
+        bind_group,
+        capacity,
+        // Nothing is dispatched before the positions are written
+        dot_count: 0,
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+struct GpuConnectionsLabel;
+
+struct GpuConnectionsNode;
+
+impl render_graph::Node for GpuConnectionsNode {
+    fn run(
+        &self,
+        _graph: &mut render_graph::RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), render_graph::NodeRunError> {
+        let Some(buffers) = world.get_resource::<GpuConnectionBuffers>() else {
+            return Ok(());
+        };
+        let pipeline = world.resource::<GpuConnectionsPipeline>();
+        let Some(compute_pipeline) = world
+            .resource::<PipelineCache>()
+            .get_compute_pipeline(pipeline.pipeline)
+        else {
+            return Ok(());
+        };
+        // The slot picked by `prepare_gpu_connections`, none while both are in use
+        let readback = world.resource::<GpuReadback>();
+        let Some((slot, edges)) = readback.slots.iter().find_map(|slot| match slot.state {
+            ReadbackState::Copied { edges, .. } => Some((slot, edges)),
+            _ => None,
+        }) else {
+            return Ok(());
+        };
+        if buffers.dot_count == 0 {
+            return Ok(());
+        }
+
+        let encoder = render_context.command_encoder();
+        {
+            let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("gpu_connections_pass"),
+                ..default()
+            });
+            pass.set_pipeline(compute_pipeline);
+            pass.set_bind_group(0, &buffers.bind_group, &[]);
+            pass.dispatch_workgroups(
+                (buffers.dot_count as u32).div_ceil(GPU_WORKGROUP_SIZE),
+                1,
+                1,
+            );
+        }
+        // Only the edges likely to be there, not the whole buffer
+        encoder.copy_buffer_to_buffer(&buffers.edges, 0, &slot.buffer, 0, edges_size(edges));
+        Ok(())
+    }
+}
+
+// Maps the copy made this frame, and reads the one mapped since an earlier frame.
+// The GPU isn't waited for, a copy not mapped yet is read on a later frame.
+fn read_back_edges(
+    device: Res<RenderDevice>,
+    mut readback: ResMut<GpuReadback>,
+    edges: Res<GpuEdges>,
+) {
+    device.poll(Maintain::Poll);
+    let readback = &mut *readback;
+    for slot in &mut readback.slots {
+        match &slot.state {
+            ReadbackState::Free => {}
+            &ReadbackState::Copied { dot_count, edges } => {
+                let result = MapResult::default();
+                let callback_result = result.clone();
+                let slice = slot.buffer.slice(..edges_size(edges));
+                device.map_buffer(&slice, MapMode::Read, move |mapped| {
+                    *callback_result.lock().unwrap() = Some(mapped);
+                });
+                slot.state = ReadbackState::Mapping {
+                    dot_count,
+                    edges,
+                    result,
+                };
+            }
+            ReadbackState::Mapping {
+                dot_count,
+                edges: copied,
+                result,
+            } => {
+                let Some(mapped) = result.lock().unwrap().take() else {
+                    continue;
+                };
+                let dot_count = *dot_count;
+                let copied = *copied;
+                slot.state = ReadbackState::Free;
+                if let Err(err) = mapped {
+                    error!("Couldn't read back the GPU connections: {err}");
+                    continue;
+                }
+
+                let slice = slot.buffer.slice(..edges_size(copied));
+                let pairs = {
+                    let data = slice.get_mapped_range();
+                    let header = GPU_EDGES_HEADER_SIZE as usize;
+                    let found: u32 = bytemuck::pod_read_unaligned(&data[..4]);
+                    let found = found as usize;
+                    readback.last_count = found;
+                    if found > GPU_MAX_EDGES {
+                        warn!("The GPU found {found} connections, only {GPU_MAX_EDGES} are kept");
+                    }
+                    // More edges than copied, the next copy is sized for them and the
+                    // CPU is used until then
+                    if found > copied && copied < GPU_MAX_EDGES {
+                        None
+                    } else {
+                        let count = found.min(copied);
+                        let end = header + count * GPU_EDGE_SIZE as usize;
+                        Some(bytemuck::cast_slice::<u8, [u32; 2]>(&data[header..end]).to_vec())
+                    }
+                };
+                slot.buffer.unmap();
+
+                if let Some(pairs) = pairs {
+                    *edges.0.lock().unwrap() = Some(GpuEdgeList { dot_count, pairs });
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,39 @@
+// Finds the pairs of dots closer than `radius`, one invocation per dot
+
+struct Params {
+    count: u32,
+    max_edges: u32,
+    radius: f32,
+    _padding: u32,
+}
+
+struct Edges {
+    count: atomic<u32>,
+    _padding: u32,
+    pairs: array<vec2<u32>>,
+}
+
+@group(0) @binding(0) var<storage, read> positions: array<vec2<f32>>;
+@group(0) @binding(1) var<storage, read_write> edges: Edges;
+@group(0) @binding(2) var<uniform> params: Params;
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    if i >= params.count {
+        return;
+    }
+
+    let position = positions[i];
+    let max_dist_squared = params.radius * params.radius;
+    for (var j = i + 1u; j < params.count; j++) {
+        let delta = positions[j] - position;
+        if dot(delta, delta) < max_dist_squared {
+            // The count keeps growing past the capacity, so the CPU knows edges were lost
+            let slot = atomicAdd(&edges.count, 1u);
+            if slot < params.max_edges {
+                edges.pairs[slot] = vec2<u32>(i, j);
+            }
+        }
+    }
+}
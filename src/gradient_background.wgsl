@@ -0,0 +1,24 @@
+// Two color gradient whose direction slowly turns around the screen
+
+#import bevy_sprite::{
+    mesh2d_vertex_output::VertexOutput,
+    mesh2d_view_bindings::globals,
+}
+
+struct GradientMaterial {
+    color_a: vec4<f32>,
+    color_b: vec4<f32>,
+    // Time for a full turn, in s
+    period: f32,
+}
+
+@group(2) @binding(0) var<uniform> material: GradientMaterial;
+
+@fragment
+fn fragment(mesh: VertexOutput) -> @location(0) vec4<f32> {
+    let angle = globals.time * 6.2831853 / material.period;
+    let direction = vec2<f32>(cos(angle), sin(angle));
+    // The corners are at most sqrt(2) / 2 away from the center along `direction`
+    let t = dot(mesh.uv - vec2<f32>(0.5), direction) * 0.7071 + 0.5;
+    return mix(material.color_a, material.color_b, clamp(t, 0., 1.));
+}
@@ -0,0 +1,133 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+// Offsets of the neighbouring cells looked at from every cell. Only half of the
+// neighbourhood is needed, the other half sees the pair from the other cell.
+const NEIGHBOUR_CELLS: [IVec2; 4] = [
+    IVec2::new(1, 0),
+    IVec2::new(-1, 1),
+    IVec2::new(0, 1),
+    IVec2::new(1, 1),
+];
+
+// Uniform grid bucketing points by cell, so only points in the same or adjacent
+// cells are compared. Two points closer than `cell_size` are always in adjacent
+// cells.
+#[derive(Default)]
+pub(crate) struct SpatialGrid {
+    cell_size: f32,
+    cells: HashMap<IVec2, Vec<usize>>,
+    // Corners of the cells holding points
+    min_cell: IVec2,
+    max_cell: IVec2,
+}
+
+impl SpatialGrid {
+    fn cell_of(&self, position: Vec2) -> IVec2 {
+        (position / self.cell_size).floor().as_ivec2()
+    }
+
+    pub(crate) fn rebuild(&mut self, cell_size: f32, positions: &[Vec2]) {
+        self.cell_size = cell_size;
+
+        // Cells left empty last frame are dropped, the others keep their allocation
+        self.cells.retain(|_, cell| !cell.is_empty());
+        for cell in self.cells.values_mut() {
+            cell.clear();
+        }
+        self.min_cell = IVec2::MAX;
+        self.max_cell = IVec2::MIN;
+        for (i, &position) in positions.iter().enumerate() {
+            let cell = self.cell_of(position);
+            self.cells.entry(cell).or_default().push(i);
+            self.min_cell = self.min_cell.min(cell);
+            self.max_cell = self.max_cell.max(cell);
+        }
+    }
+
+    pub(crate) fn cell_count(&self) -> usize {
+        self.cells.values().filter(|cell| !cell.is_empty()).count()
+    }
+
+    // Calls `f` once for every pair of points lying in the same or adjacent cells.
+    // Returns the number of pairs.
+    pub(crate) fn for_each_pair(&self, mut f: impl FnMut(usize, usize)) -> usize {
+        let mut pairs = 0;
+        for (&cell, points) in &self.cells {
+            for (k, &i) in points.iter().enumerate() {
+                for &j in &points[k + 1..] {
+                    f(i, j);
+                }
+            }
+            pairs += points.len() * points.len().saturating_sub(1) / 2;
+
+            for offset in NEIGHBOUR_CELLS {
+                let Some(neighbours) = self.cells.get(&(cell + offset)) else {
+                    continue;
+                };
+                for &i in points {
+                    for &j in neighbours {
+                        f(i, j);
+                    }
+                }
+                pairs += points.len() * neighbours.len();
+            }
+        }
+        pairs
+    }
+
+    // The `count` points closest to the point `i` whatever their distance, with
+    // their distance and closest first. The rings of cells around it are searched
+    // until no point of the next ring can be closer than the farthest one found.
+    pub(crate) fn nearest(
+        &self,
+        positions: &[Vec2],
+        i: usize,
+        count: usize,
+        nearest: &mut Vec<(f32, usize)>,
+    ) {
+        nearest.clear();
+        if count == 0 {
+            return;
+        }
+        let position = positions[i];
+        let center = self.cell_of(position);
+        let last_ring = (center - self.min_cell)
+            .max(self.max_cell - center)
+            .max_element();
+        for ring in 0..=last_ring {
+            // The points of this ring are at least that far
+            let ring_distance = (ring - 1).max(0) as f32 * self.cell_size;
+            if nearest.len() == count && nearest[count - 1].0 <= ring_distance {
+                return;
+            }
+            for y in -ring..=ring {
+                // Only the first and last rows are full, the others have their ends
+                let step = if y.abs() == ring {
+                    1
+                } else {
+                    (2 * ring).max(1)
+                };
+                for x in (-ring..=ring).step_by(step as usize) {
+                    let Some(points) = self.cells.get(&(center + IVec2::new(x, y))) else {
+                        continue;
+                    };
+                    for &j in points {
+                        if j == i {
+                            continue;
+                        }
+                        let distance = position.distance(positions[j]);
+                        if nearest.len() == count {
+                            if distance >= nearest[count - 1].0 {
+                                continue;
+                            }
+                            nearest.pop();
+                        }
+                        let at = nearest.partition_point(|&(closer, _)| closer <= distance);
+                        nearest.insert(at, (distance, j));
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use bevy::prelude::*;
+
+use crate::hud::{hud_text, toggle_visibility, HudRoot};
+use crate::keys::{Control, KeyBindings, CONTROLS};
+use crate::theme::Theme;
+
+const HELP_PADDING: Val = Val::Px(12.0);
+const HELP_BACKGROUND: Color = Color::rgba(0., 0., 0., 0.75);
+const HELP_KEYS_WIDTH: f32 = 170.; // In px
+const HELP_ACTION_WIDTH: f32 = 360.; // In px
+
+// Full screen node centering the help panel
+#[derive(Component)]
+pub(crate) struct HelpOverlay;
+
+// Spawns the hidden help, with one row (keys, action) per control of `CONTROLS`
+// split in two columns
+pub(crate) fn spawn_help(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    bindings: Res<KeyBindings>,
+    root: Query<Entity, With<HudRoot>>,
+) {
+    let help = commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            HelpOverlay,
+        ))
+        .with_children(|overlay| {
+            overlay
+                .spawn(NodeBundle {
+                    style: Style {
+                        padding: UiRect::all(HELP_PADDING),
+                        column_gap: HELP_PADDING,
+                        ..default()
+                    },
+                    background_color: HELP_BACKGROUND.into(),
+                    ..default()
+                })
+                .with_children(|panel| {
+                    for controls in CONTROLS.chunks(CONTROLS.len().div_ceil(2)) {
+                        panel
+                            .spawn(NodeBundle {
+                                style: Style {
+                                    flex_direction: FlexDirection::Column,
+                                    ..default()
+                                },
+                                ..default()
+                            })
+                            .with_children(|column| {
+                                for control in controls {
+                                    spawn_help_row(column, &theme, &bindings, control);
+                                }
+                            });
+                    }
+                });
+        })
+        .id();
+    commands.entity(root.single()).add_child(help);
+}
+
+fn spawn_help_row(
+    parent: &mut ChildBuilder,
+    theme: &Theme,
+    bindings: &KeyBindings,
+    control: &Control,
+) {
+    parent.spawn(NodeBundle::default()).with_children(|row| {
+        let keys = control.keys_text(bindings);
+        for (value, width) in [
+            (&*keys, HELP_KEYS_WIDTH),
+            (control.action, HELP_ACTION_WIDTH),
+        ] {
+            let (text, marker) = hud_text(value, theme);
+            row.spawn((
+                text.with_style(Style {
+                    width: Val::Px(width),
+                    ..default()
+                }),
+                marker,
+            ));
+        }
+    });
+}
+
+pub(crate) fn toggle_help(mut query: Query<&mut Visibility, With<HelpOverlay>>) {
+    let mut visibility = query.single_mut();
+    toggle_visibility(&mut visibility);
+}
@@ -0,0 +1,689 @@
+use bevy::diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin};
+use bevy::prelude::*;
+use bevy::window::{PrimaryWindow, WindowMode};
+use std::collections::VecDeque;
+
+use crate::connections::{Connections, PerfStats};
+use crate::diagnostics::DotNetworkDiagnosticsPlugin;
+use crate::dots::{Dot, Velocity};
+use crate::keys::{Action, KeyBindings};
+use crate::physics::EnergyDiagnostic;
+use crate::theme::Theme;
+use crate::SimuConf;
+
+const INFO_TEXT_PADDING: Val = Val::Px(6.0);
+const INFO_TEXT_SIZE: f32 = 16.;
+// The FPS and the frame time are green above `FPS_GOOD`, red under `FPS_POOR`
+const FPS_GOOD: f64 = 55.;
+const FPS_POOR: f64 = 30.;
+const FPS_COLOR_GOOD: Color = Color::rgb(0.45, 0.9, 0.45);
+const FPS_COLOR_FAIR: Color = Color::rgb(1., 0.75, 0.3);
+const FPS_COLOR_POOR: Color = Color::rgb(1., 0.35, 0.3);
+
+pub(crate) const HISTOGRAM_BUCKETS: usize = 10;
+const HISTOGRAM_BAR_WIDTH: f32 = 180.; // In px
+const HISTOGRAM_BAR_HEIGHT: f32 = 10.; // In px
+const HISTOGRAM_LABEL_WIDTH: f32 = 80.; // In px
+pub(crate) const HISTOGRAM_BAR_ALPHA: f32 = 0.8;
+const HISTOGRAM_BACKGROUND: Color = Color::rgba(0., 0., 0., 0.5);
+
+// About 5 s at 60 FPS, one bar of 1 px per frame
+const GRAPH_FRAMES: usize = 300;
+const GRAPH_HEIGHT: f32 = 60.; // In px
+
+// Frame time at the top of the graph, in ms
+const GRAPH_MAX_FRAME_TIME: f32 = 50.;
+// Line marking the frame time of 60 FPS, in ms
+const GRAPH_TARGET_FRAME_TIME: f32 = 1000. / 60.;
+const GRAPH_TARGET_COLOR: Color = Color::rgba(1., 1., 1., 0.3);
+
+// Parent of every UI element, hidden to get a clean view
+#[derive(Component)]
+pub(crate) struct HudRoot;
+
+// The info text
+#[derive(Component)]
+pub(crate) struct InfoText;
+
+// Every text of the HUD, colored by the theme
+#[derive(Component)]
+pub(crate) struct HudText;
+
+#[derive(Component)]
+pub(crate) struct HistogramBar;
+
+// The performance HUD, at the top right of the screen
+#[derive(Component)]
+pub(crate) struct PerfHud;
+
+#[derive(Component)]
+pub(crate) struct PerfHudText;
+
+// Scrolling bars of the last frame times, the newest on the right
+#[derive(Component)]
+pub(crate) struct FrameTimeGraph {
+    bars: Vec<Entity>,
+    frame_times: VecDeque<f32>,
+}
+
+// Distances of the drawn connections, bucketed from 0 to `connect_force`
+#[derive(Resource, Default)]
+pub(crate) struct DistanceHistogram {
+    pub(crate) enabled: bool,
+    pub(crate) counts: [usize; HISTOGRAM_BUCKETS],
+    pub(crate) max_distance: f32,
+}
+
+// Histogram drawn with UI nodes, one row (range, bar, count) per bucket
+#[derive(Component)]
+pub(crate) struct HistogramWidget {
+    range_labels: Vec<Entity>,
+    bars: Vec<Entity>,
+    count_labels: Vec<Entity>,
+}
+
+#[derive(Component)]
+pub(crate) struct DistanceHistogramPanel;
+
+// Speeds of the dots, bucketed from 0 to the fastest spawn speed, or to the fastest
+// dot when one goes faster
+#[derive(Resource, Default)]
+pub(crate) struct SpeedHistogram {
+    pub(crate) enabled: bool,
+    counts: [usize; HISTOGRAM_BUCKETS],
+}
+
+#[derive(Component)]
+pub(crate) struct SpeedHistogramPanel;
+
+// Row at the bottom left of the screen holding the shown histograms
+#[derive(Component)]
+pub(crate) struct HistogramRow;
+
+fn update_histogram_widget(
+    widget: &HistogramWidget,
+    counts: &[usize],
+    bucket_size: f32,
+    styles: &mut Query<&mut Style>,
+    texts: &mut Query<&mut Text>,
+) {
+    let max_count = counts.iter().copied().max().unwrap_or(0).max(1);
+    for (i, &count) in counts.iter().enumerate() {
+        if let Ok(mut text) = texts.get_mut(widget.range_labels[i]) {
+            text.sections[0].value = format!(
+                "{:.0}-{:.0}",
+                bucket_size * i as f32,
+                bucket_size * (i + 1) as f32
+            );
+        }
+        if let Ok(mut style) = styles.get_mut(widget.bars[i]) {
+            style.width = Val::Px(HISTOGRAM_BAR_WIDTH * count as f32 / max_count as f32);
+        }
+        if let Ok(mut text) = texts.get_mut(widget.count_labels[i]) {
+            text.sections[0].value = count.to_string();
+        }
+    }
+}
+
+pub(crate) fn update_distance_histogram(
+    histogram: Res<DistanceHistogram>,
+    panel: Query<&HistogramWidget, With<DistanceHistogramPanel>>,
+    mut styles: Query<&mut Style>,
+    mut texts: Query<&mut Text>,
+) {
+    let bucket_size = histogram.max_distance / HISTOGRAM_BUCKETS as f32;
+    update_histogram_widget(
+        panel.single(),
+        &histogram.counts,
+        bucket_size,
+        &mut styles,
+        &mut texts,
+    );
+}
+
+// Appends a "label: value" field, the value being drawn in `value_color`
+fn push_field(
+    sections: &mut Vec<TextSection>,
+    style: &TextStyle,
+    label: &str,
+    value: String,
+    value_color: Color,
+) {
+    push_label(sections, style, &format!("{label}: "));
+    sections.push(TextSection::new(
+        value,
+        TextStyle {
+            color: value_color,
+            ..style.clone()
+        },
+    ));
+}
+
+fn push_label(sections: &mut Vec<TextSection>, style: &TextStyle, label: &str) {
+    let separator = if sections.is_empty() { "" } else { " | " };
+    sections.push(TextSection::new(
+        format!("{separator}{label}"),
+        style.clone(),
+    ));
+}
+
+fn fps_color(fps: f64) -> Color {
+    if fps >= FPS_GOOD {
+        FPS_COLOR_GOOD
+    } else if fps >= FPS_POOR {
+        FPS_COLOR_FAIR
+    } else {
+        FPS_COLOR_POOR
+    }
+}
+
+pub(crate) fn update_speed_histogram(
+    mut histogram: ResMut<SpeedHistogram>,
+    simu_conf: Res<SimuConf>,
+    dots: Query<&Velocity, With<Dot>>,
+    panel: Query<&HistogramWidget, With<SpeedHistogramPanel>>,
+    mut styles: Query<&mut Style>,
+    mut texts: Query<&mut Text>,
+) {
+    let max_speed = dots
+        .iter()
+        .map(|velocity| velocity.length())
+        .fold(simu_conf.max_spawn_speed(), f32::max);
+    let bucket_size = max_speed / HISTOGRAM_BUCKETS as f32;
+
+    histogram.counts = [0; HISTOGRAM_BUCKETS];
+    for velocity in &dots {
+        let bucket = (velocity.length() / bucket_size) as usize;
+        histogram.counts[bucket.min(HISTOGRAM_BUCKETS - 1)] += 1;
+    }
+
+    update_histogram_widget(
+        panel.single(),
+        &histogram.counts,
+        bucket_size,
+        &mut styles,
+        &mut texts,
+    );
+}
+
+pub(crate) fn toggle_speed_histogram(
+    mut histogram: ResMut<SpeedHistogram>,
+    mut panel: Query<&mut Style, With<SpeedHistogramPanel>>,
+) {
+    histogram.enabled = !histogram.enabled;
+    show_histogram(&mut panel.single_mut(), histogram.enabled);
+}
+
+// Hidden histograms take no room in `HistogramRow`, so the shown ones stay packed
+// to the left
+pub(crate) fn show_histogram(style: &mut Style, shown: bool) {
+    style.display = if shown { Display::Flex } else { Display::None };
+}
+
+pub(crate) fn update_info_text(
+    simu_conf: Res<SimuConf>,
+    stats: Res<PerfStats>,
+    energy: Res<EnergyDiagnostic>,
+    connections: Res<Connections>,
+    diagnostics: Res<DiagnosticsStore>,
+    (theme, bindings): (Res<Theme>, Res<KeyBindings>),
+    mut query: Query<&mut Text, With<InfoText>>,
+) {
+    let palette = theme.palette();
+    let hint = |label: &str, actions: &[Action]| format!("{label} ({})", bindings.hint(actions));
+    let style = TextStyle {
+        font_size: INFO_TEXT_SIZE,
+        color: palette.hud,
+        ..default()
+    };
+    let value_color = palette.line_near;
+    let smoothed = |diagnostic| {
+        diagnostics
+            .get(&diagnostic)
+            .and_then(|diagnostic| diagnostic.smoothed())
+            .unwrap_or(0.)
+    };
+    let fps = smoothed(FrameTimeDiagnosticsPlugin::FPS);
+    let frame_time = smoothed(FrameTimeDiagnosticsPlugin::FRAME_TIME);
+
+    let sections = &mut query.single_mut().sections;
+    sections.clear();
+    push_field(sections, &style, "FPS", format!("{fps:.0}"), fps_color(fps));
+    push_field(
+        sections,
+        &style,
+        "Frame",
+        format!("{frame_time:.1} ms"),
+        fps_color(fps),
+    );
+    push_field(
+        sections,
+        &style,
+        &format!("Dot (Click/{})", bindings.hint(&[Action::Clear])),
+        simu_conf.number_of_dots.to_string(),
+        value_color,
+    );
+    push_field(
+        sections,
+        &style,
+        "Connections",
+        connections.len().to_string(),
+        value_color,
+    );
+    push_field(
+        sections,
+        &style,
+        &hint(
+            "Connect Force",
+            &[Action::IncreaseForce, Action::DecreaseForce],
+        ),
+        format!("{:.0}", simu_conf.connect_force),
+        value_color,
+    );
+    push_field(
+        sections,
+        &style,
+        &hint(
+            "Alpha floor",
+            &[Action::IncreaseAlphaFloor, Action::DecreaseAlphaFloor],
+        ),
+        format!("{:.2}", simu_conf.min_alpha),
+        value_color,
+    );
+    push_field(
+        sections,
+        &style,
+        &hint("Speed", &[Action::IncreaseSpeed, Action::DecreaseSpeed]),
+        format!("{:.2}", simu_conf.speed),
+        value_color,
+    );
+    push_field(
+        sections,
+        &style,
+        &hint("Line width", &[Action::ThinLines, Action::ThickenLines]),
+        format!("{:.1}", simu_conf.max_line_width),
+        value_color,
+    );
+    push_field(
+        sections,
+        &style,
+        &hint("Links", &[Action::NextConnectionMode]),
+        format!("{:?}", simu_conf.connection_mode),
+        value_color,
+    );
+    push_field(
+        sections,
+        &style,
+        &hint("Edges", &[Action::NextBoundaryMode]),
+        format!("{:?}", simu_conf.boundary_mode),
+        value_color,
+    );
+    push_field(
+        sections,
+        &style,
+        &hint("Theme", &[Action::NextTheme]),
+        palette.name.to_string(),
+        value_color,
+    );
+    if stats.drawn < stats.candidates {
+        push_field(
+            sections,
+            &style,
+            "Drawing",
+            format!("{} of {}", stats.drawn, stats.candidates),
+            FPS_COLOR_FAIR,
+        );
+    }
+    if energy.enabled {
+        push_field(
+            sections,
+            &style,
+            &hint("Kinetic energy", &[Action::ToggleEnergy]),
+            format!("{:.0}", energy.kinetic),
+            value_color,
+        );
+    }
+    push_label(sections, &style, &hint("Reverse dots", &[Action::Reverse]));
+    push_label(sections, &style, &hint("Help", &[Action::ToggleHelp]));
+}
+
+// Shows or hides a UI element. Shown elements inherit the visibility of
+// `HudRoot`, so they are hidden with the whole HUD.
+pub(crate) fn toggle_visibility(visibility: &mut Visibility) {
+    *visibility = if *visibility == Visibility::Hidden {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+}
+
+pub(crate) fn toggle_hud(mut root: Query<&mut Visibility, With<HudRoot>>) {
+    toggle_visibility(&mut root.single_mut());
+}
+
+// Switches between a window and borderless fullscreen. The world bounds follow the
+// resize like for any other one.
+pub(crate) fn toggle_fullscreen(mut window: Query<&mut Window, With<PrimaryWindow>>) {
+    let Ok(mut window) = window.get_single_mut() else {
+        return;
+    };
+    window.mode = match window.mode {
+        WindowMode::Windowed => WindowMode::BorderlessFullscreen,
+        _ => WindowMode::Windowed,
+    };
+}
+
+pub(crate) fn hud_text(value: &str, theme: &Theme) -> (TextBundle, HudText) {
+    let text = TextBundle::from_section(
+        value,
+        TextStyle {
+            font_size: INFO_TEXT_SIZE,
+            color: theme.palette().hud,
+            ..default()
+        },
+    );
+    (text, HudText)
+}
+
+// Spawns an hidden histogram panel, to be put in `HistogramRow`
+fn spawn_histogram(
+    commands: &mut Commands,
+    theme: &Theme,
+    title: &str,
+    axis_label: &str,
+) -> Entity {
+    let mut range_labels = Vec::with_capacity(HISTOGRAM_BUCKETS);
+    let mut bars = Vec::with_capacity(HISTOGRAM_BUCKETS);
+    let mut count_labels = Vec::with_capacity(HISTOGRAM_BUCKETS);
+
+    let panel = commands
+        .spawn(NodeBundle {
+            style: Style {
+                display: Display::None,
+                padding: UiRect::all(INFO_TEXT_PADDING),
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            background_color: HISTOGRAM_BACKGROUND.into(),
+            ..default()
+        })
+        .with_children(|panel| {
+            panel.spawn(hud_text(title, theme));
+            panel
+                .spawn(NodeBundle {
+                    style: Style {
+                        justify_content: JustifyContent::SpaceBetween,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|header| {
+                    header.spawn(hud_text(axis_label, theme));
+                    header.spawn(hud_text("count", theme));
+                });
+
+            for _ in 0..HISTOGRAM_BUCKETS {
+                panel
+                    .spawn(NodeBundle {
+                        style: Style {
+                            align_items: AlignItems::Center,
+                            column_gap: INFO_TEXT_PADDING,
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|row| {
+                        let (label, marker) = hud_text("", theme);
+                        range_labels.push(
+                            row.spawn((
+                                label.with_style(Style {
+                                    width: Val::Px(HISTOGRAM_LABEL_WIDTH),
+                                    ..default()
+                                }),
+                                marker,
+                            ))
+                            .id(),
+                        );
+                        // The bar sits in a fixed width track so the counts stay aligned
+                        row.spawn(NodeBundle {
+                            style: Style {
+                                width: Val::Px(HISTOGRAM_BAR_WIDTH),
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|track| {
+                            bars.push(
+                                track
+                                    .spawn((
+                                        NodeBundle {
+                                            style: Style {
+                                                width: Val::Px(0.),
+                                                height: Val::Px(HISTOGRAM_BAR_HEIGHT),
+                                                ..default()
+                                            },
+                                            background_color: theme
+                                                .palette()
+                                                .line_near
+                                                .with_a(HISTOGRAM_BAR_ALPHA)
+                                                .into(),
+                                            ..default()
+                                        },
+                                        HistogramBar,
+                                    ))
+                                    .id(),
+                            );
+                        });
+                        count_labels.push(row.spawn(hud_text("0", theme)).id());
+                    });
+            }
+        })
+        .id();
+
+    commands.entity(panel).insert(HistogramWidget {
+        range_labels,
+        bars,
+        count_labels,
+    });
+    panel
+}
+
+pub(crate) fn update_perf_hud(
+    stats: Res<PerfStats>,
+    simu_conf: Res<SimuConf>,
+    diagnostics: Res<DiagnosticsStore>,
+    perf_hud: Query<&Visibility, With<PerfHud>>,
+    mut query: Query<&mut Text, With<PerfHudText>>,
+) {
+    if perf_hud.single() == Visibility::Hidden {
+        return;
+    }
+    let mut text = query.single_mut();
+
+    let smoothed = |path| {
+        diagnostics
+            .get(path)
+            .and_then(|diagnostic| diagnostic.smoothed())
+            .unwrap_or(0.)
+    };
+    let pacing = match (simu_conf.vsync, simu_conf.fps_cap > 0.) {
+        (true, false) => "VSync".to_string(),
+        (true, true) => format!("VSync, capped at {:.0} FPS", simu_conf.fps_cap),
+        (false, false) => "uncapped".to_string(),
+        (false, true) => format!("capped at {:.0} FPS", simu_conf.fps_cap),
+    };
+    text.sections[0].value = format!(
+        "FPS: {:.0} ({pacing}) | Broadphase (F5): {:?}, {} cells | Pair checks{}: {} | Connections drawn: {} / {}\n\
+         Neighbour search: {:.2} ms | Connections: {:.2} ms | Collisions: {:.2} ms",
+        smoothed(&FrameTimeDiagnosticsPlugin::FPS),
+        simu_conf.broadphase,
+        stats.cells,
+        if stats.on_gpu { " on GPU (F6)" } else { "" },
+        stats.pair_checks,
+        stats.drawn,
+        stats.candidates,
+        smoothed(&DotNetworkDiagnosticsPlugin::NEIGHBOUR_SEARCH_TIME),
+        smoothed(&DotNetworkDiagnosticsPlugin::CONNECTIONS_TIME),
+        smoothed(&DotNetworkDiagnosticsPlugin::COLLISIONS_TIME),
+    );
+}
+
+pub(crate) fn update_frame_time_graph(
+    time: Res<Time<Real>>,
+    perf_hud: Query<&Visibility, With<PerfHud>>,
+    mut graph: Query<&mut FrameTimeGraph>,
+    mut bars: Query<(&mut Style, &mut BackgroundColor)>,
+) {
+    let mut graph = graph.single_mut();
+    // Kept up to date while hidden, so the graph is full as soon as it is shown
+    if graph.frame_times.len() == GRAPH_FRAMES {
+        graph.frame_times.pop_front();
+    }
+    graph.frame_times.push_back(time.delta_seconds() * 1000.);
+    if perf_hud.single() == Visibility::Hidden {
+        return;
+    }
+
+    let empty = GRAPH_FRAMES - graph.frame_times.len();
+    for (i, &bar) in graph.bars.iter().enumerate() {
+        let Ok((mut style, mut color)) = bars.get_mut(bar) else {
+            continue;
+        };
+        let frame_time = i.checked_sub(empty).map_or(0., |i| graph.frame_times[i]);
+        style.height = Val::Percent((frame_time / GRAPH_MAX_FRAME_TIME).min(1.) * 100.);
+        color.0 = fps_color(1000. / frame_time.max(f32::EPSILON) as f64);
+    }
+}
+
+fn spawn_frame_time_graph(commands: &mut Commands) -> Entity {
+    let mut bars = Vec::with_capacity(GRAPH_FRAMES);
+    let graph = commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Px(GRAPH_FRAMES as f32),
+                height: Val::Px(GRAPH_HEIGHT),
+                align_items: AlignItems::FlexEnd,
+                ..default()
+            },
+            background_color: HISTOGRAM_BACKGROUND.into(),
+            ..default()
+        })
+        .with_children(|graph| {
+            graph.spawn(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Percent(GRAPH_TARGET_FRAME_TIME / GRAPH_MAX_FRAME_TIME * 100.),
+                    width: Val::Percent(100.),
+                    height: Val::Px(1.),
+                    ..default()
+                },
+                background_color: GRAPH_TARGET_COLOR.into(),
+                ..default()
+            });
+            for _ in 0..GRAPH_FRAMES {
+                bars.push(
+                    graph
+                        .spawn(NodeBundle {
+                            style: Style {
+                                width: Val::Px(1.),
+                                height: Val::Px(0.),
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .id(),
+                );
+            }
+        })
+        .id();
+    commands.entity(graph).insert(FrameTimeGraph {
+        bars,
+        frame_times: VecDeque::with_capacity(GRAPH_FRAMES),
+    });
+    graph
+}
+
+pub(crate) fn spawn_hud(mut commands: Commands, theme: Res<Theme>) {
+    let root = commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    ..default()
+                },
+                ..default()
+            },
+            HudRoot,
+        ))
+        .id();
+
+    let histogram = spawn_histogram(
+        &mut commands,
+        &theme,
+        "Connection distances",
+        "distance (px)",
+    );
+    commands.entity(histogram).insert(DistanceHistogramPanel);
+    let speed_histogram = spawn_histogram(&mut commands, &theme, "Dot speeds", "speed (px/s)");
+    commands.entity(speed_histogram).insert(SpeedHistogramPanel);
+    let histograms = commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: INFO_TEXT_PADDING,
+                    left: INFO_TEXT_PADDING,
+                    align_items: AlignItems::FlexEnd,
+                    column_gap: INFO_TEXT_PADDING,
+                    ..default()
+                },
+                ..default()
+            },
+            HistogramRow,
+        ))
+        .push_children(&[histogram, speed_histogram])
+        .id();
+
+    let (info_text, marker) = hud_text("info text", &theme);
+    let info_text = commands
+        .spawn((
+            InfoText,
+            info_text.with_style(Style {
+                position_type: PositionType::Absolute,
+                top: INFO_TEXT_PADDING,
+                left: INFO_TEXT_PADDING,
+                ..default()
+            }),
+            marker,
+        ))
+        .id();
+
+    let (perf_text, marker) = hud_text("", &theme);
+    let perf_text = commands.spawn((PerfHudText, perf_text, marker)).id();
+    let graph = spawn_frame_time_graph(&mut commands);
+    let perf_hud = commands
+        .spawn((
+            PerfHud,
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: INFO_TEXT_PADDING,
+                    right: INFO_TEXT_PADDING,
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::FlexEnd,
+                    row_gap: INFO_TEXT_PADDING,
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+        ))
+        .push_children(&[perf_text, graph])
+        .id();
+
+    commands
+        .entity(root)
+        .push_children(&[histograms, info_text, perf_hud]);
+}
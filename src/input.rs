@@ -0,0 +1,430 @@
+#[cfg(not(target_arch = "wasm32"))]
+use bevy::app::AppExit;
+use bevy::input::mouse::MouseWheel;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use crate::actions::SimAction;
+use crate::camera::{cursor_world_position, MainCamera};
+use crate::dots::{splittable_dot_at, Dot, DotScale, Mass};
+use crate::hud::{
+    show_histogram, toggle_visibility, DistanceHistogram, DistanceHistogramPanel, PerfHud,
+};
+use crate::inspector::BoxSelection;
+use crate::keys::{Action, KeyBindings};
+use crate::physics::EnergyDiagnostic;
+use crate::{Pattern, SimuConf};
+
+// Changes of the settings per frame while their key is held
+const FORCE_STEP: f32 = 2.;
+const ALPHA_FLOOR_STEP: f32 = 0.005;
+const SPEED_STEP: f32 = 0.04;
+const LINE_WIDTH_STEP: f32 = 0.05;
+const DAMPING_STEP: f32 = 0.01;
+const RESTITUTION_STEP: f32 = 0.01;
+// In s, a right button held longer only repels the dots
+const BURST_CLICK_DURATION: f32 = 0.25;
+// Per notch of the mouse wheel
+const ATTRACTOR_STRENGTH_STEP: f32 = 10_000.;
+const BRUSH_RADIUS_STEP: f32 = 5.;
+
+// Sent every frame while the key is held
+const HELD_ACTIONS: [(Action, SimAction); 12] = [
+    (
+        Action::IncreaseForce,
+        SimAction::ChangeConnectForce(FORCE_STEP),
+    ),
+    (
+        Action::DecreaseForce,
+        SimAction::ChangeConnectForce(-FORCE_STEP),
+    ),
+    (
+        Action::IncreaseAlphaFloor,
+        SimAction::ChangeAlphaFloor(ALPHA_FLOOR_STEP),
+    ),
+    (
+        Action::DecreaseAlphaFloor,
+        SimAction::ChangeAlphaFloor(-ALPHA_FLOOR_STEP),
+    ),
+    (Action::IncreaseSpeed, SimAction::ChangeSpeed(SPEED_STEP)),
+    (Action::DecreaseSpeed, SimAction::ChangeSpeed(-SPEED_STEP)),
+    (
+        Action::ThickenLines,
+        SimAction::ChangeLineWidth(LINE_WIDTH_STEP),
+    ),
+    (
+        Action::ThinLines,
+        SimAction::ChangeLineWidth(-LINE_WIDTH_STEP),
+    ),
+    (
+        Action::IncreaseDamping,
+        SimAction::ChangeDamping(DAMPING_STEP),
+    ),
+    (
+        Action::DecreaseDamping,
+        SimAction::ChangeDamping(-DAMPING_STEP),
+    ),
+    (
+        Action::IncreaseRestitution,
+        SimAction::ChangeRestitution(RESTITUTION_STEP),
+    ),
+    (
+        Action::DecreaseRestitution,
+        SimAction::ChangeRestitution(-RESTITUTION_STEP),
+    ),
+];
+
+// Sent once when the key is pressed
+const PRESSED_ACTIONS: [(Action, SimAction); 25] = [
+    (Action::ToggleFreeze, SimAction::ToggleFreeze),
+    (
+        Action::ToggleDensityAdaptive,
+        SimAction::ToggleDensityAdaptive,
+    ),
+    (Action::NextColorMode, SimAction::NextColorMode),
+    (Action::ToggleCollisions, SimAction::ToggleCollisions),
+    (Action::ToggleSparks, SimAction::ToggleSparks),
+    (Action::ToggleGradient, SimAction::ToggleGradient),
+    (Action::ToggleBloom, SimAction::ToggleBloom),
+    (Action::NextBroadphase, SimAction::NextBroadphase),
+    (Action::NextConnectionMode, SimAction::NextConnectionMode),
+    (Action::ToggleTriangles, SimAction::ToggleTriangles),
+    (Action::NextBoundaryMode, SimAction::NextBoundaryMode),
+    (Action::NextFramePacing, SimAction::NextFramePacing),
+    (Action::ToggleGpu, SimAction::ToggleGpu),
+    (Action::ToggleLinesOrder, SimAction::ToggleLinesOrder),
+    (Action::ToggleFlowField, SimAction::ToggleFlowField),
+    (Action::ToggleGravity, SimAction::ToggleGravity),
+    (Action::ToggleSprings, SimAction::ToggleSprings),
+    (Action::ToggleMerging, SimAction::ToggleMerging),
+    (Action::ToggleThreeD, SimAction::ToggleThreeD),
+    (Action::Reverse, SimAction::Reverse),
+    (Action::Randomize, SimAction::Randomize),
+    (Action::Clear, SimAction::Clear),
+    (Action::DeleteSelection, SimAction::DeleteSelection),
+    (Action::PinSelection, SimAction::PinSelection),
+    (Action::TogglePlayer, SimAction::TogglePlayer),
+];
+
+// Set while the pointer is over a settings panel, so clicking it doesn't spawn dots
+#[derive(Resource, Default)]
+pub(crate) struct PointerOverUi(pub(crate) bool);
+
+// The systems sending `SimAction` from the keyboard and the mouse, the systems
+// reading them run after
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct MapInput;
+
+pub(crate) fn map_keyboard_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut actions: EventWriter<SimAction>,
+) {
+    for (action, sim_action) in HELD_ACTIONS {
+        if bindings.pressed(&keyboard_input, action) {
+            actions.send(sim_action);
+        }
+    }
+    for (action, sim_action) in PRESSED_ACTIONS {
+        if bindings.just_pressed(&keyboard_input, action) {
+            actions.send(sim_action);
+        }
+    }
+
+    // With Ctrl, the same keys may move the camera
+    if keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        if bindings.just_pressed(&keyboard_input, Action::SaveScene) {
+            actions.send(SimAction::SaveScene);
+        }
+        if bindings.just_pressed(&keyboard_input, Action::LoadScene) {
+            actions.send(SimAction::LoadScene);
+        }
+    }
+}
+
+// Run while the left button is held with the spawn tool, on a timer. Nothing is
+// spawned on the large dots, a click splits them instead.
+pub(crate) fn map_mouse_spawn(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    dots: Query<(Entity, &Transform, &DotScale, &Mass), With<Dot>>,
+    simu_conf: Res<SimuConf>,
+    mut actions: EventWriter<SimAction>,
+) {
+    if let Some(cursor_pos) = cursor_world_position(&windows, &camera_query) {
+        if splittable_dot_at(cursor_pos, &dots, &simu_conf).is_none() {
+            actions.send(SimAction::SpawnDot(cursor_pos));
+        }
+    }
+}
+
+// Run when the left button is pressed with the spawn tool
+pub(crate) fn map_mouse_split(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    dots: Query<(Entity, &Transform, &DotScale, &Mass), With<Dot>>,
+    simu_conf: Res<SimuConf>,
+    mut actions: EventWriter<SimAction>,
+) {
+    if let Some(cursor_pos) = cursor_world_position(&windows, &camera_query) {
+        if splittable_dot_at(cursor_pos, &dots, &simu_conf).is_some() {
+            actions.send(SimAction::SplitDot(cursor_pos));
+        }
+    }
+}
+
+pub(crate) fn shift_pressed(keyboard_input: Res<ButtonInput<KeyCode>>) -> bool {
+    keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight])
+}
+
+// Run while Shift is held or a box is drawn, whatever the tool: the box goes from
+// where the left button was pressed to the cursor, its dots are selected when the
+// button is released
+pub(crate) fn map_mouse_box_select(
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut box_selection: ResMut<BoxSelection>,
+    mut actions: EventWriter<SimAction>,
+) {
+    let Some(cursor_pos) = cursor_world_position(&windows, &camera_query) else {
+        return;
+    };
+    if mouse_input.just_pressed(MouseButton::Left) {
+        box_selection.start = Some(cursor_pos);
+    }
+    let Some(start) = box_selection.start else {
+        return;
+    };
+    box_selection.end = cursor_pos;
+    if !mouse_input.pressed(MouseButton::Left) {
+        actions.send(SimAction::SelectBox(Rect::from_corners(start, cursor_pos)));
+        box_selection.start = None;
+    }
+}
+
+// Run when the left button is pressed with the pin tool
+pub(crate) fn map_mouse_pin(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut actions: EventWriter<SimAction>,
+) {
+    if let Some(cursor_pos) = cursor_world_position(&windows, &camera_query) {
+        actions.send(SimAction::TogglePin(cursor_pos));
+    }
+}
+
+// Run when the left button is pressed with the emitter tool
+pub(crate) fn map_mouse_emitter(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut actions: EventWriter<SimAction>,
+) {
+    if let Some(cursor_pos) = cursor_world_position(&windows, &camera_query) {
+        actions.send(SimAction::ToggleEmitter(cursor_pos));
+    }
+}
+
+// Run when the left button is pressed with the grab tool: the dot under the cursor
+// is grabbed and selected
+pub(crate) fn map_mouse_grab(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut actions: EventWriter<SimAction>,
+) {
+    if let Some(cursor_pos) = cursor_world_position(&windows, &camera_query) {
+        actions.send(SimAction::Select(cursor_pos));
+        actions.send(SimAction::Grab(cursor_pos));
+    }
+}
+
+// Run while a dot is grabbed: it follows the cursor until the left button is
+// released
+pub(crate) fn map_mouse_drag(
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut actions: EventWriter<SimAction>,
+) {
+    if !mouse_input.pressed(MouseButton::Left) {
+        actions.send(SimAction::Release);
+    } else if let Some(cursor_pos) = cursor_world_position(&windows, &camera_query) {
+        actions.send(SimAction::Drag(cursor_pos));
+    }
+}
+
+// Run while the attract key is held, or the left button with the attract tool: the
+// dots follow the cursor, Shift repels them
+pub(crate) fn map_mouse_attractor(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut actions: EventWriter<SimAction>,
+) {
+    if let Some(target) = cursor_world_position(&windows, &camera_query) {
+        actions.send(SimAction::Attract {
+            target,
+            repel: keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]),
+        });
+    }
+}
+
+// The wheel changes the strength of the attractor while its key is held, else the
+// radius of the brush with Ctrl, or with Shift the number of dots it places. It
+// zooms without them (see `zoom_camera`).
+pub(crate) fn map_mouse_wheel(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut wheel_events: EventReader<MouseWheel>,
+    mut actions: EventWriter<SimAction>,
+) {
+    let attracting = bindings.pressed(&keyboard_input, Action::Attract);
+    let shift = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let ctrl = keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    for event in wheel_events.read().filter(|event| event.y != 0.) {
+        if attracting {
+            actions.send(SimAction::ChangeAttractorStrength(
+                event.y.signum() * ATTRACTOR_STRENGTH_STEP,
+            ));
+        } else if shift {
+            actions.send(SimAction::ChangeBrushCount(event.y.signum() as i32));
+        } else if ctrl {
+            actions.send(SimAction::ChangeBrushRadius(
+                event.y.signum() * BRUSH_RADIUS_STEP,
+            ));
+        }
+    }
+}
+
+// Run while the right button is held
+pub(crate) fn map_mouse_repulsion(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut actions: EventWriter<SimAction>,
+) {
+    if let Some(cursor_pos) = cursor_world_position(&windows, &camera_query) {
+        actions.send(SimAction::Repulse(cursor_pos));
+    }
+}
+
+// The pattern keys spawn their pattern on the cursor, turning with Shift
+pub(crate) fn map_pattern_keys(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut actions: EventWriter<SimAction>,
+) {
+    let Some(position) = cursor_world_position(&windows, &camera_query) else {
+        return;
+    };
+    let spin = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    for (action, pattern) in [
+        (Action::SpawnGrid, Pattern::Grid),
+        (Action::SpawnRing, Pattern::Ring),
+        (Action::SpawnSpiral, Pattern::Spiral),
+    ] {
+        if bindings.just_pressed(&keyboard_input, action) {
+            actions.send(SimAction::SpawnPattern(pattern, position, spin));
+        }
+    }
+}
+
+// Run when the right button is released. Only a short click sends a burst, holding
+// the button repels the dots instead.
+pub(crate) fn map_mouse_burst(
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    time: Res<Time>,
+    mut pressed_at: Local<f32>,
+    mut actions: EventWriter<SimAction>,
+) {
+    if mouse_input.just_pressed(MouseButton::Right) {
+        *pressed_at = time.elapsed_seconds();
+    }
+    if !mouse_input.just_released(MouseButton::Right)
+        || time.elapsed_seconds() - *pressed_at > BURST_CLICK_DURATION
+    {
+        return;
+    }
+    if let Some(cursor_pos) = cursor_world_position(&windows, &camera_query) {
+        actions.send(SimAction::Burst(cursor_pos));
+    }
+}
+
+// Run when the middle button is released without dragging the view: a well, a
+// vortex with Shift (counterclockwise) or Ctrl (clockwise), or an emitter with Alt
+pub(crate) fn map_mouse_well(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut actions: EventWriter<SimAction>,
+) {
+    let Some(position) = cursor_world_position(&windows, &camera_query) else {
+        return;
+    };
+    let shift = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let ctrl = keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let alt = keyboard_input.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]);
+    actions.send(if alt {
+        SimAction::ToggleEmitter(position)
+    } else if shift || ctrl {
+        SimAction::ToggleVortex {
+            position,
+            clockwise: ctrl,
+        }
+    } else {
+        SimAction::ToggleWell(position)
+    });
+}
+
+// Run while the herd key is held
+pub(crate) fn map_mouse_herd(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut actions: EventWriter<SimAction>,
+) {
+    if let Some(cursor_pos) = cursor_world_position(&windows, &camera_query) {
+        actions.send(SimAction::Herd(cursor_pos));
+    }
+}
+
+// Run while the eraser key is held, or the left button with the erase tool
+pub(crate) fn map_mouse_eraser(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut actions: EventWriter<SimAction>,
+) {
+    if let Some(cursor_pos) = cursor_world_position(&windows, &camera_query) {
+        actions.send(SimAction::Erase(cursor_pos));
+    }
+}
+
+// The panels of the HUD, they aren't simulation actions
+pub(crate) fn handle_hud_keys(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut energy: ResMut<EnergyDiagnostic>,
+    mut histogram: ResMut<DistanceHistogram>,
+    mut histogram_panel: Query<&mut Style, With<DistanceHistogramPanel>>,
+    mut perf_hud: Query<&mut Visibility, With<PerfHud>>,
+) {
+    if bindings.just_pressed(&keyboard_input, Action::TogglePerfHud) {
+        toggle_visibility(&mut perf_hud.single_mut());
+    }
+
+    if bindings.just_pressed(&keyboard_input, Action::ToggleEnergy) {
+        energy.enabled = !energy.enabled;
+    }
+
+    if bindings.just_pressed(&keyboard_input, Action::ToggleHistogram) {
+        histogram.enabled = !histogram.enabled;
+        show_histogram(&mut histogram_panel.single_mut(), histogram.enabled);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn quit(mut writer: EventWriter<AppExit>) {
+    writer.send(AppExit);
+}
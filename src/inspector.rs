@@ -0,0 +1,262 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use crate::actions::SimAction;
+use crate::broadphase::NeighbourSearch;
+use crate::camera::{cursor_world_position, MainCamera};
+use crate::dots::{dot_at, Age, Degree, Dot, DotScale, Pinned, Velocity};
+use crate::hud::{hud_text, HudRoot};
+use crate::input::PointerOverUi;
+use crate::keys::{Action, KeyBindings};
+use crate::theme::Theme;
+use crate::SimuConf;
+
+const INSPECTOR_PADDING: Val = Val::Px(6.0);
+const INSPECTOR_BACKGROUND: Color = Color::rgba(0., 0., 0., 0.5);
+const SELECTION_MARKER_GAP: f32 = 6.; // In px, between the dot and its ring
+
+// The cursor has to rest that long before a dot is highlighted, in s
+const HOVER_IDLE_DELAY: f32 = 0.3;
+// Farthest a highlighted dot can be from the cursor, in px
+const HOVER_PICK_RADIUS: f32 = 60.;
+
+// The dot shown by the inspector, selected by clicking it. Several dots are
+// selected with a box, they can then be deleted or pinned together.
+#[derive(Component)]
+pub(crate) struct Selected;
+
+// Rectangle drawn with Shift and the left button, in world coordinates. The dots
+// inside are selected once the button is released.
+#[derive(Resource, Default)]
+pub(crate) struct BoxSelection {
+    pub(crate) start: Option<Vec2>,
+    pub(crate) end: Vec2,
+}
+
+// Dot closest to the resting cursor, as an index in the positions of
+// `NeighbourSearch`. Only its connections are drawn bright, the others are dimmed.
+#[derive(Resource, Default)]
+pub(crate) struct HoveredDot(pub(crate) Option<usize>);
+
+// Panel at the bottom right of the screen, shown while a dot is selected
+#[derive(Component)]
+pub(crate) struct InspectorPanel;
+
+#[derive(Component)]
+pub(crate) struct InspectorText;
+
+pub(crate) fn spawn_inspector(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    root: Query<Entity, With<HudRoot>>,
+) {
+    let (text, marker) = hud_text("", &theme);
+    let text = commands.spawn((InspectorText, text, marker)).id();
+    let panel = commands
+        .spawn((
+            InspectorPanel,
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: INSPECTOR_PADDING,
+                    right: INSPECTOR_PADDING,
+                    padding: UiRect::all(INSPECTOR_PADDING),
+                    ..default()
+                },
+                background_color: INSPECTOR_BACKGROUND.into(),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+        ))
+        .add_child(text)
+        .id();
+    commands.entity(root.single()).add_child(panel);
+}
+
+// Selects the dot at the position of the action, a click next to the dots clears
+// the selection
+pub(crate) fn select_dots(
+    mut actions: EventReader<SimAction>,
+    mut commands: Commands,
+    dots: Query<(Entity, &Transform, &DotScale), With<Dot>>,
+    selected: Query<Entity, With<Selected>>,
+    simu_conf: Res<SimuConf>,
+) {
+    for action in actions.read() {
+        let SimAction::Select(position) = *action else {
+            continue;
+        };
+        for dot in &selected {
+            commands.entity(dot).remove::<Selected>();
+        }
+        if let Some(dot) = dot_at(position, &dots, &simu_conf) {
+            commands.entity(dot).insert(Selected);
+        }
+    }
+}
+
+// Selects the dots inside the box of the action instead
+pub(crate) fn select_box(
+    mut actions: EventReader<SimAction>,
+    mut commands: Commands,
+    dots: Query<(Entity, &Transform), With<Dot>>,
+    selected: Query<Entity, With<Selected>>,
+) {
+    for action in actions.read() {
+        let SimAction::SelectBox(area) = *action else {
+            continue;
+        };
+        for dot in &selected {
+            commands.entity(dot).remove::<Selected>();
+        }
+        for (dot, transform) in &dots {
+            if area.contains(transform.translation.truncate()) {
+                commands.entity(dot).insert(Selected);
+            }
+        }
+    }
+}
+
+pub(crate) fn delete_selection(
+    selected: Query<Entity, (With<Selected>, With<Dot>)>,
+    mut commands: Commands,
+    mut simu_conf: ResMut<SimuConf>,
+) {
+    for dot in &selected {
+        commands.entity(dot).despawn();
+        simu_conf.number_of_dots -= 1;
+    }
+}
+
+// Pins every selected dot, or unpins them when they all are
+pub(crate) fn pin_selection(
+    selected: Query<(Entity, Has<Pinned>), With<Selected>>,
+    mut commands: Commands,
+) {
+    let all_pinned = selected.iter().all(|(_, pinned)| pinned);
+    for (dot, _) in &selected {
+        if all_pinned {
+            commands.entity(dot).remove::<Pinned>();
+        } else {
+            commands.entity(dot).insert(Pinned);
+        }
+    }
+}
+
+// Kept up to date every frame, the panel is hidden once the dots are deleted. A box
+// of several dots only shows how many there are.
+pub(crate) fn update_inspector(
+    selected: Query<(&Transform, &Velocity, &Degree, &Age), With<Selected>>,
+    bindings: Res<KeyBindings>,
+    mut panel: Query<&mut Visibility, With<InspectorPanel>>,
+    mut text: Query<&mut Text, With<InspectorText>>,
+) {
+    let mut visibility = panel.single_mut();
+    let mut text = text.single_mut();
+    let count = selected.iter().count();
+    if count == 0 {
+        *visibility = Visibility::Hidden;
+        return;
+    }
+    *visibility = Visibility::Inherited;
+    let Ok((transform, velocity, degree, age)) = selected.get_single() else {
+        text.sections[0].value = format!(
+            "{count} dots selected\nDelete ({}) | Pin ({})",
+            bindings.hint(&[Action::DeleteSelection]),
+            bindings.hint(&[Action::PinSelection]),
+        );
+        return;
+    };
+    let position = transform.translation;
+    text.sections[0].value = format!(
+        "Position: {:.0}, {:.0}\nVelocity: {:.0}, {:.0}\nSpeed: {:.0} px/s\nConnections: {}\nAge: {:.1} s\nFollow ({})",
+        position.x,
+        position.y,
+        velocity.x,
+        velocity.y,
+        velocity.length(),
+        **degree,
+        **age,
+        bindings.hint(&[Action::FollowSelection]),
+    );
+}
+
+// A ring around the selected dots and the box being drawn, in the color of the
+// sparks
+pub(crate) fn draw_selection(
+    selected: Query<(&Transform, &DotScale), With<Selected>>,
+    box_selection: Res<BoxSelection>,
+    simu_conf: Res<SimuConf>,
+    theme: Res<Theme>,
+    mut gizmos: Gizmos,
+) {
+    if let Some(start) = box_selection.start {
+        let area = Rect::from_corners(start, box_selection.end);
+        gizmos.rect_2d(area.center(), 0., area.size(), theme.palette().spark);
+    }
+    for (transform, scale) in &selected {
+        gizmos.circle_2d(
+            transform.translation.truncate(),
+            simu_conf.dot_size * **scale + SELECTION_MARKER_GAP,
+            theme.palette().spark,
+        );
+    }
+}
+
+// Run once the neighbour search is rebuilt. Moving the cursor or pressing a button
+// clears the highlight until the cursor rests again.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn hover_dots(
+    mut cursor_moved: EventReader<CursorMoved>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    search: Res<NeighbourSearch>,
+    pointer: Res<PointerOverUi>,
+    simu_conf: Res<SimuConf>,
+    time: Res<Time>,
+    mut idle: Local<f32>,
+    mut hovered: ResMut<HoveredDot>,
+) {
+    *idle += time.delta_seconds();
+    if cursor_moved.read().count() > 0 || mouse_input.get_pressed().len() > 0 {
+        *idle = 0.;
+    }
+    let cursor = cursor_world_position(&windows, &camera_query)
+        .filter(|_| simu_conf.hover_highlight && *idle >= HOVER_IDLE_DELAY && !pointer.0);
+    let dot = cursor.and_then(|cursor| {
+        search
+            .positions()
+            .iter()
+            .enumerate()
+            .map(|(i, position)| (i, position.distance(cursor)))
+            .filter(|&(_, distance)| distance < HOVER_PICK_RADIUS)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(i, _)| i)
+    });
+    if hovered.0 != dot {
+        hovered.0 = dot;
+    }
+}
+
+// A ring around the highlighted dot, in the color of the closest connections
+pub(crate) fn draw_hovered_dot(
+    hovered: Res<HoveredDot>,
+    search: Res<NeighbourSearch>,
+    dots: Query<&DotScale>,
+    simu_conf: Res<SimuConf>,
+    theme: Res<Theme>,
+    mut gizmos: Gizmos,
+) {
+    let Some(index) = hovered.0 else {
+        return;
+    };
+    let Ok(scale) = dots.get(search.entities()[index]) else {
+        return;
+    };
+    gizmos.circle_2d(
+        search.positions()[index],
+        simu_conf.dot_size * **scale + SELECTION_MARKER_GAP,
+        theme.palette().line_near,
+    );
+}
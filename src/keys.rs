@@ -0,0 +1,548 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+
+// What a key does, the keys themselves are in `KeyBindings`
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum Action {
+    IncreaseForce,
+    DecreaseForce,
+    ToggleDensityAdaptive,
+    NextConnectionMode,
+    ToggleTriangles,
+    IncreaseAlphaFloor,
+    DecreaseAlphaFloor,
+    ThickenLines,
+    ThinLines,
+    IncreaseSpeed,
+    DecreaseSpeed,
+    IncreaseDamping,
+    DecreaseDamping,
+    IncreaseRestitution,
+    DecreaseRestitution,
+    ToggleFreeze,
+    ToggleLinesOrder,
+    NextColorMode,
+    NextTheme,
+    ToggleGradient,
+    ToggleBloom,
+    ToggleCollisions,
+    ToggleSparks,
+    ToggleFlowField,
+    ToggleGravity,
+    ToggleSprings,
+    ToggleMerging,
+    ToggleThreeD,
+    ToggleControlBar,
+    ToggleHelp,
+    TogglePerfHud,
+    ToggleEnergy,
+    ToggleHistogram,
+    NextBroadphase,
+    NextBoundaryMode,
+    ToggleBounds,
+    ToggleGpu,
+    ToggleSpeedHistogram,
+    ToggleFlowVectors,
+    ToggleHud,
+    ToggleFullscreen,
+    ToggleDiagnosticsWindow,
+    NextFramePacing,
+    Reverse,
+    MoveUp,
+    MoveLeft,
+    MoveDown,
+    MoveRight,
+    ZoomIn,
+    ZoomOut,
+    Attract,
+    Herd,
+    Erase,
+    Clear,
+    Randomize,
+    DeleteSelection,
+    PinSelection,
+    SpawnGrid,
+    SpawnRing,
+    SpawnSpiral,
+    TogglePlayer,
+    FollowSelection,
+    // With Ctrl
+    SaveScene,
+    LoadScene,
+    Quit,
+}
+
+const DEFAULT_BINDINGS: &[(Action, &[KeyCode])] = &[
+    (Action::IncreaseForce, &[KeyCode::KeyI]),
+    (Action::DecreaseForce, &[KeyCode::KeyK]),
+    (Action::ToggleDensityAdaptive, &[KeyCode::KeyM]),
+    (
+        Action::NextConnectionMode,
+        &[KeyCode::Digit0, KeyCode::NumpadMultiply],
+    ),
+    (
+        Action::ToggleTriangles,
+        &[KeyCode::IntlBackslash, KeyCode::NumpadDivide],
+    ),
+    (Action::IncreaseAlphaFloor, &[KeyCode::KeyY]),
+    (Action::DecreaseAlphaFloor, &[KeyCode::KeyH]),
+    (Action::ThickenLines, &[KeyCode::BracketRight]),
+    (Action::ThinLines, &[KeyCode::BracketLeft]),
+    (Action::IncreaseSpeed, &[KeyCode::KeyU]),
+    (Action::DecreaseSpeed, &[KeyCode::KeyJ]),
+    (Action::IncreaseDamping, &[KeyCode::Period]),
+    (Action::DecreaseDamping, &[KeyCode::Comma]),
+    (Action::IncreaseRestitution, &[KeyCode::PageUp]),
+    (Action::DecreaseRestitution, &[KeyCode::PageDown]),
+    (Action::ToggleFreeze, &[KeyCode::KeyP]),
+    (Action::ToggleLinesOrder, &[KeyCode::KeyL]),
+    (Action::NextColorMode, &[KeyCode::KeyT]),
+    (Action::NextTheme, &[KeyCode::KeyQ]),
+    (Action::ToggleGradient, &[KeyCode::KeyZ]),
+    (Action::ToggleBloom, &[KeyCode::KeyV]),
+    (Action::ToggleCollisions, &[KeyCode::KeyC]),
+    (Action::ToggleSparks, &[KeyCode::KeyX]),
+    (Action::ToggleFlowField, &[KeyCode::Backspace]),
+    (Action::ToggleGravity, &[KeyCode::F12]),
+    (Action::ToggleSprings, &[KeyCode::Semicolon]),
+    (Action::ToggleMerging, &[KeyCode::Slash]),
+    (
+        Action::ToggleThreeD,
+        &[KeyCode::ContextMenu, KeyCode::Numpad3],
+    ),
+    (Action::ToggleControlBar, &[KeyCode::Tab]),
+    (Action::ToggleHelp, &[KeyCode::F1]),
+    (Action::TogglePerfHud, &[KeyCode::F2]),
+    (Action::ToggleEnergy, &[KeyCode::F3]),
+    (Action::ToggleHistogram, &[KeyCode::F4]),
+    (Action::NextBroadphase, &[KeyCode::F5]),
+    (Action::NextBoundaryMode, &[KeyCode::F9]),
+    (Action::ToggleBounds, &[KeyCode::KeyB]),
+    (Action::ToggleGpu, &[KeyCode::F6]),
+    (Action::ToggleSpeedHistogram, &[KeyCode::F7]),
+    (Action::ToggleFlowVectors, &[KeyCode::F8]),
+    (Action::ToggleHud, &[KeyCode::F10]),
+    (Action::ToggleFullscreen, &[KeyCode::F11]),
+    (Action::ToggleDiagnosticsWindow, &[KeyCode::End]),
+    (Action::NextFramePacing, &[KeyCode::ScrollLock]),
+    (Action::Reverse, &[KeyCode::KeyR]),
+    (Action::MoveUp, &[KeyCode::KeyW, KeyCode::ArrowUp]),
+    (Action::MoveLeft, &[KeyCode::KeyA, KeyCode::ArrowLeft]),
+    (Action::MoveDown, &[KeyCode::KeyS, KeyCode::ArrowDown]),
+    (Action::MoveRight, &[KeyCode::KeyD, KeyCode::ArrowRight]),
+    (Action::ZoomIn, &[KeyCode::Equal, KeyCode::NumpadAdd]),
+    (Action::ZoomOut, &[KeyCode::Minus, KeyCode::NumpadSubtract]),
+    (Action::Attract, &[KeyCode::Home]),
+    (Action::Herd, &[KeyCode::KeyG]),
+    (Action::Erase, &[KeyCode::KeyE]),
+    (Action::Clear, &[KeyCode::Space]),
+    (Action::SaveScene, &[KeyCode::KeyS]),
+    (Action::LoadScene, &[KeyCode::KeyO]),
+    (Action::Randomize, &[KeyCode::KeyN]),
+    (Action::DeleteSelection, &[KeyCode::Delete]),
+    (Action::PinSelection, &[KeyCode::Enter]),
+    (Action::SpawnGrid, &[KeyCode::Quote]),
+    (Action::SpawnRing, &[KeyCode::Backslash]),
+    (Action::SpawnSpiral, &[KeyCode::Backquote]),
+    (Action::TogglePlayer, &[KeyCode::Insert]),
+    (Action::FollowSelection, &[KeyCode::KeyF]),
+    (Action::Quit, &[KeyCode::Escape]),
+];
+
+// Camera bookmarks with Alt, presets with Ctrl
+const SLOTS: [KeyCode; 9] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
+pub(crate) const SLOT_COUNT: usize = SLOTS.len();
+
+// Keys of every action, read by the input systems and listed by the help overlay.
+// Can be inserted before adding `DotNetworkPlugin` to change the default keys,
+// Ctrl, Alt and Shift stay the modifiers.
+#[derive(Resource, Clone)]
+pub struct KeyBindings {
+    actions: HashMap<Action, Vec<KeyCode>>,
+    slots: [KeyCode; SLOT_COUNT],
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            actions: DEFAULT_BINDINGS
+                .iter()
+                .map(|(action, keys)| (*action, keys.to_vec()))
+                .collect(),
+            slots: SLOTS,
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        self.actions.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    // Replaces the keys of the action, none to disable it
+    pub fn bind(&mut self, action: Action, keys: Vec<KeyCode>) {
+        self.actions.insert(action, keys);
+    }
+
+    // The keys of the actions, as written in the info text, e.g. "I/K"
+    pub(crate) fn hint(&self, actions: &[Action]) -> String {
+        actions
+            .iter()
+            .flat_map(|action| self.keys(*action))
+            .map(|key| key_name(*key))
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    pub(crate) fn slots(&self) -> &[KeyCode; SLOT_COUNT] {
+        &self.slots
+    }
+
+    pub(crate) fn pressed(&self, input: &ButtonInput<KeyCode>, action: Action) -> bool {
+        input.any_pressed(self.keys(action).iter().copied())
+    }
+
+    pub(crate) fn just_pressed(&self, input: &ButtonInput<KeyCode>, action: Action) -> bool {
+        input.any_just_pressed(self.keys(action).iter().copied())
+    }
+}
+
+// Run conditions, like `input_pressed` and `input_just_pressed` for the keys bound
+// to the action
+pub(crate) fn action_pressed(
+    action: Action,
+) -> impl FnMut(Res<ButtonInput<KeyCode>>, Res<KeyBindings>) -> bool + Clone {
+    move |input, bindings| bindings.pressed(&input, action)
+}
+
+pub(crate) fn action_just_pressed(
+    action: Action,
+) -> impl FnMut(Res<ButtonInput<KeyCode>>, Res<KeyBindings>) -> bool + Clone {
+    move |input, bindings| bindings.just_pressed(&input, action)
+}
+
+// Keys listed on a line of the help
+pub(crate) enum Keys {
+    Actions(&'static [Action]),
+    Slots,
+}
+
+// A line of the help overlay
+pub(crate) struct Control {
+    // Written before the keys, e.g. "Ctrl + "
+    pub(crate) modifier: &'static str,
+    pub(crate) keys: Keys,
+    pub(crate) action: &'static str,
+}
+
+impl Control {
+    // The keys as written in the help, long runs like the bookmarks are shortened
+    // to "1..9"
+    pub(crate) fn keys_text(&self, bindings: &KeyBindings) -> String {
+        let keys: Vec<KeyCode> = match self.keys {
+            Keys::Actions(actions) => actions
+                .iter()
+                .flat_map(|action| bindings.keys(*action))
+                .copied()
+                .collect(),
+            Keys::Slots => bindings.slots().to_vec(),
+        };
+        let keys = match keys.as_slice() {
+            [first, .., last] if keys.len() > 4 => {
+                format!("{}..{}", key_name(*first), key_name(*last))
+            }
+            keys => keys
+                .iter()
+                .map(|key| key_name(*key))
+                .collect::<Vec<_>>()
+                .join(" / "),
+        };
+        format!("{}{}", self.modifier, keys)
+    }
+}
+
+const fn control(actions: &'static [Action], action: &'static str) -> Control {
+    Control {
+        modifier: "",
+        keys: Keys::Actions(actions),
+        action,
+    }
+}
+
+pub(crate) const CONTROLS: &[Control] = &[
+    control(
+        &[Action::IncreaseForce, Action::DecreaseForce],
+        "Increase / decrease connect force",
+    ),
+    control(
+        &[Action::ToggleDensityAdaptive],
+        "Adapt the connect force to the density",
+    ),
+    control(
+        &[Action::NextConnectionMode],
+        "Connect the close dots, a triangulation, a spanning tree or the nearest dots",
+    ),
+    control(
+        &[Action::ToggleTriangles],
+        "Fill the triangles of close connected dots",
+    ),
+    control(
+        &[Action::IncreaseAlphaFloor, Action::DecreaseAlphaFloor],
+        "Raise / lower the opacity of the faintest connections",
+    ),
+    control(
+        &[Action::ThickenLines, Action::ThinLines],
+        "Thicken / thin the closest connections",
+    ),
+    control(
+        &[Action::IncreaseSpeed, Action::DecreaseSpeed],
+        "Increase / decrease speed",
+    ),
+    control(
+        &[Action::IncreaseDamping, Action::DecreaseDamping],
+        "Increase / decrease the air friction",
+    ),
+    control(
+        &[Action::IncreaseRestitution, Action::DecreaseRestitution],
+        "Increase / decrease the bounciness of the edges",
+    ),
+    control(&[Action::ToggleFreeze], "Pause"),
+    control(
+        &[Action::ToggleLinesOrder],
+        "Draw the connections above or below the dots",
+    ),
+    control(&[Action::NextColorMode], "Switch the color of the dots"),
+    control(&[Action::NextTheme], "Switch to the next color theme"),
+    control(&[Action::ToggleGradient], "Toggle the gradient background"),
+    control(&[Action::ToggleBloom], "Toggle the glow (bloom)"),
+    control(
+        &[Action::ToggleCollisions],
+        "Toggle collisions between dots",
+    ),
+    control(&[Action::ToggleSparks], "Toggle the sparks of collisions"),
+    control(
+        &[Action::ToggleFlowField],
+        "Make the dots flow along a noise field",
+    ),
+    control(
+        &[Action::ToggleGravity],
+        "Toggle gravity, the dots bounce on the floor",
+    ),
+    control(
+        &[Action::ToggleSprings],
+        "Make the connections act as springs",
+    ),
+    control(
+        &[Action::ToggleMerging],
+        "Merge the dots touching each other",
+    ),
+    control(
+        &[Action::ToggleThreeD],
+        "Switch to a 3D view turning around the dots, or back",
+    ),
+    control(&[Action::Reverse], "Reverse the direction of every dot"),
+    control(&[Action::Randomize], "Respawn the dots at random positions"),
+    control(&[Action::Clear], "Delete all dots"),
+    Control {
+        modifier: "Click",
+        keys: Keys::Actions(&[]),
+        action: "Use the current tool of the toolbar",
+    },
+    Control {
+        modifier: "Shift + ",
+        keys: Keys::Slots,
+        action: "Pick a tool: spawn, erase, grab, pin, attract, emitter",
+    },
+    Control {
+        modifier: "Shift + drag",
+        keys: Keys::Actions(&[]),
+        action: "Select the dots in a box",
+    },
+    control(&[Action::DeleteSelection], "Delete the selected dots"),
+    control(
+        &[Action::PinSelection],
+        "Pin the selected dots, or unpin them",
+    ),
+    Control {
+        modifier: "(Shift +) ",
+        keys: Keys::Actions(&[Action::SpawnGrid, Action::SpawnRing, Action::SpawnSpiral]),
+        action: "Place a grid / ring / spiral of dots, turning with Shift",
+    },
+    Control {
+        modifier: "Ctrl/Shift + wheel",
+        keys: Keys::Actions(&[]),
+        action: "Change the radius / number of dots of the brush",
+    },
+    Control {
+        modifier: "Wheel / middle drag",
+        keys: Keys::Actions(&[]),
+        action: "Zoom around the cursor / move the camera",
+    },
+    Control {
+        modifier: "Hold ",
+        keys: Keys::Actions(&[Action::Attract]),
+        action: "Attract the dots to the cursor, Shift to repel, wheel for the strength",
+    },
+    Control {
+        modifier: "Hold ",
+        keys: Keys::Actions(&[Action::Herd]),
+        action: "Pull the dots around the cursor toward it",
+    },
+    Control {
+        modifier: "Hold ",
+        keys: Keys::Actions(&[Action::Erase]),
+        action: "Erase the dots around the cursor",
+    },
+    Control {
+        modifier: "Middle click",
+        keys: Keys::Actions(&[]),
+        action: "Place or remove a gravity well",
+    },
+    Control {
+        modifier: "Shift/Ctrl + middle click",
+        keys: Keys::Actions(&[]),
+        action: "Place a vortex spinning counterclockwise / clockwise",
+    },
+    Control {
+        modifier: "Alt + middle click",
+        keys: Keys::Actions(&[]),
+        action: "Place or remove an emitter of dots",
+    },
+    Control {
+        modifier: "Right click",
+        keys: Keys::Actions(&[]),
+        action: "Spawn a ring of dots flying outward",
+    },
+    Control {
+        modifier: "Hold right click",
+        keys: Keys::Actions(&[]),
+        action: "Push away the dots around the cursor",
+    },
+    control(&[Action::ToggleControlBar], "Show the sliders"),
+    control(&[Action::ToggleHelp], "Show this help"),
+    control(
+        &[Action::TogglePerfHud],
+        "Show the performance HUD and the frame time graph",
+    ),
+    control(&[Action::ToggleEnergy], "Show the kinetic energy"),
+    control(
+        &[Action::ToggleHistogram],
+        "Show the histogram of the connection distances",
+    ),
+    control(
+        &[Action::NextBroadphase],
+        "Switch the broadphase (grid, quadtree or every pair)",
+    ),
+    control(&[Action::ToggleGpu], "Find the connections on the GPU"),
+    control(
+        &[Action::NextBoundaryMode],
+        "Switch the edges of the world (walls, wrap around, circle or polygon)",
+    ),
+    control(
+        &[Action::ToggleBounds],
+        "Show or hide the edges of the world",
+    ),
+    control(
+        &[Action::ToggleSpeedHistogram],
+        "Show the histogram of the dot speeds",
+    ),
+    control(
+        &[Action::ToggleFlowVectors],
+        "Show the directions of the flow field",
+    ),
+    control(&[Action::ToggleHud], "Hide or show the whole HUD"),
+    control(
+        &[Action::ToggleFullscreen],
+        "Switch between fullscreen and a window",
+    ),
+    control(
+        &[Action::ToggleDiagnosticsWindow],
+        "Move the HUD to a second window, or back",
+    ),
+    control(
+        &[Action::NextFramePacing],
+        "Switch between VSync, uncapped frames and a 30 FPS cap",
+    ),
+    control(&[Action::MoveUp], "Move the camera up"),
+    control(&[Action::MoveLeft], "Move the camera left"),
+    control(&[Action::MoveDown], "Move the camera down"),
+    control(&[Action::MoveRight], "Move the camera right"),
+    control(
+        &[Action::FollowSelection],
+        "Follow the selected dot with the camera, or stop",
+    ),
+    control(
+        &[Action::TogglePlayer],
+        "Add a dot steered with the move keys, or remove it",
+    ),
+    control(&[Action::ZoomIn], "Zoom in"),
+    control(&[Action::ZoomOut], "Zoom out"),
+    Control {
+        modifier: "Alt + Shift + ",
+        keys: Keys::Slots,
+        action: "Bookmark the current view",
+    },
+    Control {
+        modifier: "Alt + ",
+        keys: Keys::Slots,
+        action: "Go to a bookmarked view",
+    },
+    Control {
+        modifier: "Ctrl + ",
+        keys: Keys::Slots,
+        action: "Store the settings as a preset",
+    },
+    Control {
+        modifier: "",
+        keys: Keys::Slots,
+        action: "Switch to a stored preset",
+    },
+    Control {
+        modifier: "Ctrl + ",
+        keys: Keys::Actions(&[Action::SaveScene, Action::LoadScene]),
+        action: "Save / load the whole simulation",
+    },
+    control(&[Action::Quit], "Quit"),
+];
+
+fn key_name(key: KeyCode) -> String {
+    match key {
+        KeyCode::BracketLeft => "[".into(),
+        KeyCode::BracketRight => "]".into(),
+        KeyCode::Equal => "+".into(),
+        KeyCode::Minus => "-".into(),
+        KeyCode::Period => ".".into(),
+        KeyCode::Comma => ",".into(),
+        KeyCode::Semicolon => ";".into(),
+        KeyCode::Slash => "/".into(),
+        KeyCode::Quote => "'".into(),
+        KeyCode::Backslash => "\\".into(),
+        KeyCode::Backquote => "`".into(),
+        // The extra key of the ISO keyboards, next to the left Shift
+        KeyCode::IntlBackslash => "<".into(),
+        KeyCode::ContextMenu => "Menu".into(),
+        KeyCode::NumpadAdd => "Numpad +".into(),
+        KeyCode::NumpadSubtract => "Numpad -".into(),
+        KeyCode::NumpadMultiply => "Numpad *".into(),
+        KeyCode::NumpadDivide => "Numpad /".into(),
+        _ => {
+            let name = format!("{key:?}");
+            ["Key", "Digit", "Arrow"]
+                .iter()
+                .find_map(|prefix| name.strip_prefix(prefix))
+                .unwrap_or(&name)
+                .to_string()
+        }
+    }
+}
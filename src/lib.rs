@@ -0,0 +1,592 @@
+//! A network of bouncing dots, connected by lines when they get close to each
+//! other. Add `DotNetworkPlugin` to a Bevy app (with `DefaultPlugins`) to get the
+//! whole simulation, e.g. as an animated background.
+
+use bevy::app::AppExit;
+use bevy::diagnostic::FrameTimeDiagnosticsPlugin;
+use bevy::input::common_conditions::*;
+use bevy::prelude::*;
+use bevy::time::common_conditions::on_timer;
+use std::time::Duration;
+
+mod actions;
+mod arena;
+mod background;
+mod broadphase;
+mod camera;
+mod clusters;
+mod color;
+mod conf;
+mod config;
+mod connections;
+mod controls;
+mod delaunay;
+mod diagnostics;
+#[cfg(not(target_arch = "wasm32"))]
+mod diagnostics_window;
+mod dots;
+#[cfg(feature = "egui")]
+mod egui_panel;
+mod emitters;
+mod flow;
+mod gamepad;
+mod gpu;
+mod grid;
+mod help;
+mod hud;
+mod input;
+mod inspector;
+mod keys;
+mod pacing;
+mod particles;
+mod patterns;
+mod physics;
+mod player;
+mod post_fx;
+mod presets;
+mod quadtree;
+mod scene;
+mod slider;
+mod spanning_tree;
+mod theme;
+mod three_d;
+mod tools;
+mod touch;
+mod triangles;
+mod wallpaper;
+mod wells;
+
+pub use actions::SimAction;
+pub use arena::load_polygon;
+pub use broadphase::Broadphase;
+pub use camera::{CameraConfig, ToneMapping};
+pub use color::ColorMode;
+pub use conf::SimuConf;
+pub use config::{Config, ConfigFile, WindowConfig};
+pub use connections::ConnectionMode;
+pub use diagnostics::DotNetworkDiagnosticsPlugin;
+pub use dots::{Dot, Velocity};
+pub use keys::{Action, KeyBindings};
+pub use patterns::Pattern;
+pub use physics::BoundaryMode;
+pub use scene::Autosave;
+pub use wallpaper::Wallpaper;
+
+use actions::*;
+use background::*;
+use broadphase::*;
+use camera::*;
+use clusters::*;
+use color::*;
+use config::*;
+use connections::*;
+use controls::*;
+use dots::*;
+use emitters::*;
+use flow::*;
+use gamepad::*;
+use gpu::*;
+use help::*;
+use hud::*;
+use input::*;
+use inspector::*;
+use keys::*;
+use pacing::*;
+use particles::*;
+use patterns::*;
+use physics::*;
+use player::*;
+use post_fx::*;
+use presets::*;
+use scene::*;
+use slider::*;
+use theme::*;
+use three_d::*;
+use tools::*;
+use touch::*;
+use triangles::*;
+use wallpaper::*;
+use wells::*;
+
+const DRAG_SPAWN_INTERVAL: u64 = 70; // In ms
+const ENERGY_UPDATE_INTERVAL: u64 = 250; // In ms
+const HISTOGRAM_UPDATE_INTERVAL: u64 = 250; // In ms
+const CONFIG_RELOAD_INTERVAL: u64 = 500; // In ms
+
+fn clusters_shown(simu_conf: Res<SimuConf>) -> bool {
+    simu_conf.color_mode == ColorMode::Clusters
+}
+
+fn three_d_shown(simu_conf: Res<SimuConf>) -> bool {
+    simu_conf.three_d
+}
+
+// The dots, their cameras, the HUD and the keyboard controls. `SimuConf` and
+// `CameraConfig` can be inserted before adding the plugin to change the defaults,
+// `SimuConf::number_of_dots` random dots are then spawned on the first frame. With
+// an `Autosave`, the simulation is saved on exit and restored on the next launch.
+// With a `Wallpaper`, the dots are drawn over a transparent background.
+pub struct DotNetworkPlugin;
+
+impl Plugin for DotNetworkPlugin {
+    fn build(&self, app: &mut App) {
+        // Needed by the perf HUD, the host app may already have it
+        if !app.is_plugin_added::<FrameTimeDiagnosticsPlugin>() {
+            app.add_plugins(FrameTimeDiagnosticsPlugin);
+        }
+
+        #[cfg(feature = "egui")]
+        app.add_plugins(egui_panel::EguiPanelPlugin);
+
+        app.add_plugins((
+            GpuConnectionsPlugin,
+            GradientBackgroundPlugin,
+            PostFxPlugin,
+            DotNetworkDiagnosticsPlugin,
+        ))
+        .register_type::<SimuConf>()
+        .register_type::<Dot>()
+        .register_type::<Velocity>()
+        .init_resource::<SimuConf>()
+        .init_resource::<ConfigFile>()
+        .init_resource::<Theme>()
+        .init_resource::<KeyBindings>()
+        .init_resource::<DotRng>()
+        .init_resource::<SpawnOrder>()
+        .init_resource::<CameraConfig>()
+        .init_resource::<CameraBookmarks>()
+        .init_resource::<MousePan>()
+        .init_resource::<FollowCamera>()
+        .init_resource::<Presets>()
+        .init_resource::<WorldBounds>()
+        .init_resource::<BoundsOutline>()
+        .init_resource::<NeighbourSearch>()
+        .init_resource::<ConnectionRenderer>()
+        .init_resource::<Connections>()
+        .init_resource::<Clusters>()
+        .init_resource::<PointerOverUi>()
+        .init_resource::<BoxSelection>()
+        .init_resource::<HoveredDot>()
+        .init_resource::<Tool>()
+        .init_resource::<PerfStats>()
+        .init_resource::<EnergyDiagnostic>()
+        .init_resource::<DistanceHistogram>()
+        .init_resource::<SpeedHistogram>()
+        .init_resource::<FlowFieldVectors>()
+        .add_event::<DotCollision>()
+        .add_event::<SimAction>()
+        .add_systems(
+            Startup,
+            (
+                setup_dot_assets,
+                load_presets,
+                spawn_connection_mesh,
+                spawn_triangle_mesh,
+                spawn_background,
+                spawn_cameras,
+                spawn_gamepad_cursor,
+                restore_autosave
+                    .after(setup_dot_assets)
+                    .after(spawn_cameras)
+                    .run_if(resource_exists::<Autosave>),
+                (
+                    spawn_hud,
+                    (
+                        spawn_control_bar,
+                        spawn_help,
+                        spawn_inspector,
+                        spawn_toolbar,
+                    ),
+                    start_wallpaper.run_if(resource_exists::<Wallpaper>),
+                )
+                    .chain(),
+            ),
+        )
+        .add_systems(
+            Update,
+            (
+                (
+                    (
+                        (
+                            map_mouse_spawn
+                                .run_if(on_timer(Duration::from_millis(DRAG_SPAWN_INTERVAL)))
+                                .run_if(input_pressed(MouseButton::Left)),
+                            map_mouse_split.run_if(input_just_pressed(MouseButton::Left)),
+                        )
+                            .run_if(tool_is(Tool::Spawn)),
+                        map_mouse_eraser
+                            .run_if(input_pressed(MouseButton::Left))
+                            .run_if(tool_is(Tool::Erase))
+                            .run_if(not(action_pressed(Action::Erase))),
+                        map_mouse_grab
+                            .run_if(input_just_pressed(MouseButton::Left))
+                            .run_if(tool_is(Tool::Grab)),
+                        map_mouse_pin
+                            .run_if(input_just_pressed(MouseButton::Left))
+                            .run_if(tool_is(Tool::Pin)),
+                        map_mouse_attractor
+                            .run_if(input_pressed(MouseButton::Left))
+                            .run_if(tool_is(Tool::Attract))
+                            .run_if(not(action_pressed(Action::Attract))),
+                        map_mouse_emitter
+                            .run_if(input_just_pressed(MouseButton::Left))
+                            .run_if(tool_is(Tool::PlaceEmitter)),
+                    )
+                        .run_if(not(shift_pressed))
+                        .run_if(|selection: Res<BoxSelection>| selection.start.is_none()),
+                    map_mouse_box_select.run_if(
+                        shift_pressed
+                            .or_else(|selection: Res<BoxSelection>| selection.start.is_some()),
+                    ),
+                )
+                    .in_set(MapInput)
+                    .run_if(|pointer: Res<PointerOverUi>| !pointer.0),
+                map_mouse_repulsion
+                    .in_set(MapInput)
+                    .run_if(input_pressed(MouseButton::Right))
+                    .run_if(|pointer: Res<PointerOverUi>| !pointer.0),
+                map_mouse_drag
+                    .in_set(MapInput)
+                    .run_if(|grabbed: Query<(), With<Grabbed>>| !grabbed.is_empty()),
+                map_mouse_burst
+                    .in_set(MapInput)
+                    .run_if(|pointer: Res<PointerOverUi>| !pointer.0),
+                map_mouse_well
+                    .in_set(MapInput)
+                    .after(pan_camera)
+                    .run_if(input_just_released(MouseButton::Middle))
+                    .run_if(|pan: Res<MousePan>| pan.distance < PAN_DRAG_THRESHOLD)
+                    .run_if(|pointer: Res<PointerOverUi>| !pointer.0),
+                (toggle_wells.after(MapInput), draw_wells).chain(),
+                (
+                    toggle_emitters.after(MapInput),
+                    emit_dots,
+                    draw_emitters.run_if(not(resource_exists::<Wallpaper>)),
+                )
+                    .chain(),
+                map_touch_spawn
+                    .in_set(MapInput)
+                    .run_if(on_timer(Duration::from_millis(DRAG_SPAWN_INTERVAL)))
+                    .run_if(|pointer: Res<PointerOverUi>| !pointer.0),
+                map_touch_gestures.in_set(MapInput),
+                (
+                    move_gamepad_cursor,
+                    map_gamepad_spawn
+                        .in_set(MapInput)
+                        .run_if(on_timer(Duration::from_millis(DRAG_SPAWN_INTERVAL))),
+                )
+                    .chain(),
+                map_gamepad_input.in_set(MapInput),
+                draw_gamepad_cursor.after(move_gamepad_cursor),
+                (
+                    spawn_dots_at,
+                    spawn_bursts,
+                    spawn_patterns,
+                    split_dots.before(merge_dots),
+                    toggle_pins,
+                )
+                    .after(MapInput),
+                draw_pins,
+                (
+                    toggle_bounds_outline.run_if(action_just_pressed(Action::ToggleBounds)),
+                    draw_world_bounds.after(update_world_bounds).run_if(
+                        |outline: Res<BoundsOutline>, conf: Res<SimuConf>| outline.shown(&conf),
+                    ),
+                )
+                    .chain(),
+                (
+                    (select_dots, select_box).after(MapInput),
+                    update_inspector,
+                    draw_selection,
+                )
+                    .chain(),
+                (
+                    hover_dots
+                        .after(update_neighbour_search)
+                        .before(draw_connections),
+                    draw_hovered_dot,
+                )
+                    .chain(),
+                draw_brush
+                    .run_if(tool_is(Tool::Spawn))
+                    .run_if(|conf: Res<SimuConf>| conf.brush_radius > 0.),
+            ),
+        )
+        .add_systems(
+            Update,
+            compute_energy
+                .run_if(on_timer(Duration::from_millis(ENERGY_UPDATE_INTERVAL)))
+                .run_if(|energy: Res<EnergyDiagnostic>| energy.enabled),
+        )
+        .add_systems(
+            Update,
+            update_distance_histogram
+                .run_if(on_timer(Duration::from_millis(HISTOGRAM_UPDATE_INTERVAL)))
+                .run_if(|histogram: Res<DistanceHistogram>| histogram.enabled),
+        )
+        .add_systems(
+            Update,
+            update_speed_histogram
+                .after(apply_dot_dot_collision)
+                .run_if(|histogram: Res<SpeedHistogram>| histogram.enabled),
+        )
+        .add_systems(
+            Update,
+            (
+                add_depth,
+                (
+                    switch_cameras.run_if(resource_changed::<SimuConf>),
+                    orbit_camera.after(update_world_bounds),
+                    draw_dots_3d.run_if(three_d_shown),
+                )
+                    .chain(),
+            ),
+        )
+        .add_systems(
+            Update,
+            toggle_speed_histogram.run_if(action_just_pressed(Action::ToggleSpeedHistogram)),
+        )
+        .add_systems(
+            Update,
+            (
+                toggle_flow_vectors.run_if(action_just_pressed(Action::ToggleFlowVectors)),
+                draw_flow_vectors.run_if(|vectors: Res<FlowFieldVectors>| vectors.enabled),
+            )
+                .chain(),
+        )
+        .add_systems(
+            Update,
+            randomize_dots
+                .after(update_world_bounds)
+                .run_if(run_once())
+                .run_if(|dots: Query<(), With<Dot>>| dots.is_empty()),
+        )
+        .add_systems(
+            Update,
+            clear_dots
+                .after(MapInput)
+                .before(merge_dots)
+                .run_if(on_sim_action(SimAction::Clear)),
+        )
+        .add_systems(
+            Update,
+            (
+                delete_selection
+                    .after(MapInput)
+                    .before(merge_dots)
+                    .run_if(on_sim_action(SimAction::DeleteSelection)),
+                erase_dots.after(delete_selection).before(merge_dots),
+                draw_eraser.run_if(action_pressed(Action::Erase).or_else(tool_is(Tool::Erase))),
+                pin_selection
+                    .after(MapInput)
+                    .run_if(on_sim_action(SimAction::PinSelection)),
+            ),
+        )
+        .add_systems(
+            Update,
+            toggle_player
+                .after(MapInput)
+                .before(merge_dots)
+                .run_if(on_sim_action(SimAction::TogglePlayer)),
+        )
+        .add_systems(
+            Update,
+            reverse_dots
+                .after(MapInput)
+                .run_if(on_sim_action(SimAction::Reverse)),
+        )
+        .add_systems(
+            Update,
+            toggle_lines_order
+                .after(MapInput)
+                .run_if(on_sim_action(SimAction::ToggleLinesOrder)),
+        )
+        .add_systems(
+            Update,
+            save_scene
+                .after(MapInput)
+                .run_if(on_sim_action(SimAction::SaveScene)),
+        )
+        .add_systems(
+            Update,
+            load_scene
+                .after(MapInput)
+                .run_if(on_sim_action(SimAction::LoadScene)),
+        )
+        .add_systems(
+            Update,
+            (
+                reload_config_file.run_if(on_timer(Duration::from_millis(CONFIG_RELOAD_INTERVAL))),
+                apply_gizmo_config.run_if(resource_changed::<ConfigFile>),
+            )
+                .chain(),
+        )
+        .add_systems(
+            Last,
+            save_autosave
+                .run_if(on_event::<AppExit>())
+                .run_if(resource_exists::<Autosave>),
+        )
+        .add_systems(
+            Update,
+            cycle_theme.run_if(action_just_pressed(Action::NextTheme)),
+        )
+        .add_systems(
+            Update,
+            toggle_control_bar.run_if(action_just_pressed(Action::ToggleControlBar)),
+        )
+        .add_systems(
+            Update,
+            (
+                toggle_hud.run_if(action_just_pressed(Action::ToggleHud)),
+                toggle_fullscreen.run_if(action_just_pressed(Action::ToggleFullscreen)),
+            ),
+        )
+        .add_systems(
+            Update,
+            toggle_help.run_if(action_just_pressed(Action::ToggleHelp)),
+        )
+        .add_systems(
+            Update,
+            randomize_dots
+                .after(MapInput)
+                .run_if(on_sim_action(SimAction::Randomize)),
+        )
+        .add_systems(
+            Update,
+            (
+                (map_keyboard_input, map_pattern_keys).in_set(MapInput),
+                (
+                    map_mouse_attractor.run_if(action_pressed(Action::Attract)),
+                    map_mouse_wheel,
+                    map_mouse_herd.run_if(action_pressed(Action::Herd)),
+                    map_mouse_eraser.run_if(action_pressed(Action::Erase)),
+                )
+                    .in_set(MapInput),
+                apply_sim_actions.after(MapInput),
+                handle_hud_keys,
+                use_presets,
+                update_info_text,
+                update_perf_hud,
+                update_frame_time_graph,
+                (
+                    drag_sliders,
+                    sync_conf_sliders,
+                    (update_slider_fills, update_slider_values),
+                )
+                    .chain(),
+                (
+                    track_pointer_over_controls,
+                    select_tool,
+                    update_toolbar
+                        .run_if(resource_changed::<Tool>.or_else(resource_changed::<Theme>)),
+                )
+                    .chain(),
+                (
+                    apply_attractor.after(MapInput),
+                    apply_cursor_fields.after(MapInput),
+                    (apply_wells, apply_vortices),
+                    apply_flow_field.run_if(|conf: Res<SimuConf>| conf.flow_field),
+                    apply_gravity.run_if(|conf: Res<SimuConf>| conf.gravity),
+                    apply_springs.run_if(|conf: Res<SimuConf>| conf.springs),
+                    apply_damping.run_if(|conf: Res<SimuConf>| conf.damping != 0.),
+                    move_player,
+                    hold_pinned_dots,
+                    (apply_dot_velocity, move_depth.run_if(three_d_shown)),
+                    grab_dots,
+                    update_world_bounds,
+                    apply_dot_collision,
+                    merge_dots.run_if(|conf: Res<SimuConf>| conf.merge_dots),
+                    apply_dot_dot_collision.run_if(|conf: Res<SimuConf>| conf.dot_collisions),
+                    update_neighbour_search,
+                    connect_dot,
+                    update_clusters.run_if(clusters_shown),
+                    (
+                        draw_connections.run_if(not(three_d_shown)),
+                        draw_triangles,
+                        draw_connections_3d.run_if(three_d_shown),
+                        update_degrees,
+                    ),
+                )
+                    .chain(),
+                spawn_collision_particles
+                    .after(apply_dot_dot_collision)
+                    .run_if(|conf: Res<SimuConf>| conf.collision_particles),
+                (
+                    (tick_lifetimes, draw_particles),
+                    (
+                        expire_dots,
+                        track_spawn_order,
+                        evict_oldest_dots.run_if(|conf: Res<SimuConf>| conf.max_dots > 0),
+                    )
+                        .chain()
+                        .before(MapInput),
+                ),
+                resize_dots,
+                apply_theme.run_if(resource_changed::<Theme>),
+                update_background.after(toggle_lines_order).run_if(
+                    resource_changed::<SimuConf>
+                        .or_else(resource_changed::<Theme>)
+                        .or_else(resource_changed::<ConnectionRenderer>),
+                ),
+                (
+                    update_bloom.after(toggle_lines_order).run_if(
+                        resource_changed::<SimuConf>
+                            .or_else(resource_changed::<ConnectionRenderer>),
+                    ),
+                    apply_present_mode.run_if(resource_changed::<SimuConf>),
+                    update_tonemapping.run_if(resource_changed::<SimuConf>),
+                    // Every frame for the grain
+                    update_post_fx.after(toggle_lines_order),
+                    update_anti_aliasing.after(toggle_lines_order).run_if(
+                        resource_changed::<SimuConf>
+                            .or_else(resource_changed::<ConnectionRenderer>),
+                    ),
+                ),
+                (
+                    age_dots,
+                    update_age_palette.run_if(resource_changed::<SimuConf>),
+                    (recolor_dots, scale_dots),
+                    fade_dots.after(recolor_clusters),
+                )
+                    .chain(),
+                recolor_clusters
+                    .after(update_clusters)
+                    .run_if(clusters_shown),
+                (
+                    control_camera,
+                    pan_camera,
+                    zoom_camera,
+                    toggle_follow_camera.run_if(action_just_pressed(Action::FollowSelection)),
+                    follow_selected_dot.run_if(|follow: Res<FollowCamera>| follow.enabled),
+                    move_camera_to_bookmark,
+                    sync_lines_camera,
+                    follow_main_camera,
+                )
+                    .chain(),
+            ),
+        );
+
+        // A web page is closed by the browser, not by the app
+        #[cfg(not(target_arch = "wasm32"))]
+        app.add_systems(
+            Update,
+            quit.run_if(action_pressed(Action::Quit).or_else(gamepad_quit_pressed)),
+        );
+
+        #[cfg(not(target_arch = "wasm32"))]
+        app.add_systems(Last, limit_frame_rate);
+
+        // A page has a single canvas
+        #[cfg(not(target_arch = "wasm32"))]
+        app.add_systems(
+            Update,
+            (
+                diagnostics_window::toggle_diagnostics_window
+                    .run_if(action_just_pressed(Action::ToggleDiagnosticsWindow)),
+                diagnostics_window::close_diagnostics_window,
+            )
+                .chain(),
+        );
+    }
+}
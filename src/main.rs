@@ -1,274 +1,216 @@
-use bevy::app::AppExit;
-use bevy::diagnostic::FrameTimeDiagnosticsPlugin;
 use bevy::diagnostic::LogDiagnosticsPlugin;
-use bevy::input::common_conditions::*;
 use bevy::prelude::*;
-use bevy::sprite::MaterialMesh2dBundle;
-use bevy::time::common_conditions::on_timer;
-use rand::Rng;
-use std::time::Duration;
-
-/**
- * Default values.
- */
-const CONNECT_FORCE: f32 = 300.;
-const SPEED: f32 = 1.;
-const DOT_SIZE: f32 = 6.;
-const MIN_VEL: f32 = -600.;
-const MAX_VEL: f32 = 600.;
-
-const INFO_TEXT_PADDING: Val = Val::Px(6.0);
-const INFO_TEXT_SIZE: f32 = 16.;
-const INFO_TEXT_COLOR: Color = Color::ANTIQUE_WHITE;
-
-const DRAG_SPAWN_INTERVAL: u64 = 70; // In ms
-
-// Used to identify the Dots
-#[derive(Component)]
-struct Dot;
-
-// The info text
-#[derive(Component)]
-struct InfoText;
-
-#[derive(Component, Deref, DerefMut)]
-struct Velocity(Vec2);
-
-// Associated to the gizmos line for the line connecting the dots
-#[derive(Default, Reflect, GizmoConfigGroup)]
-struct Lines {}
-
-// Variables of the simulation
-#[derive(Resource)]
-struct SimuConf {
-    dot_size: f32,
-    speed: f32,
-    connect_force: f32,
-    min_vel: f32,
-    max_vel: f32,
-    freeze_dots: bool,
-    number_of_dots: u32,
-}
-
-fn main() {
-    App::new()
-        .add_plugins(DefaultPlugins)
-        .add_plugins(FrameTimeDiagnosticsPlugin::default())
-        .init_gizmo_group::<Lines>()
-        .insert_resource(SimuConf {
-            dot_size: DOT_SIZE,
-            speed: SPEED,
-            min_vel: MIN_VEL,
-            max_vel: MAX_VEL,
-            connect_force: CONNECT_FORCE,
-            freeze_dots: false,
-            number_of_dots: 0,
-        })
-        .add_plugins(LogDiagnosticsPlugin::default())
-        .add_systems(Startup, setup)
-        .add_systems(
-            Update,
-            spawn_dots_on_cursor
-                .run_if(on_timer(Duration::from_millis(DRAG_SPAWN_INTERVAL)))
-                .run_if(input_pressed(MouseButton::Left)),
-        )
-        .add_systems(
-            Update,
-            clear_dots.run_if(input_just_pressed(KeyCode::Space)),
-        )
-        .add_systems(
-            Update,
-            (
-                handle_keyboard_input,
-                update_info_text,
-                (apply_dot_velocity, apply_dot_collision, connect_dot).chain(),
-            ),
-        )
-        .run();
-}
-
-fn distance_between_points(p1: Vec2, p2: Vec2) -> f32 {
-    ((p2.x - p1.x).powi(2) + (p2.y - p1.y).powi(2)).sqrt()
-}
-
-fn map(value: f32, from_low: f32, from_high: f32, to_low: f32, to_high: f32) -> f32 {
-    return to_low + (to_high - to_low) * ((value - from_low) / (from_high - from_low));
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
+use dot_network::{
+    load_polygon, Autosave, BoundaryMode, CameraConfig, Config, ConfigFile, DotNetworkPlugin,
+    SimuConf, Wallpaper, WindowConfig,
+};
+use std::path::PathBuf;
+
+// Options given on the command line, they override the config file
+#[derive(Parser)]
+#[command(about = "A network of bouncing dots, connected when they get close")]
+struct LaunchOptions {
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Config file to read instead of dot-network.toml or dot-network.ron"
+    )]
+    config: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Start with new dots instead of the simulation saved on exit"
+    )]
+    no_restore: bool,
+    #[arg(long, value_name = "N", help = "Dots spawned at random positions")]
+    dots: Option<u32>,
+    #[arg(long, help = "Distance under which two dots are connected")]
+    connect_force: Option<f32>,
+    #[arg(long, help = "Speed factor of the dots")]
+    speed: Option<f32>,
+    #[arg(long, help = "Size of the dots")]
+    size: Option<f32>,
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        help = "Time after which the dots fade out and are deleted"
+    )]
+    lifetime: Option<f32>,
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Dots kept at most, the oldest ones are deleted past that"
+    )]
+    max_dots: Option<u32>,
+    #[arg(
+        long,
+        help = "Seed of the random spawns, to get the same dots every time"
+    )]
+    seed: Option<u64>,
+    #[arg(
+        long,
+        value_name = "FPS",
+        help = "Most frames per second, VSync is turned off with it"
+    )]
+    fps_cap: Option<f32>,
+    #[arg(long, value_parser = parse_zoom, help = "Initial zoom of the camera")]
+    zoom: Option<f32>,
+    #[arg(long, value_name = "X,Y", value_parser = parse_pair::<f32, ','>, help = "Initial position of the camera")]
+    camera: Option<(f32, f32)>,
+    #[arg(long, value_name = "WxH", value_parser = parse_pair::<f32, 'x'>, help = "Size of the window")]
+    window: Option<(f32, f32)>,
+    #[arg(long, value_name = "WxH", value_parser = parse_pair::<f32, 'x'>, help = "Size of the area the dots bounce in, the window by default")]
+    world: Option<(f32, f32)>,
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "RON or SVG file of a polygon the dots bounce inside"
+    )]
+    arena: Option<PathBuf>,
+    #[arg(long, help = "Open the window in borderless fullscreen")]
+    fullscreen: bool,
+    #[arg(
+        long,
+        help = "Run as a live wallpaper, in a transparent window behind the others"
+    )]
+    wallpaper: bool,
+    #[arg(long, value_name = "X,Y", value_parser = parse_pair::<i32, ','>, help = "Initial position of the window on the desktop")]
+    window_position: Option<(i32, i32)>,
+    #[arg(
+        long,
+        value_name = "INDEX",
+        help = "Open the window centered on this monitor"
+    )]
+    monitor: Option<usize>,
 }
 
-fn connect_dot(
-    mut gizmos: Gizmos<Lines>,
-    query: Query<&Transform, With<Dot>>,
-    simu_conf: Res<SimuConf>,
-) {
-    for [dot, dot2] in query.iter_combinations() {
-        let d1 = Vec2::new(dot.translation.x, dot.translation.y);
-        let d2 = Vec2::new(dot2.translation.x, dot2.translation.y);
-        let dist = distance_between_points(d1, d2);
-        if dist < simu_conf.connect_force {
-            let alpha = map(dist, 0., simu_conf.connect_force, 1., 0.);
-            let color = Color::rgba(0.93, 0.51, 0.93, alpha);
-            gizmos.line_2d(d1, d2, color);
-        }
+fn parse_pair<T: std::str::FromStr, const SEPARATOR: char>(value: &str) -> Result<(T, T), String> {
+    let error = || format!("expected two numbers separated by '{SEPARATOR}'");
+    let (x, y) = value.split_once(SEPARATOR).ok_or_else(error)?;
+    match (x.trim().parse(), y.trim().parse()) {
+        (Ok(x), Ok(y)) => Ok((x, y)),
+        _ => Err(error()),
     }
 }
 
-fn clear_dots(
-    mut query: Query<Entity, With<Dot>>,
-    mut commands: Commands,
-    mut simu_conf: ResMut<SimuConf>,
-) {
-    for dot in &mut query {
-        commands.entity(dot).despawn();
+fn parse_zoom(value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(zoom) if zoom > 0. => Ok(zoom),
+        _ => Err("expected a positive number".into()),
     }
-    simu_conf.number_of_dots = 0;
-}
-
-fn spawn_dots_on_cursor(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    windows: Query<&Window>,
-    camera_query: Query<(&Camera, &GlobalTransform)>,
-    mut simu_conf: ResMut<SimuConf>,
-) {
-    let (camera, camera_transform) = camera_query.single();
-
-    let Some(cursor_position) = windows.single().cursor_position() else {
-        return;
-    };
-
-    // Calculate a world position based on the cursor's position.
-    let Some(cursor_pos) = camera.viewport_to_world_2d(camera_transform, cursor_position) else {
-        return;
-    };
-
-    let mut rng = rand::thread_rng();
-    let r_x = rng.gen_range(simu_conf.min_vel..simu_conf.max_vel) as f32;
-    let r_y = rng.gen_range(simu_conf.min_vel..simu_conf.max_vel) as f32;
-
-    commands.spawn((
-        MaterialMesh2dBundle {
-            mesh: meshes
-                .add(Circle {
-                    radius: simu_conf.dot_size,
-                })
-                .into(),
-            // transform: Transform::default().with_scale(Vec3::splat(10.)).,
-            transform: Transform::from_xyz(cursor_pos.x, cursor_pos.y, 1.),
-            material: materials.add(Color::VIOLET),
-            ..default()
-        },
-        Dot,
-        Velocity(Vec2::new(r_x, r_y)),
-    ));
-
-    simu_conf.number_of_dots += 1;
 }
 
-fn apply_dot_velocity(
-    mut query: Query<(&mut Transform, &Velocity)>,
-    time: Res<Time>,
-    simu_conf: ResMut<SimuConf>,
-) {
-    if simu_conf.freeze_dots {
-        return;
-    }
-
-    for (mut transform, velocity) in &mut query {
-        transform.translation.x += velocity.x * simu_conf.speed * time.delta_seconds();
-        transform.translation.y += velocity.y * simu_conf.speed * time.delta_seconds();
+impl LaunchOptions {
+    // Saved in the data directory of the platform, e.g. ~/.local/share on Linux, or
+    // in the working directory when there's none
+    fn autosave(&self) -> Autosave {
+        let data_dir = dirs::data_dir().unwrap_or_default();
+        Autosave {
+            path: data_dir.join("dot-network").join("autosave.ron"),
+            restore: !self.no_restore,
+        }
     }
-}
-
-fn apply_dot_collision(
-    mut query: Query<(&mut Transform, &mut Velocity), With<Dot>>,
-    window: Query<&Window>,
-) {
-    let window = window.single();
-    let width = window.resolution.width();
-    let height = window.resolution.height();
-
-    let ratio = 2.;
 
-    for (mut transform, mut velocity) in &mut query {
-        if transform.translation.x >= width / ratio {
-            velocity.x = -velocity.x;
-            transform.translation.x = width / ratio;
-        } else if transform.translation.x <= -width / ratio {
-            velocity.x = -velocity.x;
-            transform.translation.x = -width / ratio;
+    fn simu_conf(&self, config: &Config) -> SimuConf {
+        let mut simu_conf = config.simu_conf();
+        if let Some(dots) = self.dots {
+            simu_conf.number_of_dots = dots;
         }
-
-        if transform.translation.y >= height / ratio {
-            velocity.y = -velocity.y;
-            transform.translation.y = height / ratio;
-        } else if transform.translation.y <= -height / ratio {
-            velocity.y = -velocity.y;
-            transform.translation.y = -height / ratio;
+        if let Some(connect_force) = self.connect_force {
+            simu_conf.connect_force = connect_force;
         }
-    }
-}
-
-fn handle_keyboard_input(
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    mut simu_conf: ResMut<SimuConf>,
-    mut writer: EventWriter<AppExit>,
-) {
-    if keyboard_input.pressed(KeyCode::KeyI) {
-        simu_conf.connect_force += 2.;
-    }
-
-    if keyboard_input.pressed(KeyCode::KeyK) {
-        simu_conf.connect_force -= 2.;
-    }
-
-    if keyboard_input.pressed(KeyCode::KeyU) {
-        simu_conf.speed += 0.04;
-    }
-
-    if keyboard_input.pressed(KeyCode::KeyJ) {
-        simu_conf.speed -= 0.04;
-    }
-
-    if keyboard_input.just_pressed(KeyCode::KeyR) {
-        simu_conf.speed *= -1.;
+        if let Some(speed) = self.speed {
+            simu_conf.speed = speed;
+        }
+        if let Some(size) = self.size {
+            simu_conf.dot_size = size;
+        }
+        if let Some(lifetime) = self.lifetime {
+            simu_conf.dot_lifetime = lifetime;
+        }
+        if let Some(max_dots) = self.max_dots {
+            simu_conf.max_dots = max_dots;
+        }
+        if let Some(fps_cap) = self.fps_cap {
+            simu_conf.fps_cap = fps_cap;
+            simu_conf.vsync = false;
+        }
+        if self.seed.is_some() {
+            simu_conf.seed = self.seed;
+        }
+        if let Some((width, height)) = self.world {
+            simu_conf.world_size = Some(Vec2::new(width, height));
+        }
+        if let Some(path) = &self.arena {
+            simu_conf.arena_polygon = load_polygon(path).unwrap_or_else(|error| {
+                LaunchOptions::command()
+                    .error(ErrorKind::Io, format!("can't read the arena, {error}"))
+                    .exit()
+            });
+            simu_conf.boundary_mode = BoundaryMode::Polygon;
+        }
+        simu_conf
     }
 
-    if keyboard_input.just_pressed(KeyCode::KeyP) {
-        simu_conf.freeze_dots = !simu_conf.freeze_dots;
+    fn camera(&self, config: &Config) -> CameraConfig {
+        let mut camera = config.camera;
+        if let Some(zoom) = self.zoom {
+            camera.zoom = zoom;
+        }
+        if let Some((x, y)) = self.camera {
+            camera.position = Vec2::new(x, y);
+        }
+        camera
     }
 
-    if keyboard_input.pressed(KeyCode::Escape) {
-        writer.send(AppExit);
+    fn window(&self, config: &Config) -> WindowConfig {
+        let mut window = config.window.clone();
+        if let Some((width, height)) = self.window {
+            window.size = Some(Vec2::new(width, height));
+        }
+        window.fullscreen |= self.fullscreen;
+        window.wallpaper |= self.wallpaper;
+        match (self.window_position, self.monitor) {
+            (Some((x, y)), _) => window.position = Some(IVec2::new(x, y)),
+            // The monitor given on the command line wins over the position of the file
+            (None, Some(index)) => {
+                window.position = None;
+                window.monitor = Some(index);
+            }
+            (None, None) => {}
+        }
+        window
     }
 }
 
-fn update_info_text(simu_conf: Res<SimuConf>, mut query: Query<&mut Text, With<InfoText>>) {
-    let mut text = query.single_mut();
-    let info_text = format!(
-        "Dot (Click/Space): {} | Connect Force (I/K) : {} | Speed (U/J): {}",
-        simu_conf.number_of_dots, simu_conf.connect_force, simu_conf.speed
-    );
-    text.sections[0].value = info_text;
-}
-
-fn setup(mut commands: Commands) {
-    commands.spawn(Camera2dBundle::default());
-    commands.spawn((
-        InfoText,
-        TextBundle::from(TextSection::new(
-            "info text",
-            TextStyle {
-                font_size: INFO_TEXT_SIZE,
-                color: INFO_TEXT_COLOR,
-                ..default()
-            },
-        ))
-        .with_style(Style {
-            position_type: PositionType::Absolute,
-            top: INFO_TEXT_PADDING,
-            left: INFO_TEXT_PADDING,
-            ..default()
-        }),
-    ));
+fn main() {
+    let options = LaunchOptions::parse();
+    let config_file = ConfigFile::load(options.config.clone()).unwrap_or_else(|error| {
+        LaunchOptions::command()
+            .error(
+                ErrorKind::Io,
+                format!("can't read the config file, {error}"),
+            )
+            .exit()
+    });
+    let config = &config_file.config;
+    let window = options.window(config);
+
+    let mut app = App::new();
+    if window.wallpaper {
+        app.insert_resource(Wallpaper);
+    }
+    app.add_plugins(DefaultPlugins.set(WindowPlugin {
+        primary_window: Some(window.window()),
+        ..default()
+    }))
+    .insert_resource(options.simu_conf(config))
+    .insert_resource(options.camera(config))
+    .insert_resource(config.key_bindings())
+    .insert_resource(options.autosave())
+    .insert_resource(config_file)
+    .add_plugins(DotNetworkPlugin)
+    .add_plugins(LogDiagnosticsPlugin::default())
+    .run();
 }
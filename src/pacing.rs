@@ -0,0 +1,52 @@
+use bevy::prelude::*;
+use bevy::window::{PresentMode, PrimaryWindow};
+
+use crate::SimuConf;
+
+// Cap picked by the frame pacing key, to save power
+pub(crate) const POWER_SAVING_FPS_CAP: f32 = 30.;
+
+// VSync, then uncapped frames to measure the cost of the simulation, then
+// `POWER_SAVING_FPS_CAP`
+pub(crate) fn next_frame_pacing(simu_conf: &mut SimuConf) {
+    (simu_conf.vsync, simu_conf.fps_cap) = match (simu_conf.vsync, simu_conf.fps_cap > 0.) {
+        (true, false) => (false, 0.),
+        (false, false) => (false, POWER_SAVING_FPS_CAP),
+        _ => (true, 0.),
+    };
+}
+
+pub(crate) fn apply_present_mode(
+    simu_conf: Res<SimuConf>,
+    mut window: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    let Ok(mut window) = window.get_single_mut() else {
+        return;
+    };
+    let present_mode = if simu_conf.vsync {
+        PresentMode::AutoVsync
+    } else {
+        PresentMode::AutoNoVsync
+    };
+    if window.present_mode != present_mode {
+        window.present_mode = present_mode;
+    }
+}
+
+// Run last, sleeps out the rest of the frame under `SimuConf.fps_cap`. The browser
+// paces the frames of a page itself.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn limit_frame_rate(
+    simu_conf: Res<SimuConf>,
+    mut frame_start: Local<Option<bevy::utils::Instant>>,
+) {
+    if simu_conf.fps_cap > 0. {
+        let frame_time = bevy::utils::Duration::from_secs_f32(1. / simu_conf.fps_cap);
+        if let Some(elapsed) = frame_start.map(|start| start.elapsed()) {
+            if elapsed < frame_time {
+                std::thread::sleep(frame_time - elapsed);
+            }
+        }
+    }
+    *frame_start = Some(bevy::utils::Instant::now());
+}
@@ -0,0 +1,79 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::dots::{Dot, DotRng, Velocity};
+use crate::physics::DotCollision;
+use crate::theme::Theme;
+
+const PARTICLES_PER_COLLISION: usize = 4;
+const MAX_PARTICLES_PER_FRAME: usize = 48;
+const PARTICLE_LIFETIME: f32 = 0.35; // In s
+const PARTICLE_LENGTH: f32 = 5.;
+const PARTICLE_ENERGY_RATIO: f32 = 0.6;
+
+// Spark spawned when two dots hit, it doesn't collide nor connect
+#[derive(Component)]
+pub(crate) struct Particle;
+
+// Time left before the entity is despawned, on the sparks and the dots spawned with
+// `SimuConf.dot_lifetime`
+#[derive(Component, Deref, DerefMut)]
+pub(crate) struct Lifetime(pub(crate) Timer);
+
+pub(crate) fn spawn_collision_particles(
+    mut commands: Commands,
+    mut collisions: EventReader<DotCollision>,
+    mut rng: ResMut<DotRng>,
+) {
+    let mut spawned = 0;
+
+    for collision in collisions.read() {
+        if spawned >= MAX_PARTICLES_PER_FRAME {
+            // Drain the rest of the events so they aren't handled next frame
+            collisions.clear();
+            break;
+        }
+
+        // Sparks fly sideways from the impact, perpendicular to the collision normal
+        let tangent = collision.normal.perp();
+        let speed = collision.impact_speed * PARTICLE_ENERGY_RATIO;
+        for i in 0..PARTICLES_PER_COLLISION {
+            let side = if i % 2 == 0 { 1. } else { -1. };
+            let direction =
+                (tangent * side + collision.normal * rng.gen_range(-0.5..0.5)).normalize_or_zero();
+            commands.spawn((
+                Particle,
+                Lifetime(Timer::from_seconds(PARTICLE_LIFETIME, TimerMode::Once)),
+                Transform::from_translation(collision.position.extend(2.)),
+                Velocity(direction * speed * rng.gen_range(0.5..1.)),
+            ));
+        }
+        spawned += PARTICLES_PER_COLLISION;
+    }
+}
+
+// The dots are deleted by `expire_dots`, which counts them
+pub(crate) fn tick_lifetimes(
+    mut query: Query<(Entity, &mut Lifetime), Without<Dot>>,
+    mut commands: Commands,
+    time: Res<Time>,
+) {
+    for (entity, mut lifetime) in &mut query {
+        if lifetime.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+pub(crate) fn draw_particles(
+    mut gizmos: Gizmos,
+    query: Query<(&Transform, &Velocity, &Lifetime), With<Particle>>,
+    theme: Res<Theme>,
+) {
+    for (transform, velocity, lifetime) in &query {
+        let position = Vec2::new(transform.translation.x, transform.translation.y);
+        let tail = position - velocity.normalize_or_zero() * PARTICLE_LENGTH;
+        let alpha = 1. - lifetime.fraction();
+        gizmos.line_2d(position, tail, theme.palette().spark.with_a(alpha));
+    }
+}
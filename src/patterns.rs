@@ -0,0 +1,84 @@
+use bevy::prelude::*;
+use std::f32::consts::{PI, TAU};
+
+use crate::actions::SimAction;
+use crate::dots::{random_scale, spawn_dot, DotAssets, DotRng, NewDot};
+use crate::SimuConf;
+
+// Arrangements of dots spawned at once around the cursor, to watch the structure
+// dissolve
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Pattern {
+    // `SimuConf.grid_columns` × `SimuConf.grid_rows` dots
+    Grid,
+    // `SimuConf.ring_dots` dots on a circle
+    Ring,
+    // `SimuConf.spiral_dots` dots on an Archimedean spiral, the turns
+    // `SimuConf.pattern_spacing` apart
+    Spiral,
+}
+
+impl Pattern {
+    // Offsets of the dots from the center of the pattern, every neighbour about
+    // `SimuConf.pattern_spacing` apart
+    fn offsets(self, simu_conf: &SimuConf) -> Vec<Vec2> {
+        let spacing = simu_conf.pattern_spacing;
+        match self {
+            Pattern::Grid => {
+                let (columns, rows) = (simu_conf.grid_columns, simu_conf.grid_rows);
+                let half_size = Vec2::new(columns as f32 - 1., rows as f32 - 1.) / 2.;
+                (0..rows)
+                    .flat_map(|row| (0..columns).map(move |column| (column, row)))
+                    .map(|(column, row)| {
+                        (Vec2::new(column as f32, row as f32) - half_size) * spacing
+                    })
+                    .collect()
+            }
+            Pattern::Ring => {
+                let count = simu_conf.ring_dots;
+                let radius = spacing * count as f32 / TAU;
+                (0..count)
+                    .map(|i| Vec2::from_angle(TAU * i as f32 / count as f32) * radius)
+                    .collect()
+            }
+            Pattern::Spiral => {
+                // r = b θ, the arc length to θ being about b θ² / 2
+                let b = spacing / TAU;
+                (0..simu_conf.spiral_dots)
+                    .map(|i| {
+                        let angle = (4. * PI * i as f32).sqrt();
+                        Vec2::from_angle(angle) * b * angle
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+// Spawns the pattern of the action around its position, at rest or turning around
+// its center at `SimuConf.pattern_spin` rad/s
+pub(crate) fn spawn_patterns(
+    mut actions: EventReader<SimAction>,
+    mut commands: Commands,
+    dot_assets: Res<DotAssets>,
+    mut simu_conf: ResMut<SimuConf>,
+    mut rng: ResMut<DotRng>,
+) {
+    for action in actions.read() {
+        let SimAction::SpawnPattern(pattern, position, spin) = *action else {
+            continue;
+        };
+        let angular_speed = if spin { simu_conf.pattern_spin } else { 0. };
+        let offsets = pattern.offsets(&simu_conf);
+        for offset in &offsets {
+            let dot = NewDot {
+                position: position + *offset,
+                velocity: offset.perp() * angular_speed,
+                age: 0.,
+                scale: random_scale(&mut **rng, &simu_conf),
+            };
+            spawn_dot(&mut commands, &dot_assets, &simu_conf, dot);
+        }
+        simu_conf.number_of_dots += offsets.len() as u32;
+    }
+}
@@ -0,0 +1,751 @@
+use bevy::prelude::*;
+use bevy::utils::Instant;
+use bevy::window::{PrimaryWindow, WindowResized};
+use serde::{Deserialize, Serialize};
+
+use crate::actions::SimAction;
+use crate::arena::clamp_and_reflect_polygon;
+use crate::broadphase::NeighbourSearch;
+use crate::connections::{Connections, PerfStats};
+use crate::dots::{Dot, DotScale, Mass, Pinned, Velocity};
+use crate::player::Player;
+use crate::theme::Theme;
+use crate::three_d::{depth_of, Depth};
+use crate::SimuConf;
+
+const ATTRACTOR_MAX_ACCELERATION: f32 = 2_500.;
+// The outline of the world stays faint behind the connections
+const BOUNDS_ALPHA: f32 = 0.4;
+const ARENA_SEGMENTS: usize = 128;
+// How fast the walls close in on a smaller window, in 1/s
+const BOUNDS_EASING: f32 = 8.;
+// The walls snap to the window once they are that close
+const BOUNDS_SNAP_DISTANCE: f32 = 0.5; // In px
+
+// Turn between the directions dots at the same place are pushed apart along, the
+// golden angle spreads them evenly
+const STACKED_DOTS_ANGLE: f32 = 2.399_963; // In radians
+
+// Sent by `apply_dot_dot_collision` for every impact between two dots
+#[derive(Event)]
+pub(crate) struct DotCollision {
+    pub(crate) position: Vec2,
+    pub(crate) normal: Vec2,
+    pub(crate) impact_speed: f32,
+}
+
+// What happens to the dots reaching the edges of `WorldBounds`
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Reflect, Serialize, Deserialize)]
+pub enum BoundaryMode {
+    // They bounce back
+    #[default]
+    Walls,
+    // They come back from the opposite edge. The connections are only made between
+    // dots close on screen, so there is no line across the seam.
+    Wrap,
+    // They bounce inside a circle of `SimuConf.arena_radius`, on the center of the
+    // bounds
+    Circle,
+    // They bounce inside the outline of `SimuConf.arena_polygon`, like walls while
+    // there is none
+    Polygon,
+}
+
+impl BoundaryMode {
+    pub(crate) fn next(self) -> Self {
+        match self {
+            BoundaryMode::Walls => BoundaryMode::Wrap,
+            BoundaryMode::Wrap => BoundaryMode::Circle,
+            BoundaryMode::Circle => BoundaryMode::Polygon,
+            BoundaryMode::Polygon => BoundaryMode::Walls,
+        }
+    }
+}
+
+// Area the dots are kept in, it matches the window unless `SimuConf.world_size` is
+// set. When the window shrinks, the walls close in over a few frames and push the
+// dots back instead of leaving them outside.
+#[derive(Resource, Default, Deref)]
+pub(crate) struct WorldBounds(Rect);
+
+// Whether the outline of the bounds is drawn. Until it's toggled, it is only drawn
+// when the bounds aren't the edges of the window.
+#[derive(Resource, Default)]
+pub(crate) struct BoundsOutline(Option<bool>);
+
+impl BoundsOutline {
+    pub(crate) fn shown(&self, simu_conf: &SimuConf) -> bool {
+        self.0.unwrap_or(
+            simu_conf.world_size.is_some()
+                || matches!(
+                    simu_conf.boundary_mode,
+                    BoundaryMode::Circle | BoundaryMode::Polygon
+                ),
+        )
+    }
+}
+
+// Total kinetic energy of the dots (1/2 * m * v^2, unit mass without `Mass`), used
+// to check that the physics doesn't leak or create energy
+#[derive(Resource, Default)]
+pub(crate) struct EnergyDiagnostic {
+    pub(crate) enabled: bool,
+    pub(crate) kinetic: f32,
+}
+
+pub(crate) fn apply_dot_velocity(
+    mut query: Query<(&mut Transform, &Velocity)>,
+    time: Res<Time>,
+    simu_conf: ResMut<SimuConf>,
+) {
+    if simu_conf.freeze_dots {
+        return;
+    }
+
+    for (mut transform, velocity) in &mut query {
+        transform.translation.x += velocity.x * simu_conf.speed * time.delta_seconds();
+        transform.translation.y += velocity.y * simu_conf.speed * time.delta_seconds();
+    }
+}
+
+// Pulls the dots toward the target of `SimAction::Attract` (or pushes them away),
+// the force decreases with the distance and is capped so dots near the target
+// don't explode
+pub(crate) fn apply_attractor(
+    mut actions: EventReader<SimAction>,
+    mut query: Query<(&Transform, &mut Velocity, &Mass), With<Dot>>,
+    time: Res<Time>,
+    simu_conf: Res<SimuConf>,
+) {
+    for action in actions.read() {
+        let SimAction::Attract { target, repel } = *action else {
+            continue;
+        };
+        if simu_conf.freeze_dots {
+            continue;
+        }
+
+        let sign = if repel { -1. } else { 1. };
+        let dt = simu_conf.speed * time.delta_seconds();
+
+        for (transform, mut velocity, mass) in &mut query {
+            let delta = target - Vec2::new(transform.translation.x, transform.translation.y);
+            let dist = delta.length();
+            if dist == 0. {
+                continue;
+            }
+            let acceleration =
+                (simu_conf.attractor_strength / dist).min(ATTRACTOR_MAX_ACCELERATION);
+            velocity.0 += delta / dist * acceleration / **mass * sign * dt;
+        }
+    }
+}
+
+// Only the dots near the target of `SimAction::Repulse` are pushed away, harder the
+// closer they are, which carves a hole in the swarm. `SimAction::Herd` pulls them
+// instead, so the dots around the cursor follow it.
+pub(crate) fn apply_cursor_fields(
+    mut actions: EventReader<SimAction>,
+    mut query: Query<(&Transform, &mut Velocity, &Mass), With<Dot>>,
+    time: Res<Time>,
+    simu_conf: Res<SimuConf>,
+) {
+    for action in actions.read() {
+        let (target, sign, strength, radius) = match *action {
+            SimAction::Repulse(target) => (
+                target,
+                1.,
+                simu_conf.repulsion_strength,
+                simu_conf.repulsion_radius,
+            ),
+            SimAction::Herd(target) => {
+                (target, -1., simu_conf.herd_strength, simu_conf.herd_radius)
+            }
+            _ => continue,
+        };
+        if simu_conf.freeze_dots || radius <= 0. {
+            continue;
+        }
+
+        let dt = simu_conf.speed * time.delta_seconds();
+
+        for (transform, mut velocity, mass) in &mut query {
+            let delta = Vec2::new(transform.translation.x, transform.translation.y) - target;
+            let dist = delta.length();
+            if dist == 0. || dist >= radius {
+                continue;
+            }
+            let acceleration = strength * (1. - dist / radius) / **mass;
+            velocity.0 += delta / dist * acceleration * sign * dt;
+        }
+    }
+}
+
+pub(crate) fn apply_gravity(
+    mut query: Query<&mut Velocity, With<Dot>>,
+    time: Res<Time>,
+    simu_conf: Res<SimuConf>,
+) {
+    if simu_conf.freeze_dots {
+        return;
+    }
+    let dt = simu_conf.speed * time.delta_seconds();
+    for mut velocity in &mut query {
+        velocity.y -= simu_conf.gravity_strength * dt;
+    }
+}
+
+pub(crate) fn apply_damping(
+    mut query: Query<&mut Velocity, With<Dot>>,
+    time: Res<Time>,
+    simu_conf: Res<SimuConf>,
+) {
+    if simu_conf.freeze_dots {
+        return;
+    }
+    let factor = (-simu_conf.damping * simu_conf.speed * time.delta_seconds()).exp();
+    let max_speed = simu_conf.max_spawn_speed();
+    for mut velocity in &mut query {
+        // A negative damping stops speeding up the dots already at the top speed
+        if factor > 1. && velocity.length() >= max_speed {
+            continue;
+        }
+        velocity.0 *= factor;
+    }
+}
+
+// Every connection of the last frame pulls its two dots toward
+// `SimuConf.spring_rest_length`, or pushes them apart when they are closer. The
+// connections index the dots of the same `NeighbourSearch`, a dot despawned since
+// is skipped. The heavier dot of the pair moves less.
+pub(crate) fn apply_springs(
+    connections: Res<Connections>,
+    search: Res<NeighbourSearch>,
+    mut query: Query<(&Transform, &mut Velocity, &Mass), With<Dot>>,
+    time: Res<Time>,
+    simu_conf: Res<SimuConf>,
+) {
+    if simu_conf.freeze_dots {
+        return;
+    }
+    let dt = simu_conf.speed * time.delta_seconds();
+    let entities = search.entities();
+
+    for connection in connections.iter() {
+        let [i, j] = connection.dots;
+        let (Some(&first), Some(&second)) = (entities.get(i), entities.get(j)) else {
+            continue;
+        };
+        let Ok(
+            [(first, mut first_velocity, first_mass), (second, mut second_velocity, second_mass)],
+        ) = query.get_many_mut([first, second])
+        else {
+            continue;
+        };
+        let delta = second.translation.truncate() - first.translation.truncate();
+        let dist = delta.length();
+        if dist == 0. {
+            continue;
+        }
+        let stretch = dist - simu_conf.spring_rest_length;
+        let change = delta / dist * simu_conf.spring_stiffness * stretch * dt;
+        first_velocity.0 += change / **first_mass;
+        second_velocity.0 -= change / **second_mass;
+    }
+}
+
+pub(crate) fn compute_energy(
+    query: Query<(&Velocity, Option<&Mass>), With<Dot>>,
+    mut energy: ResMut<EnergyDiagnostic>,
+) {
+    energy.kinetic = query
+        .iter()
+        .map(|(velocity, mass)| 0.5 * mass.map_or(1., |mass| **mass) * velocity.length_squared())
+        .sum();
+}
+
+// The target is only computed again when the window is resized or the settings
+// change. It is reached at once when it grows, the dots can't be outside.
+pub(crate) fn update_world_bounds(
+    mut resized: EventReader<WindowResized>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    simu_conf: Res<SimuConf>,
+    time: Res<Time>,
+    mut target: Local<Rect>,
+    mut bounds: ResMut<WorldBounds>,
+) {
+    if resized.read().count() > 0 || simu_conf.is_changed() {
+        let size = match simu_conf.world_size {
+            Some(size) => Some(size),
+            None => window
+                .get_single()
+                .ok()
+                .map(|window| Vec2::new(window.resolution.width(), window.resolution.height())),
+        };
+        // A minimized window can report an empty size, keep the last bounds instead
+        // of squashing every dot in the middle of the screen
+        if let Some(half_size) = size.map(|size| size / 2.) {
+            if half_size.min_element() > 0. {
+                *target = Rect::from_center_half_size(simu_conf.world_offset, half_size);
+            }
+        }
+    }
+    if target.is_empty() || bounds.0 == *target {
+        return;
+    }
+
+    let grows = target.contains(bounds.min) && target.contains(bounds.max);
+    let close = bounds.min.distance(target.min) < BOUNDS_SNAP_DISTANCE
+        && bounds.max.distance(target.max) < BOUNDS_SNAP_DISTANCE;
+    if bounds.is_empty() || grows || close {
+        bounds.0 = *target;
+        return;
+    }
+    let t = 1. - (-BOUNDS_EASING * time.delta_seconds()).exp();
+    bounds.0 = Rect::from_corners(
+        bounds.min.lerp(target.min, t),
+        bounds.max.lerp(target.max, t),
+    );
+}
+
+pub(crate) fn toggle_bounds_outline(mut outline: ResMut<BoundsOutline>, simu_conf: Res<SimuConf>) {
+    outline.0 = Some(!outline.shown(&simu_conf));
+}
+
+// The edges of the world, or the outline of the arena
+pub(crate) fn draw_world_bounds(
+    bounds: Res<WorldBounds>,
+    simu_conf: Res<SimuConf>,
+    theme: Res<Theme>,
+    mut gizmos: Gizmos,
+) {
+    let color = theme.palette().line_far.with_a(BOUNDS_ALPHA);
+    match simu_conf.boundary_mode {
+        BoundaryMode::Circle => {
+            gizmos
+                .circle_2d(bounds.center(), simu_conf.arena_radius, color)
+                .segments(ARENA_SEGMENTS);
+        }
+        BoundaryMode::Polygon if simu_conf.arena_polygon.len() >= 3 => {
+            let polygon = &simu_conf.arena_polygon;
+            gizmos.linestrip_2d(polygon.iter().chain(polygon.first()).copied(), color);
+        }
+        _ => gizmos.rect_2d(bounds.center(), 0., bounds.size(), color),
+    }
+}
+
+// Clamps both axes at once and reflects the velocity of every violated axis
+// toward the inside, so a dot can't stay stuck on a wall after being moved out. The
+// reflected component is scaled by `restitution`, only when it went outward so a
+// dot already bouncing back isn't slowed twice.
+fn clamp_and_reflect(position: &mut Vec3, velocity: &mut Vec2, bounds: Rect, restitution: f32) {
+    if position.x <= bounds.min.x {
+        position.x = bounds.min.x;
+        if velocity.x < 0. {
+            velocity.x *= -restitution;
+        }
+    } else if position.x >= bounds.max.x {
+        position.x = bounds.max.x;
+        if velocity.x > 0. {
+            velocity.x *= -restitution;
+        }
+    }
+
+    if position.y <= bounds.min.y {
+        position.y = bounds.min.y;
+        if velocity.y < 0. {
+            velocity.y *= -restitution;
+        }
+    } else if position.y >= bounds.max.y {
+        position.y = bounds.max.y;
+        if velocity.y > 0. {
+            velocity.y *= -restitution;
+        }
+    }
+}
+
+// Moves a dot out of the bounds back to the opposite edge, keeping its velocity
+fn wrap(position: &mut Vec3, bounds: Rect) {
+    let size = bounds.size();
+    position.x = bounds.min.x + (position.x - bounds.min.x).rem_euclid(size.x);
+    position.y = bounds.min.y + (position.y - bounds.min.y).rem_euclid(size.y);
+}
+
+// Reflects the velocity around `normal` (pointing out of the arena) if it goes
+// outward, the reflected part scaled by `restitution`
+pub(crate) fn bounce(velocity: &mut Vec2, normal: Vec2, restitution: f32) {
+    let outward_speed = velocity.dot(normal);
+    if outward_speed > 0. {
+        *velocity -= normal * outward_speed * (1. + restitution);
+    }
+}
+
+// Moves a dot out of the circle back onto it, and bounces it on the circle
+fn clamp_and_reflect_circle(
+    position: &mut Vec3,
+    velocity: &mut Vec2,
+    center: Vec2,
+    radius: f32,
+    restitution: f32,
+) {
+    let offset = position.truncate() - center;
+    if offset.length_squared() <= radius * radius {
+        return;
+    }
+    let normal = offset.normalize_or_zero();
+    let inside = center + normal * radius;
+    position.x = inside.x;
+    position.y = inside.y;
+    bounce(velocity, normal, restitution);
+}
+
+fn keep_in_bounds(position: &mut Vec3, velocity: &mut Vec2, bounds: Rect, simu_conf: &SimuConf) {
+    let restitution = simu_conf.restitution;
+    match simu_conf.boundary_mode {
+        BoundaryMode::Walls => clamp_and_reflect(position, velocity, bounds, restitution),
+        BoundaryMode::Polygon if simu_conf.arena_polygon.len() < 3 => {
+            clamp_and_reflect(position, velocity, bounds, restitution);
+        }
+        BoundaryMode::Polygon => {
+            let polygon = &simu_conf.arena_polygon;
+            clamp_and_reflect_polygon(position, velocity, polygon, restitution);
+        }
+        BoundaryMode::Wrap => wrap(position, bounds),
+        BoundaryMode::Circle => {
+            let radius = simu_conf.arena_radius;
+            clamp_and_reflect_circle(position, velocity, bounds.center(), radius, restitution);
+        }
+    }
+}
+
+// With gravity, a bounce on the floor loses some more energy so the dots end up
+// resting on it
+pub(crate) fn apply_dot_collision(
+    mut query: Query<(&mut Transform, &mut Velocity), With<Dot>>,
+    bounds: Res<WorldBounds>,
+    simu_conf: Res<SimuConf>,
+) {
+    for (mut transform, mut velocity) in &mut query {
+        keep_in_bounds(
+            &mut transform.translation,
+            &mut velocity.0,
+            bounds.0,
+            &simu_conf,
+        );
+        if simu_conf.boundary_mode == BoundaryMode::Walls
+            && simu_conf.gravity
+            && transform.translation.y <= bounds.min.y
+        {
+            velocity.y *= simu_conf.floor_restitution;
+        }
+    }
+}
+
+// Buffers reused by `merge_dots` from one frame to the next
+#[derive(Default)]
+pub(crate) struct MergeBuffers {
+    search: NeighbourSearch,
+    positions: Vec<Vec2>,
+    depths: Vec<f32>,
+    velocities: Vec<Vec2>,
+    masses: Vec<f32>,
+    // False once the dot was merged into another one
+    alive: Vec<bool>,
+    // Pinned dots and the player, never merged
+    pinned: Vec<bool>,
+}
+
+// Two touching dots become one, where their center of mass was. The area (the mass)
+// and the momentum are kept, the heavier dot takes the other one in, so spawning
+// for a while grows a few blobs. A dot merges once per frame at most, piles merge
+// over a few frames. The pinned dots and the player aren't merged.
+#[allow(clippy::type_complexity)]
+pub(crate) fn merge_dots(
+    mut query: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut Velocity,
+            &mut DotScale,
+            &mut Mass,
+            Has<Pinned>,
+            Has<Player>,
+            Option<&Depth>,
+        ),
+        With<Dot>,
+    >,
+    mut commands: Commands,
+    mut simu_conf: ResMut<SimuConf>,
+    mut buffers: Local<MergeBuffers>,
+) {
+    let MergeBuffers {
+        search,
+        positions,
+        depths,
+        velocities,
+        masses,
+        alive,
+        pinned,
+    } = &mut *buffers;
+    positions.clear();
+    depths.clear();
+    velocities.clear();
+    masses.clear();
+    alive.clear();
+    pinned.clear();
+    for (_, transform, velocity, _, mass, is_pinned, is_player, depth) in &query {
+        positions.push(transform.translation.truncate());
+        depths.push(depth_of(depth, &simu_conf));
+        velocities.push(velocity.0);
+        masses.push(**mass);
+        alive.push(true);
+        pinned.push(is_pinned || is_player);
+    }
+    // A dot of mass 1 has a radius of `dot_size`
+    let radius = |mass: f32| simu_conf.dot_size * mass.sqrt();
+    let max_dist = radius(masses.iter().copied().fold(1., f32::max)) * 2.;
+
+    search.rebuild(simu_conf.broadphase, max_dist, positions.iter().copied());
+    search.for_each_pair(|i, j| {
+        if !alive[i] || !alive[j] || pinned[i] || pinned[j] {
+            return;
+        }
+        let dist = positions[i]
+            .distance(positions[j])
+            .hypot(depths[j] - depths[i]);
+        if dist >= radius(masses[i]) + radius(masses[j]) {
+            return;
+        }
+        let (kept, merged) = if masses[i] >= masses[j] {
+            (i, j)
+        } else {
+            (j, i)
+        };
+        let mass = masses[kept] + masses[merged];
+        positions[kept] =
+            (positions[kept] * masses[kept] + positions[merged] * masses[merged]) / mass;
+        velocities[kept] =
+            (velocities[kept] * masses[kept] + velocities[merged] * masses[merged]) / mass;
+        masses[kept] = mass;
+        alive[merged] = false;
+    });
+
+    // The query is iterated in the same order as when the buffers were filled
+    for (i, (dot, mut transform, mut velocity, mut scale, mut mass, ..)) in
+        query.iter_mut().enumerate()
+    {
+        if !alive[i] {
+            commands.entity(dot).despawn();
+            simu_conf.number_of_dots -= 1;
+            continue;
+        }
+        if **mass != masses[i] {
+            transform.translation = positions[i].extend(transform.translation.z);
+            velocity.0 = velocities[i];
+            *mass = Mass(masses[i]);
+            *scale = DotScale(masses[i].sqrt());
+        }
+    }
+}
+
+// Buffers reused by `apply_dot_dot_collision` from one frame to the next
+#[derive(Default)]
+pub(crate) struct CollisionBuffers {
+    search: NeighbourSearch,
+    positions: Vec<Vec3>,
+    depths: Vec<f32>,
+    velocities: Vec<Vec2>,
+    radii: Vec<f32>,
+    masses: Vec<f32>,
+    pinned: Vec<bool>,
+}
+
+// Elastic collision between dots of different sizes and masses. A pinned dot acts
+// like an infinite mass, the other dot bounces on it.
+#[allow(clippy::type_complexity)]
+pub(crate) fn apply_dot_dot_collision(
+    mut query: Query<
+        (
+            &mut Transform,
+            &mut Velocity,
+            &DotScale,
+            &Mass,
+            Has<Pinned>,
+            Option<&Depth>,
+        ),
+        With<Dot>,
+    >,
+    simu_conf: Res<SimuConf>,
+    bounds: Res<WorldBounds>,
+    mut collisions: EventWriter<DotCollision>,
+    mut buffers: Local<CollisionBuffers>,
+    mut stats: ResMut<PerfStats>,
+) {
+    let start = Instant::now();
+    let CollisionBuffers {
+        search,
+        positions,
+        depths,
+        velocities,
+        radii,
+        masses,
+        pinned,
+    } = &mut *buffers;
+    positions.clear();
+    depths.clear();
+    velocities.clear();
+    radii.clear();
+    masses.clear();
+    pinned.clear();
+    for (transform, velocity, scale, mass, is_pinned, depth) in &query {
+        positions.push(transform.translation);
+        depths.push(depth_of(depth, &simu_conf));
+        velocities.push(velocity.0);
+        radii.push(simu_conf.dot_size * **scale);
+        masses.push(**mass);
+        pinned.push(is_pinned);
+    }
+    // Distance under which the two largest dots touch, the cells can't be smaller
+    let max_dist = radii.iter().copied().fold(simu_conf.dot_size, f32::max) * 2.;
+
+    // A single pass can leave piled up dots overlapping (separating a pair may push
+    // one of them into a third dot), every extra pass relaxes the pile a bit more
+    for _ in 0..simu_conf.collision_iterations.max(1) {
+        // Rebuilt since the previous pass moved the dots
+        search.rebuild(
+            simu_conf.broadphase,
+            max_dist,
+            positions.iter().map(|position| position.truncate()),
+        );
+        search.for_each_pair(|i, j| {
+            let delta = (positions[j] - positions[i]).truncate();
+            let dist = delta.length();
+            let min_dist = radii[i] + radii[j];
+            // In 3D, the dots pass each other when their depths are far enough apart.
+            // They are still only pushed apart on the plane.
+            let gap = dist.hypot(depths[j] - depths[i]);
+            if gap >= min_dist {
+                return;
+            }
+            // Share of the correction and of the impulse taken by each dot, the
+            // lighter one moves more
+            let (share_i, share_j) = match (pinned[i], pinned[j]) {
+                (true, true) => return,
+                (true, false) => (0., 1.),
+                (false, true) => (1., 0.),
+                (false, false) => {
+                    let (mass_i, mass_j) = (masses[i], masses[j]);
+                    (mass_j / (mass_i + mass_j), mass_i / (mass_i + mass_j))
+                }
+            };
+
+            // Push both dots apart so they stop overlapping. Dots at the same place
+            // have no direction between them, each pair gets its own.
+            let normal = if dist > 0. {
+                delta / dist
+            } else {
+                Vec2::from_angle((i + j) as f32 * STACKED_DOTS_ANGLE)
+            };
+            let correction = normal * (min_dist - gap);
+            positions[i] -= (correction * share_i).extend(0.);
+            positions[j] += (correction * share_j).extend(0.);
+
+            // Elastic impulse along the normal, unless they already separate. Dots of
+            // equal mass exchange their velocity components.
+            let impact_speed = (velocities[i] - velocities[j]).dot(normal);
+            if impact_speed <= 0. {
+                return;
+            }
+            velocities[i] -= normal * impact_speed * 2. * share_i;
+            velocities[j] += normal * impact_speed * 2. * share_j;
+
+            collisions.send(DotCollision {
+                position: positions[i].truncate() + normal * radii[i],
+                normal,
+                impact_speed,
+            });
+        });
+
+        // Separating dots piled against a wall must not push them through it
+        for (position, velocity) in positions.iter_mut().zip(velocities.iter_mut()) {
+            keep_in_bounds(position, velocity, bounds.0, &simu_conf);
+        }
+    }
+
+    // The query is iterated in the same order as when the buffers were filled
+    for ((mut transform, mut velocity, ..), (position, new_velocity)) in query
+        .iter_mut()
+        .zip(positions.iter().zip(velocities.iter()))
+    {
+        transform.translation = *position;
+        velocity.0 = *new_velocity;
+    }
+    stats.timings.collisions = start.elapsed();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clamps_and_reflects_past_a_corner() {
+        let bounds = Rect::new(-100., -50., 100., 50.);
+        let mut position = Vec3::new(120., -70., 1.);
+        let mut velocity = Vec2::new(30., -40.);
+        clamp_and_reflect(&mut position, &mut velocity, bounds, 0.5);
+        assert_eq!(position, Vec3::new(100., -50., 1.));
+        assert_eq!(velocity, Vec2::new(-15., 20.));
+    }
+
+    // Three dots stacked in the corner of the world, after a single frame of
+    // `iterations` collision passes. Whether every pair ends up apart, all of them
+    // inside the world.
+    fn separated_in_one_frame(iterations: u32) -> bool {
+        let bounds = Rect::new(0., 0., 200., 100.);
+        let mut app = App::new();
+        app.add_event::<DotCollision>()
+            .insert_resource(SimuConf {
+                collision_iterations: iterations,
+                ..default()
+            })
+            .init_resource::<PerfStats>()
+            .insert_resource(WorldBounds(bounds))
+            .add_systems(Update, apply_dot_dot_collision);
+        for _ in 0..3 {
+            app.world.spawn((
+                Dot,
+                Transform::from_translation(bounds.min.extend(1.)),
+                Velocity(Vec2::ZERO),
+                DotScale(1.),
+                Mass(1.),
+            ));
+        }
+        app.update();
+
+        let min_dist = 2. * app.world.resource::<SimuConf>().dot_size;
+        let mut dots = app.world.query_filtered::<&Transform, With<Dot>>();
+        let positions: Vec<Vec2> = dots
+            .iter(&app.world)
+            .map(|transform| transform.translation.truncate())
+            .collect();
+        for position in &positions {
+            assert!(bounds.contains(*position), "{position} out of {bounds:?}");
+        }
+        positions.iter().enumerate().all(|(i, position)| {
+            positions[i + 1..]
+                .iter()
+                .all(|other| position.distance(*other) >= min_dist - 1e-3)
+        })
+    }
+
+    #[test]
+    fn one_pass_leaves_stacked_dots_overlapping() {
+        assert!(!separated_in_one_frame(1));
+    }
+
+    #[test]
+    fn several_passes_separate_stacked_dots() {
+        // 14 are enough for this pile
+        assert!(separated_in_one_frame(20));
+    }
+}
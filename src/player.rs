@@ -0,0 +1,67 @@
+use bevy::prelude::*;
+
+use crate::camera::{move_direction, MainCamera};
+use crate::dots::{spawn_dot, DotAssets, NewDot, Velocity};
+use crate::keys::KeyBindings;
+use crate::particles::Lifetime;
+use crate::SimuConf;
+
+// How fast the player slows down once the move keys are released, in 1/s
+const PLAYER_BRAKING: f32 = 3.;
+
+// Dot steered with the move keys instead of the camera. It is connected and collides
+// like the others, but is never merged and doesn't expire.
+#[derive(Component)]
+pub(crate) struct Player;
+
+// Spawns the player at the center of the view, or removes it
+pub(crate) fn toggle_player(
+    player: Query<Entity, With<Player>>,
+    camera: Query<&Transform, With<MainCamera>>,
+    mut commands: Commands,
+    dot_assets: Res<DotAssets>,
+    mut simu_conf: ResMut<SimuConf>,
+) {
+    if let Ok(player) = player.get_single() {
+        commands.entity(player).despawn();
+        simu_conf.number_of_dots -= 1;
+        return;
+    }
+    let dot = NewDot {
+        position: camera.single().translation.truncate(),
+        velocity: Vec2::ZERO,
+        age: 0.,
+        scale: 1.,
+    };
+    let player = spawn_dot(&mut commands, &dot_assets, &simu_conf, dot);
+    commands
+        .entity(player)
+        .insert((Player, dot_assets.player_material.clone()))
+        .remove::<Lifetime>();
+    simu_conf.number_of_dots += 1;
+}
+
+// Accelerates the player toward the move keys, up to `SimuConf.player_speed`, it
+// coasts to a stop without them
+pub(crate) fn move_player(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut player: Query<&mut Velocity, With<Player>>,
+    simu_conf: Res<SimuConf>,
+    time: Res<Time>,
+) {
+    let Ok(mut velocity) = player.get_single_mut() else {
+        return;
+    };
+    if simu_conf.freeze_dots {
+        return;
+    }
+    let delta = simu_conf.speed * time.delta_seconds();
+    let direction = move_direction(&keyboard_input, &bindings);
+    if direction == Vec2::ZERO {
+        **velocity *= (-PLAYER_BRAKING * delta).exp();
+    } else {
+        **velocity += direction * simu_conf.player_acceleration * delta;
+        **velocity = velocity.clamp_length_max(simu_conf.player_speed);
+    }
+}
@@ -0,0 +1,225 @@
+use bevy::asset::load_internal_asset;
+use bevy::core_pipeline::core_2d::graph::{Core2d, Node2d};
+use bevy::core_pipeline::fullscreen_vertex_shader::fullscreen_shader_vertex_state;
+use bevy::ecs::query::QueryItem;
+use bevy::prelude::*;
+use bevy::render::extract_component::{
+    ComponentUniforms, DynamicUniformIndex, ExtractComponentPlugin, UniformComponentPlugin,
+};
+use bevy::render::render_graph::{
+    NodeRunError, RenderGraphApp, RenderGraphContext, RenderLabel, ViewNode, ViewNodeRunner,
+};
+use bevy::render::render_resource::binding_types::{sampler, texture_2d, uniform_buffer};
+use bevy::render::render_resource::*;
+use bevy::render::renderer::{RenderContext, RenderDevice};
+use bevy::render::texture::BevyDefault;
+use bevy::render::view::ViewTarget;
+use bevy::render::RenderApp;
+
+use crate::camera::{LinesCamera, MainCamera};
+use crate::SimuConf;
+
+const POST_FX_SHADER: Handle<Shader> =
+    Handle::weak_from_u128(0x8c4a_27e1_d9b3_4f60_9e15_3a7c_b2d8_41f6);
+
+pub(crate) use uniform::PostFxSettings;
+
+// `ShaderType` adds a check per field that rustc sees as never used
+#[allow(dead_code)]
+mod uniform {
+    use bevy::prelude::*;
+    use bevy::render::extract_component::ExtractComponent;
+    use bevy::render::render_resource::ShaderType;
+
+    // Strengths of the effects applied by `PostFxNode` to the image of a camera,
+    // once tone mapped
+    #[derive(Component, Clone, Copy, ExtractComponent, ShaderType)]
+    pub(crate) struct PostFxSettings {
+        pub(crate) vignette: f32,
+        pub(crate) chromatic_aberration: f32,
+        pub(crate) film_grain: f32,
+        pub(crate) time: f32,
+    }
+}
+
+// Fullscreen pass for the vignette, chromatic aberration and film grain of
+// `SimuConf`. A single pass draws the three, the ones at 0 are left out.
+pub(crate) struct PostFxPlugin;
+
+impl Plugin for PostFxPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(app, POST_FX_SHADER, "post_fx.wgsl", Shader::from_wgsl);
+        app.add_plugins((
+            ExtractComponentPlugin::<PostFxSettings>::default(),
+            UniformComponentPlugin::<PostFxSettings>::default(),
+        ));
+
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .add_render_graph_node::<ViewNodeRunner<PostFxNode>>(Core2d, PostFxLabel)
+            .add_render_graph_edges(
+                Core2d,
+                (
+                    Node2d::Tonemapping,
+                    PostFxLabel,
+                    Node2d::EndMainPassPostProcessing,
+                ),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.init_resource::<PostFxPipeline>();
+    }
+}
+
+// Like the bloom, only the last camera applies the effects, over everything drawn
+// before it
+#[allow(clippy::type_complexity)]
+pub(crate) fn update_post_fx(
+    mut commands: Commands,
+    simu_conf: Res<SimuConf>,
+    time: Res<Time>,
+    cameras: Query<(Entity, &Camera), Or<(With<MainCamera>, With<LinesCamera>)>>,
+) {
+    let enabled =
+        simu_conf.vignette > 0. || simu_conf.chromatic_aberration > 0. || simu_conf.film_grain > 0.;
+    let last_order = cameras.iter().map(|(_, camera)| camera.order).max();
+    for (entity, camera) in &cameras {
+        if enabled && Some(camera.order) == last_order {
+            commands.entity(entity).insert(PostFxSettings {
+                vignette: simu_conf.vignette,
+                chromatic_aberration: simu_conf.chromatic_aberration,
+                film_grain: simu_conf.film_grain,
+                time: time.elapsed_seconds_wrapped(),
+            });
+        } else {
+            commands.entity(entity).remove::<PostFxSettings>();
+        }
+    }
+}
+
+#[derive(Resource)]
+struct PostFxPipeline {
+    layout: BindGroupLayout,
+    sampler: Sampler,
+    // The view target is in HDR with the bloom or `SimuConf.hdr`
+    ldr_pipeline: CachedRenderPipelineId,
+    hdr_pipeline: CachedRenderPipelineId,
+}
+
+impl FromWorld for PostFxPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let device = world.resource::<RenderDevice>();
+        let layout = device.create_bind_group_layout(
+            "post_fx_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::FRAGMENT,
+                (
+                    texture_2d(TextureSampleType::Float { filterable: true }),
+                    sampler(SamplerBindingType::Filtering),
+                    uniform_buffer::<PostFxSettings>(true),
+                ),
+            ),
+        );
+        let sampler = device.create_sampler(&SamplerDescriptor::default());
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let queue_pipeline = |format| {
+            pipeline_cache.queue_render_pipeline(RenderPipelineDescriptor {
+                label: Some("post_fx_pipeline".into()),
+                layout: vec![layout.clone()],
+                push_constant_ranges: Vec::new(),
+                vertex: fullscreen_shader_vertex_state(),
+                fragment: Some(FragmentState {
+                    shader: POST_FX_SHADER,
+                    shader_defs: Vec::new(),
+                    entry_point: "fragment".into(),
+                    targets: vec![Some(ColorTargetState {
+                        format,
+                        blend: None,
+                        write_mask: ColorWrites::ALL,
+                    })],
+                }),
+                primitive: PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: MultisampleState::default(),
+            })
+        };
+        let ldr_pipeline = queue_pipeline(TextureFormat::bevy_default());
+        let hdr_pipeline = queue_pipeline(ViewTarget::TEXTURE_FORMAT_HDR);
+        PostFxPipeline {
+            layout,
+            sampler,
+            ldr_pipeline,
+            hdr_pipeline,
+        }
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+struct PostFxLabel;
+
+#[derive(Default)]
+struct PostFxNode;
+
+impl ViewNode for PostFxNode {
+    type ViewQuery = (
+        &'static ViewTarget,
+        &'static DynamicUniformIndex<PostFxSettings>,
+    );
+
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        (view_target, settings_index): QueryItem<Self::ViewQuery>,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let pipeline = world.resource::<PostFxPipeline>();
+        let pipeline_id = if view_target.is_hdr() {
+            pipeline.hdr_pipeline
+        } else {
+            pipeline.ldr_pipeline
+        };
+        let Some(render_pipeline) = world
+            .resource::<PipelineCache>()
+            .get_render_pipeline(pipeline_id)
+        else {
+            return Ok(());
+        };
+        let Some(settings) = world
+            .resource::<ComponentUniforms<PostFxSettings>>()
+            .uniforms()
+            .binding()
+        else {
+            return Ok(());
+        };
+
+        // Reads the image drawn so far and writes the other texture of the view
+        let post_process = view_target.post_process_write();
+        let bind_group = render_context.render_device().create_bind_group(
+            "post_fx_bind_group",
+            &pipeline.layout,
+            &BindGroupEntries::sequential((post_process.source, &pipeline.sampler, settings)),
+        );
+        let mut pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+            label: Some("post_fx_pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: post_process.destination,
+                resolve_target: None,
+                ops: Operations::default(),
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        pass.set_render_pipeline(render_pipeline);
+        pass.set_bind_group(0, &bind_group, &[settings_index.index()]);
+        pass.draw(0..3, 0..1);
+        Ok(())
+    }
+}
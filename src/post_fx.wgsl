@@ -0,0 +1,40 @@
+// Vignette, chromatic aberration and film grain over the whole image, a zero
+// strength leaves the effect out
+
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+
+struct PostFxSettings {
+    vignette: f32,
+    chromatic_aberration: f32,
+    film_grain: f32,
+    // In s, so the grain changes every frame
+    time: f32,
+}
+
+@group(0) @binding(0) var screen_texture: texture_2d<f32>;
+@group(0) @binding(1) var screen_sampler: sampler;
+@group(0) @binding(2) var<uniform> settings: PostFxSettings;
+
+fn hash(point: vec2<f32>) -> f32 {
+    return fract(sin(dot(point, vec2<f32>(12.9898, 78.233))) * 43758.5453);
+}
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let offset = in.uv - vec2<f32>(0.5);
+
+    // The red and blue channels drift apart toward the edges
+    let shift = offset * settings.chromatic_aberration;
+    let center = textureSample(screen_texture, screen_sampler, in.uv);
+    let red = textureSample(screen_texture, screen_sampler, in.uv + shift).r;
+    let blue = textureSample(screen_texture, screen_sampler, in.uv - shift).b;
+    var color = vec3<f32>(red, center.g, blue);
+
+    // `offset` is at most sqrt(0.5) long, in the corners
+    color *= max(1. - settings.vignette * dot(offset, offset) * 2., 0.);
+
+    let noise = hash(in.position.xy + fract(settings.time) * 1000.) - 0.5;
+    color = max(color + noise * settings.film_grain, vec3<f32>(0.));
+
+    return vec4<f32>(color, center.a);
+}
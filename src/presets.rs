@@ -0,0 +1,92 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+use crate::keys::{KeyBindings, SLOT_COUNT};
+use crate::SimuConf;
+
+const PRESETS_FILE_PATH: &str = "dot-network-presets.ron";
+
+// Settings saved under a name. The names can be changed in the file, a preset
+// stored again keeps its name.
+#[derive(Serialize, Deserialize, Clone)]
+struct Preset {
+    name: String,
+    simu_conf: SimuConf,
+}
+
+// Settings stored with Ctrl + number and recalled with the number alone, kept in
+// `PRESETS_FILE_PATH` between runs
+#[derive(Resource, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub(crate) struct Presets {
+    slots: [Option<Preset>; SLOT_COUNT],
+}
+
+impl Presets {
+    fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        let content = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(ron::from_str(&std::fs::read_to_string(path)?)?)
+    }
+}
+
+// Nothing to load until a first preset is stored
+pub(crate) fn load_presets(mut presets: ResMut<Presets>) {
+    if !std::path::Path::new(PRESETS_FILE_PATH).exists() {
+        return;
+    }
+    match Presets::load(PRESETS_FILE_PATH) {
+        Ok(loaded) => *presets = loaded,
+        Err(err) => error!("Couldn't load the presets from {PRESETS_FILE_PATH}: {err}"),
+    }
+}
+
+pub(crate) fn use_presets(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut presets: ResMut<Presets>,
+    mut simu_conf: ResMut<SimuConf>,
+) {
+    // Alt + number is for the camera bookmarks, Shift + number for the tools
+    if keyboard_input.any_pressed([
+        KeyCode::AltLeft,
+        KeyCode::AltRight,
+        KeyCode::ShiftLeft,
+        KeyCode::ShiftRight,
+    ]) {
+        return;
+    }
+    let store = keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    for (i, key) in bindings.slots().iter().enumerate() {
+        if !keyboard_input.just_pressed(*key) {
+            continue;
+        }
+        if store {
+            let name = match &presets.slots[i] {
+                Some(preset) => preset.name.clone(),
+                None => format!("Preset {}", i + 1),
+            };
+            info!("Settings stored as {name}");
+            presets.slots[i] = Some(Preset {
+                name,
+                simu_conf: simu_conf.clone(),
+            });
+            if let Err(err) = presets.save(PRESETS_FILE_PATH) {
+                error!("Couldn't save the presets to {PRESETS_FILE_PATH}: {err}");
+            }
+        } else if let Some(preset) = &presets.slots[i] {
+            info!("Switched to {}", preset.name);
+            // A different dot size is applied by `resize_dots`. The dots on screen
+            // stay, so does their count.
+            *simu_conf = SimuConf {
+                number_of_dots: simu_conf.number_of_dots,
+                ..preset.simu_conf.clone()
+            };
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use bevy::prelude::*;
+
+const QUADTREE_NODE_CAPACITY: usize = 8;
+const QUADTREE_MAX_DEPTH: u32 = 16; // Stops splitting dots piled on the same spot
+
+struct QuadNode {
+    bounds: Rect,
+    // The 4 children are stored next to each other, from the first one
+    first_child: Option<usize>,
+    points: Vec<usize>,
+}
+
+impl QuadNode {
+    fn new(bounds: Rect) -> Self {
+        QuadNode {
+            bounds,
+            first_child: None,
+            points: Vec::new(),
+        }
+    }
+}
+
+// Tree of nodes splitting in 4 once they hold too many points, so crowded areas
+// get small nodes while empty areas cost nothing
+#[derive(Default)]
+pub(crate) struct Quadtree {
+    nodes: Vec<QuadNode>,
+}
+
+impl Quadtree {
+    pub(crate) fn rebuild(&mut self, positions: &[Vec2]) {
+        self.nodes.clear();
+        let Some(&first) = positions.first() else {
+            return;
+        };
+        let bounds = positions
+            .iter()
+            .fold(Rect::from_corners(first, first), |bounds, &position| {
+                bounds.union_point(position)
+            });
+        // Padded so the root is never flat, even when every dot is aligned
+        self.nodes.push(QuadNode::new(bounds.inset(1.)));
+
+        for (i, &position) in positions.iter().enumerate() {
+            self.insert(i, position, positions);
+        }
+    }
+
+    fn child_index(bounds: Rect, position: Vec2) -> usize {
+        let center = bounds.center();
+        (position.x >= center.x) as usize + 2 * (position.y >= center.y) as usize
+    }
+
+    fn insert(&mut self, point: usize, position: Vec2, positions: &[Vec2]) {
+        let mut node = 0;
+        let mut depth = 0;
+        while let Some(first_child) = self.nodes[node].first_child {
+            node = first_child + Self::child_index(self.nodes[node].bounds, position);
+            depth += 1;
+        }
+
+        self.nodes[node].points.push(point);
+        if self.nodes[node].points.len() <= QUADTREE_NODE_CAPACITY || depth >= QUADTREE_MAX_DEPTH {
+            return;
+        }
+
+        // Split the full leaf and move its points down to the children
+        let bounds = self.nodes[node].bounds;
+        let center = bounds.center();
+        let first_child = self.nodes.len();
+        for (min, max) in [
+            (bounds.min, center),
+            (
+                Vec2::new(center.x, bounds.min.y),
+                Vec2::new(bounds.max.x, center.y),
+            ),
+            (
+                Vec2::new(bounds.min.x, center.y),
+                Vec2::new(center.x, bounds.max.y),
+            ),
+            (center, bounds.max),
+        ] {
+            self.nodes.push(QuadNode::new(Rect { min, max }));
+        }
+        self.nodes[node].first_child = Some(first_child);
+        for point in std::mem::take(&mut self.nodes[node].points) {
+            let child = first_child + Self::child_index(bounds, positions[point]);
+            self.nodes[child].points.push(point);
+        }
+    }
+
+    // Number of leaves holding at least one point
+    pub(crate) fn cell_count(&self) -> usize {
+        self.nodes
+            .iter()
+            .filter(|node| !node.points.is_empty())
+            .count()
+    }
+
+    // Calls `f` once for every pair of points whose distance along both axes is
+    // below `radius`. Returns the number of pairs.
+    pub(crate) fn for_each_pair(
+        &self,
+        radius: f32,
+        positions: &[Vec2],
+        mut f: impl FnMut(usize, usize),
+    ) -> usize {
+        let mut pairs = 0;
+        let mut stack = Vec::new();
+        for (i, &position) in positions.iter().enumerate() {
+            let area = Rect::from_center_half_size(position, Vec2::splat(radius));
+            stack.clear();
+            if !self.nodes.is_empty() {
+                stack.push(0);
+            }
+            while let Some(node) = stack.pop() {
+                let node = &self.nodes[node];
+                if node.bounds.intersect(area).is_empty() {
+                    continue;
+                }
+                if let Some(first_child) = node.first_child {
+                    stack.extend(first_child..first_child + 4);
+                    continue;
+                }
+                // Every pair is seen from both of its points, only keep one of them
+                for &j in node.points.iter().filter(|&&j| j > i) {
+                    if area.contains(positions[j]) {
+                        f(i, j);
+                        pairs += 1;
+                    }
+                }
+            }
+        }
+        pairs
+    }
+}
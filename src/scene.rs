@@ -0,0 +1,214 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use crate::camera::MainCamera;
+use crate::dots::{respawn_from, Age, Dot, DotAssets, DotScale, NewDot, Velocity};
+use crate::{CameraConfig, SimuConf};
+
+const SCENE_FILE_PATH: &str = "dot-network-scene.ron";
+const SCENE_FILE_VERSION: u32 = 3;
+
+// A whole simulation saved to disk. Every field has a default value so files
+// written by older versions, with fewer fields, can still be loaded.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct SceneFile {
+    version: u32,
+    simu_conf: SimuConf,
+    camera: CameraConfig,
+    dots: Vec<SceneDot>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct SceneDot {
+    position: Vec2,
+    velocity: Vec2,
+    // Since v2, for the age colors
+    age: f32,
+    // Since v3, the dots of older files get `SimuConf.dot_size`
+    scale: f32,
+}
+
+impl Default for SceneDot {
+    fn default() -> Self {
+        SceneDot {
+            position: Vec2::ZERO,
+            velocity: Vec2::ZERO,
+            age: 0.,
+            scale: 1.,
+        }
+    }
+}
+
+impl SceneFile {
+    fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let content = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        // The data directory of the autosave may not exist yet
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let scene: SceneFile = ron::from_str(&std::fs::read_to_string(path)?)?;
+        if scene.version > SCENE_FILE_VERSION {
+            warn!(
+                "{} was written by a newer version (v{}), unknown fields are ignored",
+                path.display(),
+                scene.version
+            );
+        }
+        Ok(scene)
+    }
+}
+
+// Where the simulation is saved on exit and restored from on the next launch.
+// Nothing is saved when it isn't inserted, e.g. when the dots are the background
+// of another app.
+#[derive(Resource)]
+pub struct Autosave {
+    pub path: PathBuf,
+    // Start with the saved dots, settings and camera
+    pub restore: bool,
+}
+
+fn capture_scene(
+    dots: &Query<(&Transform, &Velocity, &Age, &DotScale), With<Dot>>,
+    camera: &Query<(&Transform, &OrthographicProjection), With<MainCamera>>,
+    simu_conf: &SimuConf,
+) -> SceneFile {
+    let (camera_transform, projection) = camera.single();
+    SceneFile {
+        version: SCENE_FILE_VERSION,
+        simu_conf: simu_conf.clone(),
+        camera: CameraConfig {
+            position: Vec2::new(
+                camera_transform.translation.x,
+                camera_transform.translation.y,
+            ),
+            zoom: 1. / projection.scale,
+        },
+        dots: dots
+            .iter()
+            .map(|(transform, velocity, age, scale)| SceneDot {
+                position: Vec2::new(transform.translation.x, transform.translation.y),
+                velocity: velocity.0,
+                age: age.0,
+                scale: **scale,
+            })
+            .collect(),
+    }
+}
+
+fn restore_scene(
+    scene: SceneFile,
+    commands: &mut Commands,
+    dots: &Query<Entity, With<Dot>>,
+    camera: &mut Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+    dot_assets: &DotAssets,
+    simu_conf: &mut SimuConf,
+) {
+    // A different dot size is applied by `resize_dots`
+    *simu_conf = scene.simu_conf;
+
+    let (mut camera_transform, mut projection) = camera.single_mut();
+    camera_transform.translation.x = scene.camera.position.x;
+    camera_transform.translation.y = scene.camera.position.y;
+    projection.scale = 1. / scene.camera.zoom;
+
+    let new_dots = scene.dots.into_iter().map(|dot| NewDot {
+        position: dot.position,
+        velocity: dot.velocity,
+        age: dot.age,
+        scale: dot.scale,
+    });
+    respawn_from(commands, dot_assets, simu_conf, dots, new_dots);
+}
+
+pub(crate) fn save_scene(
+    dots: Query<(&Transform, &Velocity, &Age, &DotScale), With<Dot>>,
+    camera: Query<(&Transform, &OrthographicProjection), With<MainCamera>>,
+    simu_conf: Res<SimuConf>,
+) {
+    let path = Path::new(SCENE_FILE_PATH);
+    match capture_scene(&dots, &camera, &simu_conf).save(path) {
+        Ok(()) => info!("Scene saved to {SCENE_FILE_PATH}"),
+        Err(err) => error!("Couldn't save the scene to {SCENE_FILE_PATH}: {err}"),
+    }
+}
+
+pub(crate) fn load_scene(
+    mut commands: Commands,
+    dots: Query<Entity, With<Dot>>,
+    mut camera: Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+    dot_assets: Res<DotAssets>,
+    mut simu_conf: ResMut<SimuConf>,
+) {
+    let scene = match SceneFile::load(Path::new(SCENE_FILE_PATH)) {
+        Ok(scene) => scene,
+        Err(err) => {
+            error!("Couldn't load the scene from {SCENE_FILE_PATH}: {err}");
+            return;
+        }
+    };
+    restore_scene(
+        scene,
+        &mut commands,
+        &dots,
+        &mut camera,
+        &dot_assets,
+        &mut simu_conf,
+    );
+    info!("Scene loaded from {SCENE_FILE_PATH}");
+}
+
+// Runs in `Last` on the frame `AppExit` is sent, before the app stops
+pub(crate) fn save_autosave(
+    autosave: Res<Autosave>,
+    dots: Query<(&Transform, &Velocity, &Age, &DotScale), With<Dot>>,
+    camera: Query<(&Transform, &OrthographicProjection), With<MainCamera>>,
+    simu_conf: Res<SimuConf>,
+) {
+    let path = &autosave.path;
+    match capture_scene(&dots, &camera, &simu_conf).save(path) {
+        Ok(()) => info!("Simulation saved to {}", path.display()),
+        Err(err) => error!("Couldn't save the simulation to {}: {err}", path.display()),
+    }
+}
+
+// On startup, the dots then replace the `SimuConf::number_of_dots` random ones
+pub(crate) fn restore_autosave(
+    autosave: Res<Autosave>,
+    mut commands: Commands,
+    dots: Query<Entity, With<Dot>>,
+    mut camera: Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+    dot_assets: Res<DotAssets>,
+    mut simu_conf: ResMut<SimuConf>,
+) {
+    let path = &autosave.path;
+    if !autosave.restore || !path.exists() {
+        return;
+    }
+    match SceneFile::load(path) {
+        Ok(scene) => {
+            restore_scene(
+                scene,
+                &mut commands,
+                &dots,
+                &mut camera,
+                &dot_assets,
+                &mut simu_conf,
+            );
+            info!("Simulation restored from {}", path.display());
+        }
+        Err(err) => error!(
+            "Couldn't restore the simulation from {}: {err}",
+            path.display()
+        ),
+    }
+}
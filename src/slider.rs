@@ -0,0 +1,92 @@
+use bevy::prelude::*;
+use bevy::ui::RelativeCursorPosition;
+
+const SLIDER_WIDTH: f32 = 160.; // In px
+const SLIDER_HEIGHT: f32 = 10.; // In px
+const SLIDER_TRACK_COLOR: Color = Color::rgba(1., 1., 1., 0.15);
+
+// Horizontal slider made of UI nodes, set by pressing or dragging the mouse on it
+#[derive(Component)]
+pub(crate) struct Slider {
+    pub(crate) min: f32,
+    pub(crate) max: f32,
+    // Can be outside of the range when set from elsewhere, the fill is clamped
+    pub(crate) value: f32,
+}
+
+impl Slider {
+    fn ratio(&self) -> f32 {
+        ((self.value - self.min) / (self.max - self.min)).clamp(0., 1.)
+    }
+}
+
+// Part of the track filled up to the value, tinted by the theme
+#[derive(Component)]
+pub(crate) struct SliderFill;
+
+// Spawns the track of `slider` in `parent` and returns it
+pub(crate) fn spawn_slider(parent: &mut ChildBuilder, slider: Slider, color: Color) -> Entity {
+    let ratio = slider.ratio();
+    parent
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Px(SLIDER_WIDTH),
+                    height: Val::Px(SLIDER_HEIGHT),
+                    ..default()
+                },
+                background_color: SLIDER_TRACK_COLOR.into(),
+                ..default()
+            },
+            slider,
+            Interaction::default(),
+            RelativeCursorPosition::default(),
+        ))
+        .with_children(|track| {
+            track.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(ratio * 100.),
+                        height: Val::Percent(100.),
+                        ..default()
+                    },
+                    background_color: color.into(),
+                    ..default()
+                },
+                SliderFill,
+            ));
+        })
+        .id()
+}
+
+// Moves the pressed sliders to the cursor. It keeps following the cursor outside of
+// the track until the button is released.
+pub(crate) fn drag_sliders(
+    mut sliders: Query<(&Interaction, &RelativeCursorPosition, &mut Slider)>,
+) {
+    for (interaction, cursor, mut slider) in &mut sliders {
+        let Some(cursor) = cursor.normalized else {
+            continue;
+        };
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let value = slider.min + cursor.x.clamp(0., 1.) * (slider.max - slider.min);
+        if slider.value != value {
+            slider.value = value;
+        }
+    }
+}
+
+pub(crate) fn update_slider_fills(
+    sliders: Query<(&Slider, &Children), Changed<Slider>>,
+    mut fills: Query<&mut Style, With<SliderFill>>,
+) {
+    for (slider, children) in &sliders {
+        for &child in children {
+            if let Ok(mut style) = fills.get_mut(child) {
+                style.width = Val::Percent(slider.ratio() * 100.);
+            }
+        }
+    }
+}
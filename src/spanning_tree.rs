@@ -0,0 +1,74 @@
+use bevy::prelude::*;
+
+use crate::delaunay::Delaunay;
+
+// The tree is only computed again after that long, in between the same dots stay
+// connected
+const SPANNING_TREE_INTERVAL: f32 = 0.2; // In s
+
+// Euclidean minimum spanning tree of the dots, the shortest set of connections
+// linking them all. It is part of the Delaunay triangulation, so only the edges of
+// the triangulation are sorted, then Kruskal's algorithm keeps the shortest ones
+// that don't close a loop. In 3D, the triangulation is the one of the plane, so the
+// tree is the shortest one among its edges.
+#[derive(Default)]
+pub(crate) struct SpanningTree {
+    delaunay: Delaunay,
+    weighted: Vec<(f32, [usize; 2])>,
+    // Union-find of the dots already linked together
+    parents: Vec<usize>,
+    edges: Vec<[usize; 2]>,
+    // Dots and time since the tree was computed
+    dot_count: usize,
+    age: f32,
+}
+
+impl SpanningTree {
+    // Edges of the tree, as pairs of indices in `positions`. It is computed again
+    // when it is too old or the number of dots changed, since the indices would no
+    // longer be the same dots.
+    pub(crate) fn update(
+        &mut self,
+        positions: &[Vec2],
+        delta: f32,
+        distance: impl Fn(usize, usize) -> f32,
+    ) -> &[[usize; 2]] {
+        self.age += delta;
+        if self.age < SPANNING_TREE_INTERVAL && self.dot_count == positions.len() {
+            return &self.edges;
+        }
+        self.age = 0.;
+        self.dot_count = positions.len();
+
+        self.weighted.clear();
+        self.weighted.extend(
+            self.delaunay
+                .triangulate(positions)
+                .iter()
+                .map(|&[i, j]| (distance(i, j), [i, j])),
+        );
+        self.weighted
+            .sort_unstable_by(|(a, _), (b, _)| a.total_cmp(b));
+
+        self.parents.clear();
+        self.parents.extend(0..positions.len());
+        self.edges.clear();
+        for &(_, [i, j]) in &self.weighted {
+            let (root_i, root_j) = (root(&mut self.parents, i), root(&mut self.parents, j));
+            if root_i != root_j {
+                self.parents[root_i] = root_j;
+                self.edges.push([i, j]);
+            }
+        }
+        &self.edges
+    }
+}
+
+// Halves the path to the root on the way, so the trees stay shallow
+fn root(parents: &mut [usize], mut dot: usize) -> usize {
+    while parents[dot] != dot {
+        parents[dot] = parents[parents[dot]];
+        dot = parents[dot];
+    }
+    dot
+}
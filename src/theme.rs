@@ -0,0 +1,138 @@
+use bevy::prelude::*;
+
+use crate::dots::DotAssets;
+use crate::hud::{HistogramBar, HudText, HISTOGRAM_BAR_ALPHA};
+use crate::slider::SliderFill;
+use crate::wallpaper::Wallpaper;
+use crate::ConfigFile;
+
+// Colors of the whole app. Connections go from `line_near`, for the closest dots,
+// to `line_far` as they get longer.
+pub(crate) struct Palette {
+    pub(crate) name: &'static str,
+    pub(crate) dot: Color,
+    pub(crate) line_near: Color,
+    pub(crate) line_far: Color,
+    pub(crate) background: Color,
+    // Second color of the gradient background
+    pub(crate) background_glow: Color,
+    pub(crate) hud: Color,
+    pub(crate) spark: Color,
+}
+
+pub(crate) const PALETTES: [Palette; 4] = [
+    Palette {
+        name: "Violet",
+        dot: Color::VIOLET,
+        line_near: Color::rgb(0.93, 0.51, 0.93),
+        line_far: Color::rgb(0.93, 0.51, 0.93),
+        // Bevy's default clear color
+        background: Color::rgb(0.169, 0.173, 0.184),
+        background_glow: Color::rgb(0.24, 0.17, 0.27),
+        hud: Color::ANTIQUE_WHITE,
+        spark: Color::rgb(1., 0.85, 0.95),
+    },
+    Palette {
+        name: "Ocean",
+        dot: Color::rgb(0.55, 0.9, 1.),
+        line_near: Color::rgb(0.6, 1., 0.95),
+        line_far: Color::rgb(0.15, 0.4, 0.9),
+        background: Color::rgb(0.02, 0.06, 0.12),
+        background_glow: Color::rgb(0.02, 0.16, 0.2),
+        hud: Color::rgb(0.75, 0.9, 1.),
+        spark: Color::rgb(0.85, 1., 1.),
+    },
+    Palette {
+        name: "Ember",
+        dot: Color::rgb(1., 0.75, 0.3),
+        line_near: Color::rgb(1., 0.9, 0.5),
+        line_far: Color::rgb(0.8, 0.15, 0.1),
+        background: Color::rgb(0.08, 0.03, 0.02),
+        background_glow: Color::rgb(0.2, 0.06, 0.03),
+        hud: Color::rgb(1., 0.85, 0.7),
+        spark: Color::rgb(1., 0.95, 0.6),
+    },
+    Palette {
+        name: "Paper",
+        dot: Color::rgb(0.15, 0.15, 0.2),
+        line_near: Color::rgb(0.2, 0.2, 0.3),
+        line_far: Color::rgb(0.55, 0.55, 0.65),
+        background: Color::rgb(0.95, 0.94, 0.9),
+        background_glow: Color::rgb(0.85, 0.88, 0.95),
+        hud: Color::rgb(0.2, 0.2, 0.25),
+        spark: Color::rgb(0.4, 0.3, 0.3),
+    },
+];
+
+// Current palette, cycled at runtime
+#[derive(Resource, PartialEq)]
+pub(crate) struct Theme {
+    index: usize,
+}
+
+// Starts with the theme named in the config file, if any
+impl FromWorld for Theme {
+    fn from_world(world: &mut World) -> Self {
+        let name = world
+            .get_resource::<ConfigFile>()
+            .and_then(|file| file.config.theme.clone());
+        name.map_or(Theme { index: 0 }, |name| {
+            Theme::named(&name).unwrap_or_else(|| {
+                warn!("Unknown theme {name}, using {}", PALETTES[0].name);
+                Theme { index: 0 }
+            })
+        })
+    }
+}
+
+impl Theme {
+    // The palette with this name, whatever the case
+    pub(crate) fn named(name: &str) -> Option<Theme> {
+        PALETTES
+            .iter()
+            .position(|palette| palette.name.eq_ignore_ascii_case(name))
+            .map(|index| Theme { index })
+    }
+
+    pub(crate) fn palette(&self) -> &'static Palette {
+        &PALETTES[self.index]
+    }
+
+    pub(crate) fn next(&mut self) {
+        self.index = (self.index + 1) % PALETTES.len();
+    }
+}
+
+pub(crate) fn cycle_theme(mut theme: ResMut<Theme>) {
+    theme.next();
+}
+
+// Repaints everything that isn't redrawn every frame. The dots pick the new default
+// material in `recolor_dots`, the connections and sparks read the theme when drawn.
+#[allow(clippy::type_complexity)]
+pub(crate) fn apply_theme(
+    theme: Res<Theme>,
+    mut clear_color: ResMut<ClearColor>,
+    wallpaper: Option<Res<Wallpaper>>,
+    mut dot_assets: ResMut<DotAssets>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut texts: Query<&mut Text, With<HudText>>,
+    mut bars: Query<&mut BackgroundColor, Or<(With<HistogramBar>, With<SliderFill>)>>,
+) {
+    let palette = theme.palette();
+    clear_color.0 = if wallpaper.is_some() {
+        Color::NONE
+    } else {
+        palette.background
+    };
+    dot_assets.set_color(&mut materials, palette.dot);
+    dot_assets.set_player_color(&mut materials, palette.spark);
+    for mut text in &mut texts {
+        for section in &mut text.sections {
+            section.style.color = palette.hud;
+        }
+    }
+    for mut bar in &mut bars {
+        bar.0 = palette.line_near.with_a(HISTOGRAM_BAR_ALPHA);
+    }
+}
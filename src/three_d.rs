@@ -0,0 +1,253 @@
+use bevy::core_pipeline::tonemapping::Tonemapping;
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::broadphase::NeighbourSearch;
+use crate::camera::{LinesCamera, MainCamera};
+use crate::clusters::Clusters;
+use crate::connections::{connection_color, Connections};
+use crate::dots::{Dot, DotRng, Pinned};
+use crate::inspector::HoveredDot;
+use crate::physics::WorldBounds;
+use crate::player::Player;
+use crate::theme::Theme;
+use crate::SimuConf;
+
+// Distance of the orbiting camera to the center of the world, relative to the
+// size of the world
+const ORBIT_DISTANCE: f32 = 1.2;
+// Height of the camera above the plane of the world, relative to its distance
+const ORBIT_ELEVATION: f32 = 0.3;
+// Above the 2D cameras, so it draws the HUD
+const ORBIT_CAMERA_ORDER: isize = 2;
+const DOT_SEGMENTS: usize = 12;
+// Dots at the back are that much smaller than at the front with a full depth fade,
+// on top of the perspective
+const DEPTH_FADE_SHRINK: f32 = 0.5;
+
+// Position of a dot across the plane of the world, only used by the 3D mode. The z
+// of its transform is kept for the draw order of the 2D view. Every dot gets one
+// when it's spawned, so the mode can be switched on at any time.
+#[derive(Component)]
+pub(crate) struct Depth {
+    pub(crate) z: f32,
+    velocity: f32,
+}
+
+// Camera turning around the dots in the 3D mode, the 2D cameras are switched off
+// while it's there
+#[derive(Component, Default)]
+pub(crate) struct OrbitCamera {
+    angle: f32,
+}
+
+// Depth of a dot in the current mode, 0 in 2D so the distances stay on the plane
+pub(crate) fn depth_of(depth: Option<&Depth>, simu_conf: &SimuConf) -> f32 {
+    match depth {
+        Some(depth) if simu_conf.three_d => depth.z,
+        _ => 0.,
+    }
+}
+
+// Half the depth of the box, a negative depth from a scene or a preset is flat
+fn half_depth(simu_conf: &SimuConf) -> f32 {
+    simu_conf.depth.max(0.) / 2.
+}
+
+// Anywhere in the depth of the box, moving like along the other axes
+pub(crate) fn add_depth(
+    new_dots: Query<Entity, Added<Dot>>,
+    mut commands: Commands,
+    simu_conf: Res<SimuConf>,
+    mut rng: ResMut<DotRng>,
+) {
+    let half_depth = half_depth(&simu_conf);
+    for dot in &new_dots {
+        // `gen_range` panics on an empty range
+        let velocity = if simu_conf.min_vel < simu_conf.max_vel {
+            rng.gen_range(simu_conf.min_vel..simu_conf.max_vel)
+        } else {
+            simu_conf.min_vel
+        };
+        commands.entity(dot).insert(Depth {
+            z: rng.gen_range(-half_depth..=half_depth),
+            velocity,
+        });
+    }
+}
+
+// The dots bounce on the front and back of the box like on the walls. The pinned
+// dots and the player stay at their depth.
+pub(crate) fn move_depth(
+    mut dots: Query<&mut Depth, (Without<Pinned>, Without<Player>)>,
+    simu_conf: Res<SimuConf>,
+    time: Res<Time>,
+) {
+    if simu_conf.freeze_dots {
+        return;
+    }
+    let half_depth = half_depth(&simu_conf);
+    let delta = simu_conf.speed * time.delta_seconds();
+    for mut depth in &mut dots {
+        depth.z += depth.velocity * delta;
+        if (depth.z > half_depth && depth.velocity > 0.)
+            || (depth.z < -half_depth && depth.velocity < 0.)
+        {
+            depth.velocity *= -simu_conf.restitution;
+        }
+        depth.z = depth.z.clamp(-half_depth, half_depth);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub(crate) fn switch_cameras(
+    mut commands: Commands,
+    simu_conf: Res<SimuConf>,
+    orbit_camera: Query<Entity, With<OrbitCamera>>,
+    mut cameras: Query<&mut Camera, Or<(With<MainCamera>, With<LinesCamera>)>>,
+) {
+    for mut camera in &mut cameras {
+        if camera.is_active == simu_conf.three_d {
+            camera.is_active = !simu_conf.three_d;
+        }
+    }
+    match (orbit_camera.get_single(), simu_conf.three_d) {
+        (Ok(camera), false) => commands.entity(camera).despawn(),
+        (Err(_), true) => {
+            commands.spawn((
+                Camera3dBundle {
+                    camera: Camera {
+                        order: ORBIT_CAMERA_ORDER,
+                        ..default()
+                    },
+                    // Like the 2D cameras, the colors of the theme are kept
+                    tonemapping: Tonemapping::None,
+                    ..default()
+                },
+                OrbitCamera::default(),
+            ));
+        }
+        _ => {}
+    }
+}
+
+// Dimming of the dots and the connections with their distance to the camera, so
+// the far side of the box recedes into the background
+struct DepthFade {
+    eye: Vec3,
+    near: f32,
+    far: f32,
+    strength: f32,
+}
+
+impl DepthFade {
+    // The nearest and farthest points of the box are at most half its diagonal
+    // closer or farther than its center
+    fn new(eye: Vec3, bounds: Rect, simu_conf: &SimuConf) -> Self {
+        let center = bounds.center().extend(0.);
+        let half_diagonal = bounds.size().extend(simu_conf.depth).length() / 2.;
+        let distance = eye.distance(center);
+        DepthFade {
+            eye,
+            near: distance - half_diagonal,
+            far: distance + half_diagonal,
+            strength: simu_conf.depth_fade.clamp(0., 1.),
+        }
+    }
+
+    // 1 at the front of the box, down to `1 - strength` at the back
+    fn at(&self, position: Vec3) -> f32 {
+        let back = ((self.eye.distance(position) - self.near) / (self.far - self.near).max(1.))
+            .clamp(0., 1.);
+        1. - self.strength * back
+    }
+}
+
+fn faded(color: Color, fade: f32) -> Color {
+    color.with_a(color.a() * fade)
+}
+
+// Starts in front of the world, like the 2D view, and slowly turns around its
+// vertical axis
+pub(crate) fn orbit_camera(
+    mut camera: Query<(&mut Transform, &mut OrbitCamera)>,
+    bounds: Res<WorldBounds>,
+    simu_conf: Res<SimuConf>,
+    time: Res<Time>,
+) {
+    let Ok((mut transform, mut orbit)) = camera.get_single_mut() else {
+        return;
+    };
+    orbit.angle += simu_conf.orbit_speed * time.delta_seconds();
+    let center = bounds.center().extend(0.);
+    let distance = bounds.size().max_element().max(simu_conf.depth) * ORBIT_DISTANCE;
+    let direction = Vec3::new(orbit.angle.sin(), ORBIT_ELEVATION, orbit.angle.cos());
+    *transform = Transform::from_translation(center + direction.normalize() * distance)
+        .looking_at(center, Vec3::Y);
+}
+
+// The 2D meshes aren't seen by the 3D camera, the dots are drawn as discs facing it
+// in the color of their material
+pub(crate) fn draw_dots_3d(
+    dots: Query<(&Transform, &Depth, &Handle<ColorMaterial>), With<Dot>>,
+    materials: Res<Assets<ColorMaterial>>,
+    camera: Query<&GlobalTransform, With<OrbitCamera>>,
+    bounds: Res<WorldBounds>,
+    simu_conf: Res<SimuConf>,
+    mut gizmos: Gizmos,
+) {
+    let Ok(camera) = camera.get_single() else {
+        return;
+    };
+    let eye = camera.translation();
+    let depth_fade = DepthFade::new(eye, **bounds, &simu_conf);
+    for (transform, depth, material) in &dots {
+        let position = transform.translation.truncate().extend(depth.z);
+        let color = materials
+            .get(material)
+            .map_or(Color::WHITE, |material| material.color);
+        let facing = Direction3d::new(eye - position).unwrap_or(Direction3d::Z);
+        let fade = depth_fade.at(position);
+        let shrink = 1. - (1. - fade) * DEPTH_FADE_SHRINK;
+        gizmos
+            .circle(
+                position,
+                facing,
+                simu_conf.dot_size * transform.scale.x * shrink,
+                faded(color, fade),
+            )
+            .segments(DOT_SEGMENTS);
+    }
+}
+
+// Every end of a connection fades with its own depth
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn draw_connections_3d(
+    connections: Res<Connections>,
+    search: Res<NeighbourSearch>,
+    clusters: Res<Clusters>,
+    hovered: Res<HoveredDot>,
+    theme: Res<Theme>,
+    simu_conf: Res<SimuConf>,
+    camera: Query<&GlobalTransform, With<OrbitCamera>>,
+    bounds: Res<WorldBounds>,
+    mut gizmos: Gizmos,
+) {
+    let Ok(camera) = camera.get_single() else {
+        return;
+    };
+    let depth_fade = DepthFade::new(camera.translation(), **bounds, &simu_conf);
+    let depths = search.depths();
+    for connection in connections.iter() {
+        let [i, j] = connection.dots;
+        let from = connection.from.extend(depths[i]);
+        let to = connection.to.extend(depths[j]);
+        let color = connection_color(connection, &clusters, &hovered, &simu_conf, &theme);
+        gizmos.line_gradient(
+            from,
+            to,
+            faded(color, depth_fade.at(from)),
+            faded(color, depth_fade.at(to)),
+        );
+    }
+}
@@ -0,0 +1,159 @@
+use bevy::prelude::*;
+
+use crate::hud::{hud_text, HudRoot, HISTOGRAM_BAR_ALPHA};
+use crate::keys::KeyBindings;
+use crate::theme::Theme;
+
+const TOOLBAR_PADDING: Val = Val::Px(6.0);
+const TOOLBAR_BACKGROUND: Color = Color::rgba(0., 0., 0., 0.5);
+
+// What the left mouse button does. Picked with Shift + its number or on the
+// toolbar, the other buttons and the keys do the same with every tool.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub(crate) enum Tool {
+    // Places dots with the brush, or splits the large dots
+    #[default]
+    Spawn,
+    Erase,
+    // Drags and selects the dots
+    Grab,
+    Pin,
+    // Pulls the dots toward the cursor, like the attract key
+    Attract,
+    PlaceEmitter,
+}
+
+impl Tool {
+    // In the order of the toolbar and of the number keys
+    const ALL: [Tool; 6] = [
+        Tool::Spawn,
+        Tool::Erase,
+        Tool::Grab,
+        Tool::Pin,
+        Tool::Attract,
+        Tool::PlaceEmitter,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Tool::Spawn => "Spawn",
+            Tool::Erase => "Erase",
+            Tool::Grab => "Grab",
+            Tool::Pin => "Pin",
+            Tool::Attract => "Attract",
+            Tool::PlaceEmitter => "Emitter",
+        }
+    }
+}
+
+// Run condition, true while `tool` is the current one
+pub(crate) fn tool_is(tool: Tool) -> impl FnMut(Res<Tool>) -> bool + Clone {
+    move |current| *current == tool
+}
+
+// Row of buttons at the top of the screen, one per tool
+#[derive(Component)]
+pub(crate) struct Toolbar;
+
+#[derive(Component)]
+pub(crate) struct ToolButton(Tool);
+
+pub(crate) fn spawn_toolbar(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    root: Query<Entity, With<HudRoot>>,
+) {
+    // Full width to center the toolbar, it lets the clicks through
+    let toolbar = commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: TOOLBAR_PADDING,
+                width: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|row| {
+            row.spawn((
+                NodeBundle {
+                    style: Style {
+                        padding: UiRect::all(TOOLBAR_PADDING),
+                        column_gap: TOOLBAR_PADDING,
+                        ..default()
+                    },
+                    background_color: TOOLBAR_BACKGROUND.into(),
+                    ..default()
+                },
+                // Tells when the pointer is over the toolbar
+                Interaction::default(),
+                Toolbar,
+            ))
+            .with_children(|bar| {
+                for (i, tool) in Tool::ALL.into_iter().enumerate() {
+                    bar.spawn((
+                        ButtonBundle {
+                            style: Style {
+                                padding: UiRect::horizontal(TOOLBAR_PADDING),
+                                ..default()
+                            },
+                            background_color: Color::NONE.into(),
+                            ..default()
+                        },
+                        ToolButton(tool),
+                    ))
+                    .with_children(|button| {
+                        button.spawn(hud_text(&format!("{} {}", i + 1, tool.name()), &theme));
+                    });
+                }
+            });
+        })
+        .id();
+    commands.entity(root.single()).add_child(toolbar);
+}
+
+// Shift + the number of a tool, or a click on its button, makes it the current one
+pub(crate) fn select_tool(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    buttons: Query<(&Interaction, &ToolButton), Changed<Interaction>>,
+    mut tool: ResMut<Tool>,
+) {
+    let mut selected = None;
+    if keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight])
+        && !keyboard_input.any_pressed([KeyCode::AltLeft, KeyCode::AltRight])
+    {
+        selected = bindings
+            .slots()
+            .iter()
+            .zip(Tool::ALL)
+            .find(|(key, _)| keyboard_input.just_pressed(**key))
+            .map(|(_, tool)| tool);
+    }
+    for (interaction, button) in &buttons {
+        if *interaction == Interaction::Pressed {
+            selected = Some(button.0);
+        }
+    }
+    if let Some(selected) = selected {
+        if *tool != selected {
+            *tool = selected;
+        }
+    }
+}
+
+// The button of the current tool is filled with the color of the connections
+pub(crate) fn update_toolbar(
+    tool: Res<Tool>,
+    theme: Res<Theme>,
+    mut buttons: Query<(&ToolButton, &mut BackgroundColor)>,
+) {
+    for (button, mut color) in &mut buttons {
+        color.0 = if button.0 == *tool {
+            theme.palette().line_near.with_a(HISTOGRAM_BAR_ALPHA)
+        } else {
+            Color::NONE
+        };
+    }
+}
@@ -0,0 +1,110 @@
+use bevy::input::touch::{ForceTouch, Touch};
+use bevy::prelude::*;
+
+use crate::actions::SimAction;
+use crate::camera::MainCamera;
+
+// Change of the connect force per px the two fingers move apart
+const PINCH_FORCE_STEP: f32 = 1.;
+// Two fingers lifted sooner than that, without a pinch, are a tap
+const TAP_DURATION: f32 = 0.3; // In s
+
+// Change of the distance between the fingers before it's a pinch
+const PINCH_THRESHOLD: f32 = 20.; // In px
+
+// Dots spawned and their size relative to `SimuConf.dot_size`, with a stylus
+// pressed as hard as it can be
+const PEN_MAX_DOTS: f32 = 4.;
+const PEN_MAX_SCALE: f32 = 2.5;
+
+// Two fingers on the screen, either a pinch or a tap
+#[derive(Default)]
+pub(crate) struct TwoFingerGesture {
+    // Time and distance between the fingers when the second one touched, None
+    // outside of a gesture
+    start: Option<(f32, f32)>,
+    pinch: bool,
+}
+
+fn spread(first: &Touch, second: &Touch) -> f32 {
+    first.position().distance(second.position())
+}
+
+// From 0 to 1, None when the device doesn't report it (most fingers)
+fn pressure(touch: &Touch) -> Option<f32> {
+    let pressure = match touch.force()? {
+        ForceTouch::Normalized(force) => force,
+        ForceTouch::Calibrated {
+            force,
+            max_possible_force,
+            ..
+        } => force / max_possible_force,
+    };
+    Some((pressure as f32).clamp(0., 1.))
+}
+
+// Run on a timer, like the left mouse button. Only one finger spawns, two are a
+// gesture. A stylus pressed harder spawns more dots, and larger ones.
+pub(crate) fn map_touch_spawn(
+    touches: Res<Touches>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut actions: EventWriter<SimAction>,
+) {
+    let mut fingers = touches.iter();
+    let (Some(finger), None) = (fingers.next(), fingers.next()) else {
+        return;
+    };
+    let Ok((camera, camera_transform)) = camera_query.get_single() else {
+        return;
+    };
+    let Some(position) = camera.viewport_to_world_2d(camera_transform, finger.position()) else {
+        return;
+    };
+    match pressure(finger) {
+        Some(pressure) => {
+            let count = 1 + (pressure * (PEN_MAX_DOTS - 1.)).round() as u32;
+            let scale = 1. + pressure * (PEN_MAX_SCALE - 1.);
+            for _ in 0..count {
+                actions.send(SimAction::SpawnSizedDot { position, scale });
+            }
+        }
+        None => {
+            actions.send(SimAction::SpawnDot(position));
+        }
+    }
+}
+
+// Spreading two fingers apart increases the connect force, pinching them decreases
+// it, tapping with both deletes every dot
+pub(crate) fn map_touch_gestures(
+    touches: Res<Touches>,
+    time: Res<Time>,
+    mut gesture: Local<TwoFingerGesture>,
+    mut actions: EventWriter<SimAction>,
+) {
+    let fingers: Vec<&Touch> = touches.iter().collect();
+    if let [first, second] = fingers[..] {
+        let (_, start_spread) = *gesture
+            .start
+            .get_or_insert((time.elapsed_seconds(), spread(first, second)));
+        if (spread(first, second) - start_spread).abs() > PINCH_THRESHOLD {
+            gesture.pinch = true;
+        }
+        if gesture.pinch {
+            let previous_spread = first
+                .previous_position()
+                .distance(second.previous_position());
+            let change = spread(first, second) - previous_spread;
+            if change != 0. {
+                actions.send(SimAction::ChangeConnectForce(change * PINCH_FORCE_STEP));
+            }
+        }
+    } else if fingers.is_empty() {
+        if let Some((start_time, _)) = gesture.start {
+            if !gesture.pinch && time.elapsed_seconds() - start_time < TAP_DURATION {
+                actions.send(SimAction::Clear);
+            }
+            *gesture = TwoFingerGesture::default();
+        }
+    }
+}
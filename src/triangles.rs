@@ -0,0 +1,134 @@
+use bevy::prelude::*;
+use bevy::render::view::{NoFrustumCulling, RenderLayers};
+use bevy::sprite::{MaterialMesh2dBundle, Mesh2dHandle};
+
+use crate::color::lerp_color;
+use crate::connections::{
+    dynamic_mesh, map, put_mesh_buffers, take_mesh_buffers, Connections, LINES_LAYER,
+};
+use crate::theme::Theme;
+use crate::SimuConf;
+
+// Fills the triangles of three dots all connected to each other and closer than
+// `SimuConf.connect_force`, on a mesh below the lines like `ConnectionMesh`
+#[derive(Component)]
+pub(crate) struct TriangleMesh;
+
+// Buffers reused by `draw_triangles` from one frame to the next
+#[derive(Default)]
+pub(crate) struct TriangleBuffers {
+    // Connected dots of every dot with a higher index, sorted
+    neighbours: Vec<Vec<usize>>,
+    // Position of every connected dot
+    positions: Vec<Vec2>,
+}
+
+pub(crate) fn spawn_triangle_mesh(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: meshes.add(dynamic_mesh()).into(),
+            material: materials.add(Color::WHITE),
+            // Between the gradient background and the connections
+            transform: Transform::from_xyz(0., 0., 0.25),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        TriangleMesh,
+        RenderLayers::layer(LINES_LAYER),
+        NoFrustumCulling,
+    ));
+}
+
+// The triangles are found on the connections: for every connection, the dots
+// connected to both of its ends. Only the dots with a higher index are kept as
+// neighbours, so every triangle is found once, from its two lowest dots. The closer
+// the dots, the more opaque the triangle, up to `SimuConf.triangle_alpha`.
+pub(crate) fn draw_triangles(
+    connections: Res<Connections>,
+    theme: Res<Theme>,
+    simu_conf: Res<SimuConf>,
+    mut triangle_mesh: Query<(&Mesh2dHandle, &mut Visibility), With<TriangleMesh>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut buffers: Local<TriangleBuffers>,
+) {
+    let Ok((handle, mut visibility)) = triangle_mesh.get_single_mut() else {
+        return;
+    };
+    if !simu_conf.fill_triangles {
+        visibility.set_if_neq(Visibility::Hidden);
+        return;
+    }
+    let Some(mesh) = meshes.get_mut(&handle.0) else {
+        return;
+    };
+    let TriangleBuffers {
+        neighbours,
+        positions,
+    } = &mut *buffers;
+    for dots in neighbours.iter_mut() {
+        dots.clear();
+    }
+    let close = || {
+        connections
+            .iter()
+            .filter(|connection| connection.dist < simu_conf.connect_force)
+    };
+    let dot_count = close()
+        .map(|connection| connection.dots[0].max(connection.dots[1]) + 1)
+        .max()
+        .unwrap_or(0);
+    if neighbours.len() < dot_count {
+        neighbours.resize_with(dot_count, Vec::new);
+    }
+    positions.resize(dot_count.max(positions.len()), Vec2::ZERO);
+    for connection in close() {
+        let [i, j] = connection.dots;
+        let (low, high) = (i.min(j), i.max(j));
+        neighbours[low].push(high);
+        positions[i] = connection.from;
+        positions[j] = connection.to;
+    }
+    for dots in neighbours.iter_mut() {
+        dots.sort_unstable();
+    }
+
+    let (mut vertices, mut colors, mut indices) = take_mesh_buffers(mesh);
+    let palette = theme.palette();
+    for (i, higher) in neighbours.iter().enumerate() {
+        for (k, &j) in higher.iter().enumerate() {
+            // Both lists are sorted, the dots connected to i and j after j
+            for &third in &higher[k + 1..] {
+                if neighbours[j].binary_search(&third).is_err() {
+                    continue;
+                }
+                let corners = [positions[i], positions[j], positions[third]];
+                let average = (corners[0].distance(corners[1])
+                    + corners[1].distance(corners[2])
+                    + corners[2].distance(corners[0]))
+                    / 3.;
+                let ratio = (average / simu_conf.connect_force).clamp(0., 1.);
+                let alpha = map(ratio, 0., 1., simu_conf.triangle_alpha, 0.);
+                let color = lerp_color(palette.line_near, palette.line_far, ratio)
+                    .with_a(alpha)
+                    .as_linear_rgba_f32();
+                let first = vertices.len() as u32;
+                for corner in corners {
+                    vertices.push(corner.extend(0.).to_array());
+                    colors.push(color);
+                }
+                indices.extend([first, first + 1, first + 2]);
+            }
+        }
+    }
+    let shown = if indices.is_empty() {
+        Visibility::Hidden
+    } else {
+        Visibility::Inherited
+    };
+    visibility.set_if_neq(shown);
+    put_mesh_buffers(mesh, vertices, colors, indices);
+}
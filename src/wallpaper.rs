@@ -0,0 +1,35 @@
+use bevy::prelude::*;
+
+use crate::emitters::Emitter;
+use crate::hud::HudRoot;
+use crate::SimuConf;
+
+// Given to the dots when they would live forever, so the emitter doesn't fill the
+// desktop
+const WALLPAPER_DOT_LIFETIME: f32 = 30.; // In s
+
+// Runs the dots as a live wallpaper, in a transparent window behind the others that
+// lets the clicks through (see `WindowConfig.wallpaper`). The mouse can't place dots
+// there, an emitter in the middle of the world shoots them all around instead.
+#[derive(Resource)]
+pub struct Wallpaper;
+
+// The desktop shows through the background, and the HUD is hidden since it can't be
+// clicked
+pub(crate) fn start_wallpaper(
+    mut commands: Commands,
+    mut simu_conf: ResMut<SimuConf>,
+    mut root: Query<&mut Visibility, With<HudRoot>>,
+) {
+    simu_conf.gradient_background = false;
+    if simu_conf.dot_lifetime <= 0. {
+        simu_conf.dot_lifetime = WALLPAPER_DOT_LIFETIME;
+    }
+    commands.spawn((
+        Emitter::new(&simu_conf).all_around(),
+        SpatialBundle::from_transform(Transform::from_translation(
+            simu_conf.world_offset.extend(0.),
+        )),
+    ));
+    *root.single_mut() = Visibility::Hidden;
+}
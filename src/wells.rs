@@ -0,0 +1,147 @@
+use bevy::prelude::*;
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use crate::actions::SimAction;
+use crate::dots::{Dot, Mass, Velocity};
+use crate::theme::Theme;
+use crate::SimuConf;
+
+// A click closer than that to a well or a vortex removes it
+const WELL_PICK_RADIUS: f32 = 20.; // In px
+const WELL_MARKER_RADIUS: f32 = 8.; // In px
+
+// Keeps the pull finite on the well itself
+const WELL_SOFTENING: f32 = 20.; // In px
+const WELL_MAX_ACCELERATION: f32 = 4_000.; // In px/s²
+const VORTEX_MAX_ACCELERATION: f32 = 3_000.; // In px/s²
+
+// A gravity well placed with the middle mouse button, it pulls every dot until it's
+// clicked again. The wells aren't saved in the scene files.
+#[derive(Component)]
+pub(crate) struct Attractor;
+
+// Makes the dots swirl around it, placed and removed like the wells. With wells on
+// the same spot, the dots orbit them like the stars of a galaxy.
+#[derive(Component)]
+pub(crate) struct Vortex {
+    pub(crate) clockwise: bool,
+}
+
+// Places a well or a vortex at the position of the action, or removes the one there,
+// whatever its kind
+#[allow(clippy::type_complexity)]
+pub(crate) fn toggle_wells(
+    mut actions: EventReader<SimAction>,
+    mut commands: Commands,
+    fields: Query<(Entity, &Transform), Or<(With<Attractor>, With<Vortex>)>>,
+) {
+    for action in actions.read() {
+        let (position, vortex) = match *action {
+            SimAction::ToggleWell(position) => (position, None),
+            SimAction::ToggleVortex {
+                position,
+                clockwise,
+            } => (position, Some(Vortex { clockwise })),
+            _ => continue,
+        };
+        let clicked = fields.iter().find(|(_, transform)| {
+            transform.translation.truncate().distance(position) < WELL_PICK_RADIUS
+        });
+        if let Some((field, _)) = clicked {
+            commands.entity(field).despawn();
+            continue;
+        }
+        let spatial =
+            SpatialBundle::from_transform(Transform::from_translation(position.extend(0.)));
+        match vortex {
+            Some(vortex) => commands.spawn((vortex, spatial)),
+            None => commands.spawn((Attractor, spatial)),
+        };
+    }
+}
+
+// Inverse-square pull of every well, softened and capped so the dots passing
+// through a well don't explode. Like gravity, it doesn't depend on the mass.
+pub(crate) fn apply_wells(
+    wells: Query<&Transform, With<Attractor>>,
+    mut dots: Query<(&Transform, &mut Velocity), With<Dot>>,
+    time: Res<Time>,
+    simu_conf: Res<SimuConf>,
+) {
+    if simu_conf.freeze_dots {
+        return;
+    }
+    let dt = simu_conf.speed * time.delta_seconds();
+
+    for well in &wells {
+        let well = well.translation.truncate();
+        for (transform, mut velocity) in &mut dots {
+            let delta = well - transform.translation.truncate();
+            let dist = delta.length();
+            if dist == 0. {
+                continue;
+            }
+            let acceleration = (simu_conf.well_strength
+                / (dist * dist + WELL_SOFTENING * WELL_SOFTENING))
+                .min(WELL_MAX_ACCELERATION);
+            velocity.0 += delta / dist * acceleration * dt;
+        }
+    }
+}
+
+// Force perpendicular to the direction of the vortex, decreasing with the distance
+// (1 / r), so the far dots orbit as fast as the close ones. The heavy dots are
+// dragged less.
+pub(crate) fn apply_vortices(
+    vortices: Query<(&Transform, &Vortex)>,
+    mut dots: Query<(&Transform, &mut Velocity, &Mass), With<Dot>>,
+    time: Res<Time>,
+    simu_conf: Res<SimuConf>,
+) {
+    if simu_conf.freeze_dots {
+        return;
+    }
+    let dt = simu_conf.speed * time.delta_seconds();
+
+    for (vortex_transform, vortex) in &vortices {
+        let center = vortex_transform.translation.truncate();
+        let sign = if vortex.clockwise { -1. } else { 1. };
+        for (transform, mut velocity, mass) in &mut dots {
+            let delta = transform.translation.truncate() - center;
+            let dist = delta.length();
+            if dist == 0. {
+                continue;
+            }
+            let acceleration =
+                (simu_conf.vortex_strength / (dist + WELL_SOFTENING)).min(VORTEX_MAX_ACCELERATION);
+            velocity.0 += delta.perp() / dist * acceleration / **mass * sign * dt;
+        }
+    }
+}
+
+// In the color of the sparks so they stand out from the dots: a dot inside a ring
+// for the wells, two arcs for the vortices
+pub(crate) fn draw_wells(
+    wells: Query<&Transform, With<Attractor>>,
+    vortices: Query<&Transform, With<Vortex>>,
+    theme: Res<Theme>,
+    mut gizmos: Gizmos,
+) {
+    let color = theme.palette().spark;
+    for well in &wells {
+        let position = well.translation.truncate();
+        gizmos.circle_2d(position, WELL_MARKER_RADIUS, color);
+        gizmos.circle_2d(position, WELL_MARKER_RADIUS * 0.3, color);
+    }
+    for vortex in &vortices {
+        let position = vortex.translation.truncate();
+        gizmos.arc_2d(position, 0., FRAC_PI_2 * 3., WELL_MARKER_RADIUS, color);
+        gizmos.arc_2d(
+            position,
+            PI,
+            FRAC_PI_2 * 3.,
+            WELL_MARKER_RADIUS * 0.5,
+            color,
+        );
+    }
+}